@@ -1,44 +1,375 @@
-use clap::Parser as ClapParser;
+use clap::{Parser as ClapParser, Subcommand};
 use std::fs::OpenOptions;
 use std::io::{BufWriter, Write};
-use std::path::PathBuf;
-use strace_parser::Parser;
+use std::path::{Path, PathBuf};
+use strace_parser::{
+    AccessPatterns, BinaryWriter, Config, DependencyGraph, ExpectedState, Filter, Format,
+    Lifetimes, Pacing, PathRules, Payload, Preset, Process, Script, StatsReport, Workload,
+};
 
 /// A library for parsing the strace output log
 #[derive(ClapParser, Debug)]
 #[clap(author, version, about, long_about = None)]
 struct Args {
-    /// The path to the logged traces
+    /// The path to the logged traces. Required unless the configuration file lists the traces
     #[clap(short, long)]
-    path: PathBuf,
+    path: Option<PathBuf>,
 
-    /// The output file path
+    /// A TOML or YAML configuration file of the traces and options. The options given on the
+    /// command line override the ones in the file
     #[clap(short, long)]
-    out: PathBuf,
+    config: Option<PathBuf>,
+
+    /// The output file path. The output is written to the standard output if not given
+    #[clap(short, long)]
+    out: Option<PathBuf>,
+
+    /// Read the traces as the ordered operations strace-parser wrote, in the text, jsonl, or
+    /// binary format, rather than as strace logs
+    #[clap(long)]
+    archived: bool,
+
+    /// The format of the ordered operations and of the graph: text, jsonl, csv, or binary, for the
+    /// operations only, to read them back with --archived. The default is text
+    #[clap(long)]
+    format: Option<Format>,
+
+    /// Keep only the operations the filter selects, like "op = write and path ~ /tmp/**"
+    #[clap(long)]
+    filter: Option<Filter>,
+
+    /// Keep only the operations of a system call, like openat or write
+    #[clap(long)]
+    syscall: Vec<String>,
+
+    /// Drop the operations on the paths of a built-in set: system, the shared libraries and other
+    /// system files, or pseudo, /proc, /sys, and /dev
+    #[clap(long)]
+    preset: Vec<Preset>,
+
+    /// Drop the operations on the paths under a prefix
+    #[clap(long)]
+    exclude: Vec<String>,
+
+    /// Keep the operations on the paths under a prefix, even if a shorter prefix is excluded.
+    /// If given, the paths under no included prefix are dropped
+    #[clap(long)]
+    include: Vec<String>,
+
+    /// Replace a path prefix while parsing, as FROM=TO, like /home/user/project=$ROOT
+    #[clap(long)]
+    rewrite: Vec<String>,
+
+    #[clap(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Replay the parsed operations under a root directory, and report the latency and errors of
+    /// each operation
+    Replay {
+        /// The directory the traced paths are replayed under
+        #[clap(short, long)]
+        root: Option<PathBuf>,
+
+        /// Replace a path prefix before replaying the paths under root, as FROM=TO
+        #[clap(long)]
+        remap: Vec<String>,
+
+        /// How long to wait between the operations of a process: afap, original, or a speed
+        /// multiplier of the original pace, like 2 or 0.5. The default is afap
+        #[clap(long)]
+        pacing: Option<Pacing>,
+
+        /// The bytes written by the write operations: zeros, repeat, random[:SEED],
+        /// compressible:RATIO[:SEED], dedup:BLOCKS[:SEED], or captured[:FILLER]. The default is
+        /// repeat
+        #[clap(long)]
+        payload: Option<Payload>,
+
+        /// Compare the files and directories under root with the ones expected from the trace
+        /// after the replay, and report the mismatches
+        #[clap(long)]
+        verify: bool,
+    },
+
+    /// Export the parsed operations as a standalone program replaying them under the root
+    /// directory given as its first argument
+    Export {
+        /// The language of the program: sh, for a POSIX shell script, or c, for a C source file.
+        /// The default is sh
+        #[clap(long)]
+        script: Option<Script>,
+    },
+
+    /// Export the reads and writes of each process as an equivalent synthetic benchmark
+    Workload {
+        /// The benchmark: fio, for a fio job file, or filebench, for a filebench workload model.
+        /// The default is fio
+        #[clap(long)]
+        benchmark: Option<Workload>,
+
+        /// The directory the benchmark creates its files in. The default is /tmp/strace-parser
+        #[clap(short, long)]
+        directory: Option<String>,
+    },
+
+    /// Report the statistics of the operations of each process, and of all the processes
+    Stats,
+
+    /// Report how each file is read and written: sequentially, randomly, with a stride, appended,
+    /// or overwritten, whether it is read once, and the reuse distances of its blocks
+    Patterns,
+
+    /// Report the critical path through the dependency graph, the width of each of its levels,
+    /// and the maximum speedup of running the processes in parallel
+    Parallelism,
+
+    /// Write the dependency graph of the processes rather than their ordered operations, in the
+    /// Graphviz dot language with the text format
+    Graph,
+
+    /// Report how long each file lives, the temporary files created and removed within the
+    /// trace, and the peak working set, which is the most bytes live at the same time
+    Lifetimes,
 }
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let args = Args::parse();
 
-    let mut parser = Parser::new(args.path);
-    let mut dep_graph = parser.parse()?;
+    let mut config = match &args.config {
+        Some(path) => Config::from_file(path)?,
+        None => Config::default(),
+    };
 
-    let out_file = OpenOptions::new()
-        .create(true)
-        .write(true)
-        .append(false)
-        .open(args.out)?;
-    let mut writer = BufWriter::new(out_file);
+    // the command line options override the configuration
+    if let Some(path) = args.path {
+        config.traces = vec![path];
+    }
+    if args.out.is_some() {
+        config.out = args.out;
+    }
+    config.archived |= args.archived;
+    if args.format.is_some() {
+        config.format = args.format;
+    }
+    if args.filter.is_some() {
+        config.filter = args.filter;
+    }
+    if !args.syscall.is_empty() {
+        config.syscalls = Some(args.syscall);
+    }
+    config.paths.presets.extend(args.preset);
+    config.paths.exclude.extend(args.exclude);
+    config.paths.include.extend(args.include);
+    for rewrite in args.rewrite.iter() {
+        let (from, to) = pair(rewrite, "rewrite")?;
+        config.paths.rewrite.insert(from, to);
+    }
 
-    let mut available_set = dep_graph.available_set()?;
-    while !available_set.is_empty() {
-        for process in available_set.iter() {
-            writer.write(process.to_string().as_ref())?;
+    match &args.command {
+        Some(Command::Replay {
+            root,
+            remap,
+            pacing,
+            payload,
+            verify,
+        }) => {
+            if let Some(root) = root {
+                config.replay.root = Some(root.clone());
+            }
+            for remap in remap.iter() {
+                let (from, to) = pair(remap, "remap")?;
+                config.replay.remap.insert(from, to);
+            }
+            if let Some(pacing) = pacing {
+                config.replay.pacing = Some(*pacing);
+            }
+            if let Some(payload) = payload {
+                config.replay.payload = Some(payload.clone());
+            }
+            config.replay.verify |= verify;
         }
+        Some(Command::Export {
+            script: Some(script),
+        }) => {
+            config.export.script = Some(*script);
+        }
+        Some(Command::Workload {
+            benchmark,
+            directory,
+        }) => {
+            if let Some(benchmark) = benchmark {
+                config.workload.benchmark = Some(*benchmark);
+            }
+            if let Some(directory) = directory {
+                config.workload.directory = Some(directory.clone());
+            }
+        }
+        _ => {}
+    }
 
-        available_set = dep_graph.available_set()?;
+    if config.traces.is_empty() {
+        return Err("no trace given, expected --path, or traces in the configuration".into());
+    }
+    if config.format == Some(Format::Binary) && args.command.is_none() && config.traces.len() > 1 {
+        return Err("the binary format holds a single trace, expected one trace".into());
+    }
+    // the system calls and the original paths are not in the archived operations
+    if config.archived && (config.syscalls.is_some() || config.paths.rules() != PathRules::new()) {
+        return Err(
+            "the system calls and path rules only apply to strace logs, not archived traces".into(),
+        );
+    }
+
+    let mut writer: BufWriter<Box<dyn Write>> = match &config.out {
+        Some(out) => BufWriter::new(Box::new(
+            OpenOptions::new()
+                .create(true)
+                .write(true)
+                .truncate(true)
+                .open(out)?,
+        )),
+        None => BufWriter::new(Box::new(std::io::stdout())),
+    };
+
+    for (i, trace) in config.traces.iter().enumerate() {
+        if config.traces.len() > 1 {
+            if i > 0 {
+                writeln!(writer)?;
+            }
+            writeln!(writer, "==> {} <==", trace.display())?;
+        }
+        run(&config, trace, args.command.as_ref(), &mut writer)?;
     }
     writer.flush()?;
 
     Ok(())
 }
+
+// parse or read a trace and run the command on it
+fn run(
+    config: &Config,
+    trace: &Path,
+    command: Option<&Command>,
+    writer: &mut BufWriter<Box<dyn Write>>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let (mut dep_graph, snapshot) = match config.archived {
+        true => {
+            let mut reader = config.reader(trace);
+            (reader.read()?, reader.snapshot()?)
+        }
+        false => {
+            let mut parser = config.parser(trace);
+            let dep_graph = parser.parse()?;
+
+            for discrepancy in parser.fd_discrepancies()? {
+                eprintln!("warning: {}", discrepancy);
+            }
+            for unresolved in parser.unresolved_paths()? {
+                eprintln!("warning: {}", unresolved);
+            }
+
+            (dep_graph, parser.snapshot()?)
+        }
+    };
+
+    match command {
+        Some(Command::Replay { .. }) => {
+            let root =
+                config.replay.root.as_ref().ok_or(
+                    "no replay root given, expected --root, or a root in the configuration",
+                )?;
+            let replay = config.replay.replay(root);
+
+            replay.prepare(&snapshot)?;
+            let report = replay.run(&mut dep_graph)?;
+            writer.write_all(report.to_string().as_ref())?;
+
+            if config.replay.verify {
+                let mut expected = ExpectedState::new(&snapshot);
+                let mut mismatches = expected.apply_replayed(&report);
+                mismatches.extend(expected.verify(&replay));
+                for mismatch in mismatches.iter() {
+                    eprintln!("mismatch: {}", mismatch);
+                }
+                eprintln!("verified the replay with {} mismatches", mismatches.len());
+            }
+
+            eprintln!(
+                "replayed {} operations in {}ms with {} errors",
+                report.ops().len(),
+                report.elapsed().as_millis(),
+                report.errors().len()
+            );
+        }
+        Some(Command::Export { .. }) => {
+            let script = config.export.script.unwrap_or(Script::Shell);
+            let program = script.export(&snapshot, &mut dep_graph)?;
+            writer.write_all(program.as_ref())?;
+        }
+        Some(Command::Workload { .. }) => {
+            let benchmark = config.workload.benchmark.unwrap_or(Workload::Fio);
+            let directory = config
+                .workload
+                .directory
+                .as_deref()
+                .unwrap_or("/tmp/strace-parser");
+            let workload = benchmark.export(&mut dep_graph, directory)?;
+            writer.write_all(workload.as_ref())?;
+        }
+        Some(Command::Stats) => {
+            let processes = processes(&mut dep_graph)?;
+            writer.write_all(StatsReport::new(&processes).to_string().as_ref())?;
+        }
+        Some(Command::Lifetimes) => {
+            let processes = processes(&mut dep_graph)?;
+            writer.write_all(Lifetimes::new(&processes, &snapshot).to_string().as_ref())?;
+        }
+        Some(Command::Parallelism) => {
+            writer.write_all(dep_graph.parallelism()?.to_string().as_ref())?;
+        }
+        Some(Command::Patterns) => {
+            let processes = processes(&mut dep_graph)?;
+            writer.write_all(AccessPatterns::new(&processes).to_string().as_ref())?;
+        }
+        Some(Command::Graph) => {
+            let format = config.format.unwrap_or(Format::Text);
+            writer.write_all(format.graph(&dep_graph)?.as_ref())?;
+        }
+        None => match config.format.unwrap_or(Format::Text) {
+            Format::Binary => {
+                let mut binary = BinaryWriter::new();
+                for process in processes(&mut dep_graph)?.iter() {
+                    binary.add_process(process)?;
+                }
+                binary.set_snapshot(&snapshot);
+                writer.write_all(&binary.finish())?;
+            }
+            format => writer.write_all(format.ops(&mut dep_graph)?.as_ref())?,
+        },
+    }
+
+    Ok(())
+}
+
+// split a FROM=TO option
+fn pair(option: &str, name: &str) -> Result<(String, String), String> {
+    option
+        .split_once("=")
+        .map(|(from, to)| (from.to_string(), to.to_string()))
+        .ok_or(format!("invalid {} {}, expected FROM=TO", name, option))
+}
+
+// the processes of all the available sets of the dependency graph
+fn processes(dep_graph: &mut DependencyGraph) -> Result<Vec<Process>, Box<dyn std::error::Error>> {
+    let mut processes = vec![];
+    let mut available_set = dep_graph.available_set()?;
+    while !available_set.is_empty() {
+        processes.extend(available_set);
+        available_set = dep_graph.available_set()?;
+    }
+
+    Ok(processes)
+}
@@ -0,0 +1,751 @@
+use crate::error::Error;
+use crate::file::{File, FileKind};
+use crate::op::{Operation, OperationType};
+use crate::parser::FileType;
+use crate::process::Process;
+use crate::snapshot::Snapshot;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+///
+/// The bytes a binary trace starts with
+///
+pub const MAGIC: &[u8; 4] = b"STPB";
+const VERSION: u8 = 1;
+
+// the flags added to the tag of an operation, if it has a timestamp, or a duration
+const TIMESTAMP: u8 = 0x40;
+const DURATION: u8 = 0x80;
+
+///
+/// A writer of the parsed processes, and the files that existed before the trace started, as a
+/// compact binary trace. The paths and the other texts are interned in a table of strings, the
+/// files, with their identities, in a table of files, and the operations of each process are a
+/// stream of their own, with the numbers as variable-length integers and each timestamp as the
+/// difference from the previous one.
+///
+#[derive(Debug, Default)]
+pub struct BinaryWriter {
+    strings: Vec<String>,                                      // the interned texts
+    string_ids: HashMap<String, usize>,                        // the index of each interned text
+    files: Vec<(usize, usize, usize, FileKind)>, // the path, canonical path, generation, and kind of each file
+    file_ids: HashMap<(usize, usize, usize, FileKind), usize>, // the index of each file
+    snapshot: Vec<(bool, usize, usize)>, // whether a directory, the path, and the size of each initial file
+    streams: Vec<(usize, usize, Vec<u8>)>, // the pid, number of operations, and operations of each process
+}
+
+impl BinaryWriter {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn add_process(&mut self, process: &Process) -> Result<(), Box<dyn std::error::Error>> {
+        let mut stream = vec![];
+        let mut last = 0;
+
+        for op in process.ops() {
+            let mut tag = tag(op.op_type());
+            if op.timestamp().is_some() {
+                tag |= TIMESTAMP;
+            }
+            if op.duration().is_some() {
+                tag |= DURATION;
+            }
+            stream.push(tag);
+
+            if let Some(timestamp) = op.timestamp() {
+                let nanos = timestamp.as_nanos() as i64;
+                put_signed(&mut stream, nanos - last);
+                last = nanos;
+            }
+            if let Some(duration) = op.duration() {
+                put_varint(&mut stream, duration.as_nanos() as u64);
+            }
+
+            match op.op_type() {
+                OperationType::Read(file, offset, len) => {
+                    put_varint(&mut stream, self.file(file)? as u64);
+                    put_signed(&mut stream, *offset as i64);
+                    put_varint(&mut stream, *len as u64);
+                }
+                OperationType::Write(file, offset, len, content) => {
+                    put_varint(&mut stream, self.file(file)? as u64);
+                    put_signed(&mut stream, *offset as i64);
+                    put_varint(&mut stream, *len as u64);
+                    put_varint(&mut stream, self.string(content) as u64);
+                }
+                OperationType::Mkdir(file, mode) => {
+                    put_varint(&mut stream, self.file(file)? as u64);
+                    put_varint(&mut stream, self.string(mode) as u64);
+                }
+                OperationType::Rename(file, to) => {
+                    put_varint(&mut stream, self.file(file)? as u64);
+                    put_varint(&mut stream, self.string(to) as u64);
+                }
+                OperationType::OpenAt(file, offset) => {
+                    put_varint(&mut stream, self.file(file)? as u64);
+                    put_signed(&mut stream, *offset as i64);
+                }
+                OperationType::Mknod(file)
+                | OperationType::Remove(file)
+                | OperationType::Truncate(file)
+                | OperationType::Stat(file)
+                | OperationType::Fstat(file)
+                | OperationType::Statx(file)
+                | OperationType::StatFS(file)
+                | OperationType::Fstatat(file)
+                | OperationType::Sync(file) => {
+                    put_varint(&mut stream, self.file(file)? as u64);
+                }
+                OperationType::GetRandom(len) => put_varint(&mut stream, *len as u64),
+                OperationType::Clone(pid) => put_varint(&mut stream, *pid as u64),
+                OperationType::Chdir(path) => put_varint(&mut stream, self.string(path) as u64),
+                OperationType::Map(file, offset, len, prot, flags) => {
+                    put_varint(&mut stream, self.file(file)? as u64);
+                    put_signed(&mut stream, *offset as i64);
+                    put_varint(&mut stream, *len as u64);
+                    put_varint(&mut stream, self.string(prot) as u64);
+                    put_varint(&mut stream, self.string(flags) as u64);
+                }
+                OperationType::Copy(from, from_offset, to, to_offset, len) => {
+                    put_varint(&mut stream, self.file(from)? as u64);
+                    put_signed(&mut stream, *from_offset as i64);
+                    put_varint(&mut stream, self.file(to)? as u64);
+                    put_signed(&mut stream, *to_offset as i64);
+                    put_varint(&mut stream, *len as u64);
+                }
+                OperationType::NoOp => {}
+            }
+        }
+
+        self.streams
+            .push((process.pid(), process.ops().len(), stream));
+
+        Ok(())
+    }
+
+    pub fn set_snapshot(&mut self, snapshot: &Snapshot) {
+        let mut files = vec![];
+        for file_type in snapshot.files() {
+            match file_type {
+                FileType::File(path, size) => files.push((false, self.string(path), *size)),
+                FileType::Dir(path, size) => files.push((true, self.string(path), *size)),
+                FileType::Other => {}
+            }
+        }
+        self.snapshot = files;
+    }
+
+    ///
+    /// The binary trace: the magic and version, then the strings, files, snapshot, and process
+    /// streams, each table with its number of entries first
+    ///
+    pub fn finish(self) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        bytes.push(VERSION);
+
+        put_varint(&mut bytes, self.strings.len() as u64);
+        for string in self.strings.iter() {
+            put_varint(&mut bytes, string.len() as u64);
+            bytes.extend_from_slice(string.as_bytes());
+        }
+
+        put_varint(&mut bytes, self.files.len() as u64);
+        for (path, canonical, generation, kind) in self.files.iter() {
+            put_varint(&mut bytes, *path as u64);
+            put_varint(&mut bytes, *canonical as u64);
+            put_varint(&mut bytes, *generation as u64);
+            bytes.push(kind_tag(*kind));
+        }
+
+        put_varint(&mut bytes, self.snapshot.len() as u64);
+        for (dir, path, size) in self.snapshot.iter() {
+            bytes.push(*dir as u8);
+            put_varint(&mut bytes, *path as u64);
+            put_varint(&mut bytes, *size as u64);
+        }
+
+        put_varint(&mut bytes, self.streams.len() as u64);
+        for (pid, ops, stream) in self.streams.iter() {
+            put_varint(&mut bytes, *pid as u64);
+            put_varint(&mut bytes, *ops as u64);
+            put_varint(&mut bytes, stream.len() as u64);
+            bytes.extend_from_slice(stream);
+        }
+
+        bytes
+    }
+
+    fn string(&mut self, s: &str) -> usize {
+        match self.string_ids.get(s) {
+            Some(id) => *id,
+            None => {
+                let id = self.strings.len();
+                self.strings.push(s.to_string());
+                self.string_ids.insert(s.to_string(), id);
+                id
+            }
+        }
+    }
+
+    fn file(&mut self, file: &Arc<File>) -> Result<usize, Box<dyn std::error::Error>> {
+        let key = (
+            self.string(file.path()?),
+            self.string(file.canonical()?),
+            file.generation(),
+            file.kind(),
+        );
+
+        Ok(match self.file_ids.get(&key) {
+            Some(id) => *id,
+            None => {
+                let id = self.files.len();
+                self.files.push(key);
+                self.file_ids.insert(key, id);
+                id
+            }
+        })
+    }
+}
+
+///
+/// A binary trace read in place: its strings are borrowed from the bytes, and the operations of
+/// each process are only decoded when the process is asked for
+///
+#[derive(Debug)]
+pub struct BinaryTrace<'a> {
+    strings: Vec<&'a str>,
+    files: Vec<(usize, usize, usize, FileKind)>, // the path, canonical path, generation, and kind of each file
+    snapshot: Vec<(bool, usize, usize)>, // whether a directory, the path, and the size of each initial file
+    streams: Vec<(usize, usize, &'a [u8])>, // the pid, number of operations, and operations of each process
+}
+
+impl<'a> BinaryTrace<'a> {
+    pub fn parse(bytes: &'a [u8]) -> Result<Self, Error> {
+        let mut cursor = Cursor { bytes, pos: 0 };
+        if cursor.bytes(MAGIC.len())? != MAGIC {
+            return Err(Error::ParseError("binary trace, bad magic".to_string()));
+        }
+        let version = cursor.byte()?;
+        if version != VERSION {
+            return Err(Error::ParseError(format!(
+                "binary trace, version {}, expected {}",
+                version, VERSION
+            )));
+        }
+
+        let mut strings = vec![];
+        for _ in 0..cursor.usize()? {
+            let len = cursor.usize()?;
+            let string = std::str::from_utf8(cursor.bytes(len)?)
+                .map_err(|e| Error::ParseError(format!("binary trace, {}", e)))?;
+            strings.push(string);
+        }
+
+        let mut files = vec![];
+        for _ in 0..cursor.usize()? {
+            let path = string_id(&strings, cursor.usize()?)?;
+            let canonical = string_id(&strings, cursor.usize()?)?;
+            let generation = cursor.usize()?;
+            let kind = match cursor.byte()? {
+                0 => FileKind::Regular,
+                1 => FileKind::Pipe,
+                2 => FileKind::Socket,
+                3 => FileKind::EventFd,
+                4 => FileKind::MemFd,
+                kind => {
+                    return Err(Error::ParseError(format!(
+                        "binary trace, file kind {}",
+                        kind
+                    )))
+                }
+            };
+            files.push((path, canonical, generation, kind));
+        }
+
+        let mut snapshot = vec![];
+        for _ in 0..cursor.usize()? {
+            let dir = cursor.byte()? != 0;
+            let path = string_id(&strings, cursor.usize()?)?;
+            snapshot.push((dir, path, cursor.usize()?));
+        }
+
+        let mut streams = vec![];
+        for _ in 0..cursor.usize()? {
+            let pid = cursor.usize()?;
+            let ops = cursor.usize()?;
+            let len = cursor.usize()?;
+            streams.push((pid, ops, cursor.bytes(len)?));
+        }
+
+        if cursor.pos != bytes.len() {
+            return Err(Error::ParseError(
+                "binary trace, trailing bytes".to_string(),
+            ));
+        }
+
+        Ok(BinaryTrace {
+            strings,
+            files,
+            snapshot,
+            streams,
+        })
+    }
+
+    ///
+    /// The pids of the processes, in the order they were written
+    ///
+    pub fn pids(&self) -> Vec<usize> {
+        self.streams.iter().map(|(pid, _, _)| *pid).collect()
+    }
+
+    ///
+    /// The number of operations of all the processes
+    ///
+    pub fn ops(&self) -> usize {
+        self.streams.iter().map(|(_, ops, _)| ops).sum()
+    }
+
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot::new(
+            self.snapshot
+                .iter()
+                .map(|(dir, path, size)| match dir {
+                    true => FileType::Dir(self.strings[*path].to_string(), *size),
+                    false => FileType::File(self.strings[*path].to_string(), *size),
+                })
+                .collect(),
+        )
+    }
+
+    ///
+    /// The process with the pid, decoding only its operations
+    ///
+    pub fn process(&self, pid: usize) -> Result<Option<Process>, Error> {
+        let files = self.files();
+        self.streams
+            .iter()
+            .find(|(id, _, _)| *id == pid)
+            .map(|stream| self.decode(stream, &files))
+            .transpose()
+    }
+
+    pub fn processes(&self) -> Result<Vec<Process>, Error> {
+        let files = self.files();
+        self.streams
+            .iter()
+            .map(|stream| self.decode(stream, &files))
+            .collect()
+    }
+
+    // the files the operations share, like the parser shares them
+    fn files(&self) -> Vec<Arc<File>> {
+        self.files
+            .iter()
+            .map(|(path, canonical, generation, kind)| {
+                Arc::new(
+                    File::with_kind(self.strings[*path], *kind)
+                        .with_canonical(self.strings[*canonical])
+                        .with_generation(*generation),
+                )
+            })
+            .collect()
+    }
+
+    fn decode(
+        &self,
+        (pid, ops, stream): &(usize, usize, &'a [u8]),
+        files: &[Arc<File>],
+    ) -> Result<Process, Error> {
+        let mut cursor = Cursor {
+            bytes: stream,
+            pos: 0,
+        };
+        let file = |cursor: &mut Cursor| -> Result<Arc<File>, Error> {
+            let id = cursor.usize()?;
+            files
+                .get(id)
+                .cloned()
+                .ok_or(Error::ParseError(format!("binary trace, file {}", id)))
+        };
+        let string = |cursor: &mut Cursor| -> Result<String, Error> {
+            Ok(self.strings[string_id(&self.strings, cursor.usize()?)?].to_string())
+        };
+
+        let mut process = Process::new(*pid);
+        let mut last = 0;
+        for _ in 0..*ops {
+            let tag = cursor.byte()?;
+
+            let timestamp = match tag & TIMESTAMP {
+                0 => None,
+                _ => {
+                    // a corrupted difference could take the timestamp out of range
+                    last = cursor
+                        .signed()?
+                        .checked_add(last)
+                        .filter(|nanos| *nanos >= 0)
+                        .ok_or(Error::ParseError(
+                            "binary trace, timestamp out of range".to_string(),
+                        ))?;
+                    Some(Duration::from_nanos(last as u64))
+                }
+            };
+            let duration = match tag & DURATION {
+                0 => None,
+                _ => Some(Duration::from_nanos(cursor.varint()?)),
+            };
+
+            let op = match tag & !(TIMESTAMP | DURATION) {
+                0 => {
+                    let file = file(&mut cursor)?;
+                    let offset = cursor.offset()?;
+                    Operation::read(file, cursor.usize()?, offset)
+                }
+                1 => {
+                    let file = file(&mut cursor)?;
+                    let offset = cursor.offset()?;
+                    let len = cursor.usize()?;
+                    Operation::write(file, string(&mut cursor)?, len, offset)
+                }
+                2 => Operation::mkdir(file(&mut cursor)?, string(&mut cursor)?),
+                3 => Operation::mknod(file(&mut cursor)?),
+                4 => Operation::remove(file(&mut cursor)?),
+                5 => Operation::rename(file(&mut cursor)?, string(&mut cursor)?),
+                6 => Operation::open_at(file(&mut cursor)?, cursor.offset()?),
+                7 => Operation::truncate(file(&mut cursor)?),
+                8 => Operation::get_random(cursor.usize()?),
+                9 => Operation::stat(file(&mut cursor)?),
+                10 => Operation::fstat(file(&mut cursor)?),
+                11 => Operation::statx(file(&mut cursor)?),
+                12 => Operation::statfs(file(&mut cursor)?),
+                13 => Operation::fstatat(file(&mut cursor)?),
+                14 => Operation::clone_op(cursor.usize()?),
+                15 => Operation::chdir(string(&mut cursor)?),
+                16 => Operation::map(
+                    file(&mut cursor)?,
+                    cursor.offset()?,
+                    cursor.usize()?,
+                    string(&mut cursor)?,
+                    string(&mut cursor)?,
+                ),
+                17 => Operation::sync(file(&mut cursor)?),
+                18 => Operation::copy(
+                    file(&mut cursor)?,
+                    cursor.offset()?,
+                    file(&mut cursor)?,
+                    cursor.offset()?,
+                    cursor.usize()?,
+                ),
+                19 => Operation::no_op(),
+                tag => {
+                    return Err(Error::ParseError(format!(
+                        "binary trace, operation tag {}",
+                        tag
+                    )))
+                }
+            };
+
+            process.add_op(op.with_timing(timestamp, duration));
+        }
+
+        if cursor.pos != stream.len() {
+            return Err(Error::ParseError(format!(
+                "binary trace, trailing bytes in process {}",
+                pid
+            )));
+        }
+
+        Ok(process)
+    }
+}
+
+// the bytes of a binary trace, read from the start
+struct Cursor<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn byte(&mut self) -> Result<u8, Error> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], Error> {
+        let end = self
+            .pos
+            .checked_add(len)
+            .filter(|end| *end <= self.bytes.len())
+            .ok_or(Error::ParseError("binary trace, truncated".to_string()))?;
+        let bytes = &self.bytes[self.pos..end];
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    // an unsigned LEB128 integer, seven bits per byte, the lowest first
+    fn varint(&mut self) -> Result<u64, Error> {
+        let mut value = 0;
+        for shift in (0..64).step_by(7) {
+            let byte = self.byte()?;
+            value |= ((byte & 0x7f) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(Error::ParseError(
+            "binary trace, integer too long".to_string(),
+        ))
+    }
+
+    // a zigzag encoded integer, so the small negative numbers are short too
+    fn signed(&mut self) -> Result<i64, Error> {
+        let value = self.varint()?;
+        Ok((value >> 1) as i64 ^ -((value & 1) as i64))
+    }
+
+    fn usize(&mut self) -> Result<usize, Error> {
+        usize::try_from(self.varint()?)
+            .map_err(|e| Error::ParseError(format!("binary trace, {}", e)))
+    }
+
+    fn offset(&mut self) -> Result<i32, Error> {
+        i32::try_from(self.signed()?).map_err(|e| Error::ParseError(format!("binary trace, {}", e)))
+    }
+}
+
+fn put_varint(bytes: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        bytes.push((value as u8 & 0x7f) | 0x80);
+        value >>= 7;
+    }
+    bytes.push(value as u8);
+}
+
+fn put_signed(bytes: &mut Vec<u8>, value: i64) {
+    put_varint(bytes, ((value << 1) ^ (value >> 63)) as u64);
+}
+
+fn string_id(strings: &[&str], id: usize) -> Result<usize, Error> {
+    match id < strings.len() {
+        true => Ok(id),
+        false => Err(Error::ParseError(format!("binary trace, string {}", id))),
+    }
+}
+
+// the tag of an operation, in the order of the operation types
+fn tag(op_type: &OperationType) -> u8 {
+    match op_type {
+        OperationType::Read(_, _, _) => 0,
+        OperationType::Write(_, _, _, _) => 1,
+        OperationType::Mkdir(_, _) => 2,
+        OperationType::Mknod(_) => 3,
+        OperationType::Remove(_) => 4,
+        OperationType::Rename(_, _) => 5,
+        OperationType::OpenAt(_, _) => 6,
+        OperationType::Truncate(_) => 7,
+        OperationType::GetRandom(_) => 8,
+        OperationType::Stat(_) => 9,
+        OperationType::Fstat(_) => 10,
+        OperationType::Statx(_) => 11,
+        OperationType::StatFS(_) => 12,
+        OperationType::Fstatat(_) => 13,
+        OperationType::Clone(_) => 14,
+        OperationType::Chdir(_) => 15,
+        OperationType::Map(_, _, _, _, _) => 16,
+        OperationType::Sync(_) => 17,
+        OperationType::Copy(_, _, _, _, _) => 18,
+        OperationType::NoOp => 19,
+    }
+}
+
+fn kind_tag(kind: FileKind) -> u8 {
+    match kind {
+        FileKind::Regular => 0,
+        FileKind::Pipe => 1,
+        FileKind::Socket => 2,
+        FileKind::EventFd => 3,
+        FileKind::MemFd => 4,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::binary::{BinaryTrace, BinaryWriter, Cursor};
+    use crate::file::{File, FileKind};
+    use crate::op::{Operation, OperationType};
+    use crate::parser::FileType;
+    use crate::process::Process;
+    use crate::snapshot::Snapshot;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    fn processes() -> Vec<Process> {
+        let link = Arc::new(File::new("/data/link").with_canonical("/data/target"));
+        let old = Arc::new(File::new("/data/out").with_generation(1));
+        let pipe = Arc::new(File::with_kind("pipe:[7]", FileKind::Pipe));
+
+        let mut parent = Process::new(10);
+        parent.add_op(
+            Operation::read(link.clone(), 4096, -1)
+                .with_timing(Some(Duration::from_micros(86_399_000_250)), None),
+        );
+        parent.add_op(
+            Operation::write(pipe.clone(), "a, b)".to_string(), 5, 0).with_timing(
+                Some(Duration::from_micros(86_399_000_100)),
+                Some(Duration::from_micros(30)),
+            ),
+        );
+        parent.add_op(Operation::map(
+            link.clone(),
+            0,
+            8192,
+            "PROT_READ".to_string(),
+            "MAP_SHARED".to_string(),
+        ));
+        parent.add_op(Operation::clone_op(11));
+        parent.add_op(Operation::no_op());
+
+        let mut child = Process::new(11);
+        child.add_op(Operation::chdir("/data".to_string()));
+        child.add_op(Operation::read(pipe.clone(), 5, 0));
+        child.add_op(Operation::copy(link.clone(), 0, old.clone(), 0, 64));
+        child.add_op(Operation::rename(old.clone(), "/data/new".to_string()));
+        child.add_op(Operation::fstatat(Arc::new(
+            File::new("/data/new").with_canonical("/data/out"),
+        )));
+        child.add_op(Operation::get_random(16));
+
+        vec![parent, child]
+    }
+
+    #[test]
+    fn round_trip() -> Result<(), Box<dyn std::error::Error>> {
+        let snapshot = Snapshot::new(vec![
+            FileType::Dir("/data".to_string(), 4096),
+            FileType::File("/data/target".to_string(), 8192),
+        ]);
+
+        let mut writer = BinaryWriter::new();
+        for process in processes().iter() {
+            writer.add_process(process)?;
+        }
+        writer.set_snapshot(&snapshot);
+        let bytes = writer.finish();
+
+        let trace = BinaryTrace::parse(&bytes)?;
+        assert_eq!(trace.pids(), vec![10, 11]);
+        assert_eq!(trace.ops(), 11);
+        assert_eq!(trace.snapshot(), snapshot);
+
+        // the operations are the same, with their timing and the identities of their files
+        let read = trace.processes()?;
+        for (read, written) in read.iter().zip(processes().iter()) {
+            assert_eq!(read.pid(), written.pid());
+            assert_eq!(read.ops(), written.ops());
+        }
+        match read[0].ops()[0].op_type() {
+            OperationType::Read(file, _, _) => {
+                assert_eq!(file.path()?, "/data/link");
+                assert_eq!(file.canonical()?, "/data/target");
+            }
+            _ => panic!("expected a read"),
+        }
+        match read[1].ops()[2].op_type() {
+            OperationType::Copy(_, _, to, _, _) => assert_eq!(to.generation(), 1),
+            _ => panic!("expected a copy"),
+        }
+
+        let child = trace.process(11)?.ok_or("no process 11")?;
+        assert_eq!(child.ops(), processes()[1].ops());
+        assert!(trace.process(12)?.is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn compact() -> Result<(), Box<dyn std::error::Error>> {
+        let file = Arc::new(File::new(
+            "/home/user/project/target/debug/deps/libregex.rlib",
+        ));
+        let mut process = Process::new(909190);
+        for i in 0..1000 {
+            process.add_op(Operation::read(file.clone(), 4096, i * 4096));
+        }
+
+        let mut writer = BinaryWriter::new();
+        writer.add_process(&process)?;
+        let bytes = writer.finish();
+
+        // the path is written once, and each read in at most 8 bytes
+        assert!(bytes.len() < 8 * 1000 + 64);
+        assert!(bytes.len() * 8 < process.to_string().len());
+
+        Ok(())
+    }
+
+    #[test]
+    fn varints() -> Result<(), Box<dyn std::error::Error>> {
+        let mut bytes = vec![];
+        super::put_varint(&mut bytes, 300);
+        super::put_signed(&mut bytes, -1);
+        super::put_signed(&mut bytes, i64::MIN);
+        super::put_varint(&mut bytes, u64::MAX);
+        assert_eq!(&bytes[..3], &[0xac, 0x02, 0x01]);
+
+        let mut cursor = Cursor {
+            bytes: &bytes,
+            pos: 0,
+        };
+        assert_eq!(cursor.varint()?, 300);
+        assert_eq!(cursor.signed()?, -1);
+        assert_eq!(cursor.signed()?, i64::MIN);
+        assert_eq!(cursor.varint()?, u64::MAX);
+        assert!(cursor.byte().is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn errors() -> Result<(), Box<dyn std::error::Error>> {
+        let mut writer = BinaryWriter::new();
+        for process in processes().iter() {
+            writer.add_process(process)?;
+        }
+        let bytes = writer.finish();
+
+        assert!(BinaryTrace::parse(b"STPA\x01\x00\x00\x00\x00").is_err());
+        assert!(BinaryTrace::parse(b"STPB\x02\x00\x00\x00\x00").is_err());
+        assert!(BinaryTrace::parse(b"STPB\x01\x00\x00\x00\x00").is_ok());
+        assert!(BinaryTrace::parse(&bytes[..bytes.len() - 1]).is_err());
+
+        // an unknown operation tag in the last process
+        let mut corrupted = bytes.clone();
+        let len = corrupted.len();
+        corrupted[len - 2] = 0x3f;
+        assert!(BinaryTrace::parse(&corrupted)?.processes().is_err());
+
+        // the timestamps, each stored as the difference from the previous one, are in range
+        let timed =
+            |timestamps: &[u64], difference: u8| -> Result<bool, Box<dyn std::error::Error>> {
+                let mut process = Process::new(10);
+                for nanos in timestamps {
+                    let timestamp = Some(Duration::from_nanos(*nanos));
+                    process.add_op(Operation::no_op().with_timing(timestamp, None));
+                }
+                let mut writer = BinaryWriter::new();
+                writer.add_process(&process)?;
+                let mut bytes = writer.finish();
+                let len = bytes.len();
+                bytes[len - 1] = difference;
+                Ok(BinaryTrace::parse(&bytes)?.processes().is_ok())
+            };
+        let max = i64::MAX as u64;
+        assert!(timed(&[max, max], 0x00)?);
+        // one more nanosecond overflows, and one less than zero is negative
+        assert!(!timed(&[max, max], 0x02)?);
+        assert!(!timed(&[0], 0x01)?);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,327 @@
+use crate::error::Error;
+use crate::export::Script;
+use crate::filter::Filter;
+use crate::format::Format;
+use crate::parser::Parser;
+use crate::payload::Payload;
+use crate::reader::Reader;
+use crate::replay::{Pacing, Replay};
+use crate::rules::{PathRules, Preset};
+use crate::workload::Workload;
+use serde::{Deserialize, Deserializer};
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+///
+/// The setup of an experiment: the traces, how they are parsed, and the options of the commands
+/// run on them, read from a TOML or YAML file. The relative paths of the traces, the output,
+/// the replay root, and the workload directory are relative to the directory of the file. The
+/// dependency graph is always ordered the same way, so there are no options for its ordering.
+///
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub traces: Vec<PathBuf>, // the logged traces, each parsed and reported on its own
+    pub out: Option<PathBuf>, // the output file, or the standard output if none
+    pub archived: bool, // whether the traces are the operations strace-parser wrote, rather than strace logs
+    #[serde(deserialize_with = "parsed")]
+    pub format: Option<Format>, // the format of the ordered operations and of the graph
+    #[serde(deserialize_with = "parsed")]
+    pub filter: Option<Filter>, // the operations kept in the dependency graph
+    pub syscalls: Option<Vec<String>>, // the system calls whose operations are kept, or all if none
+    pub paths: PathsConfig,
+    pub replay: ReplayConfig,
+    pub export: ExportConfig,
+    pub workload: WorkloadConfig,
+}
+
+///
+/// The paths excluded from the traces, and how they are rewritten
+///
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PathsConfig {
+    #[serde(deserialize_with = "all_parsed")]
+    pub presets: Vec<Preset>,
+    pub exclude: Vec<String>,
+    pub include: Vec<String>,
+    pub rewrite: BTreeMap<String, String>, // the path prefixes replaced, and their replacements
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ReplayConfig {
+    pub root: Option<PathBuf>,
+    pub remap: BTreeMap<String, String>, // the path prefixes replaced before replaying under root
+    #[serde(deserialize_with = "parsed")]
+    pub pacing: Option<Pacing>,
+    #[serde(deserialize_with = "parsed")]
+    pub payload: Option<Payload>,
+    pub verify: bool,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ExportConfig {
+    #[serde(deserialize_with = "parsed")]
+    pub script: Option<Script>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WorkloadConfig {
+    #[serde(deserialize_with = "parsed")]
+    pub benchmark: Option<Workload>,
+    pub directory: Option<String>,
+}
+
+impl Config {
+    ///
+    /// Read the configuration from a .toml, .yaml, or .yml file
+    ///
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn std::error::Error>> {
+        let path = path.as_ref();
+        let from_str = match path.extension().and_then(|extension| extension.to_str()) {
+            Some("toml") => Config::from_toml,
+            Some("yaml") | Some("yml") => Config::from_yaml,
+            _ => {
+                return Err(Box::new(Error::ParseError(format!(
+                    "configuration {}, expected a .toml, .yaml, or .yml file",
+                    path.display()
+                ))))
+            }
+        };
+        let mut config = from_str(&std::fs::read_to_string(path)?)?;
+
+        if let Some(dir) = path.parent() {
+            config.traces = config.traces.iter().map(|trace| dir.join(trace)).collect();
+            config.out = config.out.map(|out| dir.join(out));
+            config.replay.root = config.replay.root.map(|root| dir.join(root));
+            config.workload.directory = config
+                .workload
+                .directory
+                .map(|directory| dir.join(directory).to_string_lossy().to_string());
+        }
+
+        Ok(config)
+    }
+
+    pub fn from_toml(content: &str) -> Result<Self, Error> {
+        toml::from_str(content).map_err(|e| Error::ParseError(format!("configuration: {}", e)))
+    }
+
+    pub fn from_yaml(content: &str) -> Result<Self, Error> {
+        serde_yaml::from_str(content)
+            .map_err(|e| Error::ParseError(format!("configuration: {}", e)))
+    }
+
+    ///
+    /// The parser of a trace, with the filter, path rules, and system calls of the configuration
+    ///
+    pub fn parser<P: AsRef<Path>>(&self, trace: P) -> Parser {
+        let mut parser = Parser::new(trace.as_ref().to_path_buf());
+        if let Some(filter) = &self.filter {
+            parser.set_filter(filter.clone());
+        }
+        if let Some(syscalls) = &self.syscalls {
+            parser.set_syscalls(syscalls);
+        }
+        parser.set_rules(self.paths.rules());
+
+        parser
+    }
+
+    ///
+    /// The reader of an archived trace, with the filter of the configuration
+    ///
+    pub fn reader<P: AsRef<Path>>(&self, trace: P) -> Reader {
+        let mut reader = Reader::new(trace.as_ref().to_path_buf());
+        if let Some(filter) = &self.filter {
+            reader.set_filter(filter.clone());
+        }
+
+        reader
+    }
+}
+
+impl PathsConfig {
+    pub fn rules(&self) -> PathRules {
+        let mut rules = PathRules::new();
+        for preset in self.presets.iter() {
+            rules.preset(*preset);
+        }
+        for prefix in self.exclude.iter() {
+            rules.exclude(prefix);
+        }
+        for prefix in self.include.iter() {
+            rules.include(prefix);
+        }
+        for (from, to) in self.rewrite.iter() {
+            rules.rewrite(from, to);
+        }
+
+        rules
+    }
+}
+
+impl ReplayConfig {
+    ///
+    /// The replay under the root, with the remaps, pacing, and payload of the configuration
+    ///
+    pub fn replay<P: AsRef<Path>>(&self, root: P) -> Replay {
+        let mut replay = Replay::new(root);
+        for (from, to) in self.remap.iter() {
+            replay.remap(from, to);
+        }
+        if let Some(pacing) = self.pacing {
+            replay.set_pacing(pacing);
+        }
+        if let Some(payload) = &self.payload {
+            replay.set_payload(payload.clone());
+        }
+
+        replay
+    }
+}
+
+// deserialize a value from the string its FromStr parses, like the command line options
+fn parsed<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr,
+    T::Err: Display,
+{
+    Option::<String>::deserialize(deserializer)?
+        .map(|s| s.parse().map_err(serde::de::Error::custom))
+        .transpose()
+}
+
+fn all_parsed<'de, D, T>(deserializer: D) -> Result<Vec<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr,
+    T::Err: Display,
+{
+    Vec::<String>::deserialize(deserializer)?
+        .iter()
+        .map(|s| s.parse().map_err(serde::de::Error::custom))
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::config::Config;
+    use crate::export::Script;
+    use crate::format::Format;
+    use crate::replay::Pacing;
+    use crate::rules::Preset;
+    use std::path::PathBuf;
+
+    #[test]
+    fn toml() -> Result<(), Box<dyn std::error::Error>> {
+        let config = Config::from_toml(
+            r#"
+            traces = ["traces/build.log"]
+            format = "jsonl"
+            archived = true
+            filter = "op = write and path ~ /tmp/**"
+            syscalls = ["openat", "write"]
+
+            [paths]
+            presets = ["system", "pseudo"]
+            include = ["/proc/self/fd"]
+            rewrite = { "/home/user/project" = "$ROOT" }
+
+            [replay]
+            root = "/tmp/replay"
+            remap = { "$ROOT" = "/tmp/project" }
+            pacing = "original"
+            verify = true
+
+            [export]
+            script = "c"
+            "#,
+        )?;
+
+        assert_eq!(config.traces, vec![PathBuf::from("traces/build.log")]);
+        assert_eq!(config.format, Some(Format::Jsonl));
+        assert!(config.archived);
+        assert!(config.filter.is_some());
+        assert_eq!(
+            config.syscalls,
+            Some(vec!["openat".to_string(), "write".to_string()])
+        );
+        assert_eq!(config.paths.presets, vec![Preset::System, Preset::Pseudo]);
+
+        let rules = config.paths.rules();
+        assert!(rules.excludes("/proc/1/stat"));
+        assert!(!rules.excludes("/proc/self/fd/3"));
+        assert_eq!(rules.rewritten("/home/user/project/a.c"), "$ROOT/a.c");
+
+        let replay = config
+            .replay
+            .replay(config.replay.root.clone().unwrap_or_default());
+        assert_eq!(
+            replay.target("$ROOT/a.c").ok(),
+            Some(PathBuf::from("/tmp/replay/tmp/project/a.c"))
+        );
+        assert_eq!(config.replay.pacing, Some(Pacing::Original));
+        assert!(config.replay.verify);
+        assert_eq!(config.export.script, Some(Script::C));
+        assert_eq!(config.workload.benchmark, None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn yaml() -> Result<(), Box<dyn std::error::Error>> {
+        let config = Config::from_yaml(
+            "traces: [a.log, b.log]\npaths:\n  exclude: [/var/cache]\nworkload:\n  benchmark: filebench\n  directory: /mnt/bench\n",
+        )?;
+
+        assert_eq!(config.traces.len(), 2);
+        assert!(config.paths.rules().excludes("/var/cache/x"));
+        assert_eq!(config.workload.directory, Some("/mnt/bench".to_string()));
+
+        Ok(())
+    }
+
+    #[test]
+    fn relative() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = std::env::temp_dir().join(format!("strace-parser-config-{}", std::process::id()));
+        std::fs::create_dir_all(&dir)?;
+        let path = dir.join("experiment.toml");
+        std::fs::write(
+            &path,
+            "traces = [\"a.log\", \"/traces/b.log\"]\nout = \"a.txt\"\n\n[replay]\nroot = \"replay\"\n\n[workload]\ndirectory = \"bench\"\n",
+        )?;
+
+        let config = Config::from_file(&path)?;
+        std::fs::remove_dir_all(&dir)?;
+
+        // the relative paths are under the directory of the file, and the absolute ones are kept
+        assert_eq!(
+            config.traces,
+            vec![dir.join("a.log"), PathBuf::from("/traces/b.log")]
+        );
+        assert_eq!(config.out, Some(dir.join("a.txt")));
+        assert_eq!(config.replay.root, Some(dir.join("replay")));
+        assert_eq!(
+            config.workload.directory,
+            Some(dir.join("bench").to_string_lossy().to_string())
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn errors() {
+        assert!(Config::from_toml("trace = \"a.log\"").is_err());
+        assert!(Config::from_toml("filter = \"op >\"").is_err());
+        assert!(Config::from_toml("[paths]\npresets = [\"usr\"]").is_err());
+        assert!(Config::from_yaml("replay:\n  pacing: slow\n").is_err());
+        assert!(Config::from_file("strace.json").is_err());
+    }
+}
@@ -352,10 +352,15 @@ where
     }
 
     fn remove_incoming_neighbor(&self, neighbor: &Rc<Node<N>>) {
+        let before = self.in_neighbors.borrow().len();
         self.in_neighbors
             .borrow_mut()
             .retain(|n| !Weak::ptr_eq(n, &Rc::downgrade(neighbor)));
-        *self.in_degree.borrow_mut() -= 1;
+
+        // the edges with different labels between the same nodes count once in the in-degree
+        if self.in_neighbors.borrow().len() < before {
+            *self.in_degree.borrow_mut() -= 1;
+        }
     }
 
     pub fn in_degree(&self) -> usize {
@@ -604,6 +609,26 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    fn remove_labeled_edges() -> Result<(), Error> {
+        // two edges with different labels between the same nodes
+        let mut dag = DAG::new();
+        let n1 = dag.add_node("n1")?;
+        let n2 = dag.add_node("n2")?;
+        dag.add_edge("file", n1.clone(), n2.clone());
+        dag.add_edge("pipe", n1.clone(), n2.clone());
+
+        assert_eq!(dag.edges.len(), 2);
+        assert_eq!(n2.in_degree(), 1);
+
+        dag.remove_node(&n1);
+
+        assert_eq!(dag.edges.len(), 0);
+        assert_eq!(n2.in_degree(), 0);
+
+        Ok(())
+    }
+
     #[test]
     fn dag() -> Result<(), Error> {
         //
@@ -1,11 +1,13 @@
 use crate::dag::{Edge, DAG};
 use crate::error::Error;
-use crate::file::File;
+use crate::file::{File, FileKind};
 use crate::process::Process;
+use std::collections::HashMap;
 use std::fmt::{Display, Formatter};
 use std::hash::Hash;
 use std::rc::Rc;
 use std::sync::Arc;
+use std::time::Duration;
 
 // The dependecy graph node's can be Process or File
 #[derive(Hash, PartialEq, Eq)]
@@ -75,10 +77,9 @@ impl DependencyGraph {
         for process in processes {
             let pnode = dag.add_node(GraphNode::Process(process.clone()))?;
             for op in process.ops() {
-                let op_name = op.name();
-                if let Some(file) = op.file() {
+                for (file, label) in op.accesses() {
                     let fnode = dag.add_node(GraphNode::File(file))?;
-                    dag.add_edge(op_name, pnode.clone(), fnode);
+                    dag.add_edge(label, pnode.clone(), fnode);
                 }
             }
         }
@@ -130,6 +131,13 @@ impl DependencyGraph {
 
         for node in read_write_dag.nodes() {
             if node.data().ty() == "file" {
+                // a pipe connects its producer to its consumer directly
+                let label = if node.data().file()?.kind() == FileKind::Pipe {
+                    "pipe".to_string()
+                } else {
+                    "".to_string()
+                };
+
                 if !node.incoming_neighbors().is_empty() {
                     for incoming_process in node.incoming_neighbors().iter() {
                         let incoming_process = incoming_process.upgrade().ok_or(
@@ -144,7 +152,7 @@ impl DependencyGraph {
                                 let p2 = process_dag.add_node(GraphNode::Process(
                                     outgoing_process.data().process()?.clone(),
                                 ))?;
-                                process_dag.add_edge("".to_string(), p1.clone(), p2);
+                                process_dag.add_edge(label.clone(), p1.clone(), p2);
                             }
                         } else {
                             process_dag.add_edge("".to_string(), p1, end_node.clone());
@@ -245,6 +253,232 @@ impl DependencyGraph {
         }
         Ok(available_set)
     }
+
+    ///
+    /// Analyse how parallel the processes of the ordered graph can run. Each process is weighted
+    /// by the total duration of its operations if the trace has the durations, and by the number
+    /// of its operations otherwise. Unlike available_set, the graph is not changed.
+    ///
+    pub fn parallelism(&self) -> Result<Parallelism, Error> {
+        let nodes: Vec<_> = self
+            .dag
+            .nodes()
+            .filter(|node| node.data().ty() == "process")
+            .cloned()
+            .collect();
+
+        let weighting = match nodes.iter().any(|node| {
+            node.data()
+                .process()
+                .map(|p| p.ops().iter().any(|op| op.duration().is_some()))
+                .unwrap_or(false)
+        }) {
+            true => Weighting::Duration,
+            false => Weighting::Ops,
+        };
+
+        let mut pids = vec![];
+        let mut weights = vec![];
+        for node in nodes.iter() {
+            let process = node.data().process()?.clone();
+            weights.push(match weighting {
+                Weighting::Duration => process
+                    .ops()
+                    .iter()
+                    .filter_map(|op| op.duration())
+                    .sum::<Duration>()
+                    .as_secs_f64(),
+                Weighting::Ops => process
+                    .ops()
+                    .iter()
+                    .filter(|op| !op.accesses().is_empty())
+                    .count() as f64,
+            });
+            pids.push(process.pid());
+        }
+        let index: HashMap<usize, usize> = pids.iter().enumerate().map(|(i, p)| (*p, i)).collect();
+
+        let mut successors: Vec<Vec<usize>> = vec![vec![]; nodes.len()];
+        let mut in_degrees = vec![0; nodes.len()];
+        for (i, node) in nodes.iter().enumerate() {
+            for neighbor in node.outgoing_neighbors().iter() {
+                if let Ok(process) = neighbor.data().process() {
+                    let j = index[&process.pid()];
+                    if !successors[i].contains(&j) {
+                        successors[i].push(j);
+                        in_degrees[j] += 1;
+                    }
+                }
+            }
+        }
+
+        // the levels are the available sets, and the longest path to each process is found on
+        // the way through them
+        let mut levels: Vec<Vec<usize>> = vec![];
+        let mut distances = weights.clone();
+        let mut predecessors: Vec<Option<usize>> = vec![None; nodes.len()];
+        let mut level: Vec<usize> = (0..nodes.len()).filter(|i| in_degrees[*i] == 0).collect();
+        let mut visited = 0;
+        while !level.is_empty() {
+            level.sort_by_key(|i| pids[*i]);
+            visited += level.len();
+
+            let mut next = vec![];
+            for i in level.iter() {
+                for j in successors[*i].iter() {
+                    if distances[*i] + weights[*j] > distances[*j] {
+                        distances[*j] = distances[*i] + weights[*j];
+                        predecessors[*j] = Some(*i);
+                    }
+                    in_degrees[*j] -= 1;
+                    if in_degrees[*j] == 0 {
+                        next.push(*j);
+                    }
+                }
+            }
+
+            levels.push(level.iter().map(|i| pids[*i]).collect());
+            level = next;
+        }
+        if visited < nodes.len() {
+            return Err(Error::InvalidType(format!(
+                "dependency graph with a cycle between {} processes",
+                nodes.len() - visited
+            )));
+        }
+
+        // the critical path ends at the heaviest of the processes nothing depends on
+        let mut critical_path = vec![];
+        let mut last = (0..nodes.len())
+            .filter(|i| successors[*i].is_empty())
+            .max_by(|i, j| {
+                distances[*i]
+                    .partial_cmp(&distances[*j])
+                    .unwrap_or(std::cmp::Ordering::Equal)
+                    .then(pids[*j].cmp(&pids[*i]))
+            });
+        let length = last.map(|i| distances[i]).unwrap_or(0.0);
+        while let Some(i) = last {
+            critical_path.push(pids[i]);
+            last = predecessors[i];
+        }
+        critical_path.reverse();
+
+        Ok(Parallelism {
+            weighting,
+            levels,
+            critical_path,
+            length,
+            total: weights.iter().sum(),
+        })
+    }
+}
+
+///
+/// What the processes are weighted by in the parallelism analysis
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Weighting {
+    Ops,      // the number of operations that access files
+    Duration, // the total duration of the operations, in seconds
+}
+
+///
+/// How parallel the processes of a dependency graph can run
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct Parallelism {
+    weighting: Weighting,
+    levels: Vec<Vec<usize>>, // the pids of the processes of each available set
+    critical_path: Vec<usize>, // the pids of the processes on the heaviest path through the graph
+    length: f64,             // the weight of the critical path
+    total: f64,              // the weight of all the processes
+}
+
+impl Parallelism {
+    pub fn weighting(&self) -> Weighting {
+        self.weighting
+    }
+
+    pub fn levels(&self) -> &Vec<Vec<usize>> {
+        &self.levels
+    }
+
+    ///
+    /// The number of processes of each level
+    ///
+    pub fn widths(&self) -> Vec<usize> {
+        self.levels.iter().map(|level| level.len()).collect()
+    }
+
+    ///
+    /// The most processes that can run at the same time
+    ///
+    pub fn max_width(&self) -> usize {
+        self.widths().into_iter().max().unwrap_or(0)
+    }
+
+    pub fn critical_path(&self) -> &Vec<usize> {
+        &self.critical_path
+    }
+
+    pub fn critical_path_length(&self) -> f64 {
+        self.length
+    }
+
+    pub fn total(&self) -> f64 {
+        self.total
+    }
+
+    ///
+    /// The theoretical speedup of running the processes in parallel over running them one by
+    /// one, which is the total weight over the weight of the critical path
+    ///
+    pub fn max_speedup(&self) -> f64 {
+        if self.length <= 0.0 {
+            return 1.0;
+        }
+        self.total / self.length
+    }
+}
+
+impl Display for Parallelism {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let weight = |weight: f64| match self.weighting {
+            Weighting::Ops => format!("{} operations", weight),
+            Weighting::Duration => format!("{:.6}s", weight),
+        };
+
+        writeln!(f, "processes: {}", self.levels.iter().flatten().count())?;
+        writeln!(f, "total: {}", weight(self.total))?;
+        writeln!(
+            f,
+            "critical path: {} ({})",
+            self.critical_path
+                .iter()
+                .map(|pid| pid.to_string())
+                .collect::<Vec<_>>()
+                .join(" -> "),
+            weight(self.length)
+        )?;
+        writeln!(f, "max speedup: {:.2}", self.max_speedup())?;
+        writeln!(f, "max width: {}", self.max_width())?;
+        for (i, level) in self.levels.iter().enumerate() {
+            writeln!(
+                f,
+                "level {}: {} processes: {}",
+                i + 1,
+                level.len(),
+                level
+                    .iter()
+                    .map(|pid| pid.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )?;
+        }
+
+        Ok(())
+    }
 }
 
 impl Display for DependencyGraph {
@@ -255,20 +489,21 @@ impl Display for DependencyGraph {
 
 #[cfg(test)]
 mod test {
-    use crate::deps::DependencyGraph;
-    use crate::file::File;
+    use crate::deps::{DependencyGraph, Weighting};
+    use crate::file::{File, FileKind};
     use crate::op::Operation;
     use crate::process::Process;
     use std::sync::Arc;
+    use std::time::Duration;
 
     #[test]
     fn dependencies() -> Result<(), Box<dyn std::error::Error>> {
         // the files
-        let f1 = Arc::new(File::new("f1".to_string()));
-        let f2 = Arc::new(File::new("f2".to_string()));
-        let d1 = Arc::new(File::new("d1".to_string()));
-        let f3 = Arc::new(File::new("f3".to_string()));
-        let f4 = Arc::new(File::new("f4".to_string()));
+        let f1 = Arc::new(File::new("f1"));
+        let f2 = Arc::new(File::new("f2"));
+        let d1 = Arc::new(File::new("d1"));
+        let f3 = Arc::new(File::new("f3"));
+        let f4 = Arc::new(File::new("f4"));
 
         // the operations
         let read_f1_op = Operation::read(f1.clone(), 1, 1);
@@ -301,10 +536,7 @@ mod test {
         p3.add_op(read_f4_op.clone());
         p3.add_op(mknod_f2_op2.clone());
 
-        let mut processes = vec![];
-        processes.push(p1);
-        processes.push(p2);
-        processes.push(p3);
+        let processes = vec![p1, p2, p3];
 
         let dep_graph = DependencyGraph::new(processes)?;
 
@@ -340,4 +572,131 @@ mod test {
 
         Ok(())
     }
+
+    #[test]
+    fn shared_map_dependencies() -> Result<(), Box<dyn std::error::Error>> {
+        let f1 = Arc::new(File::new("f1"));
+        let f2 = Arc::new(File::new("f2"));
+
+        // p1 writes f1 through a shared mapping, and only reads f2 through a private mapping
+        let mut p1 = Process::new(1);
+        p1.add_op(Operation::map(
+            f1.clone(),
+            0,
+            4096,
+            "PROT_READ|PROT_WRITE".to_string(),
+            "MAP_SHARED".to_string(),
+        ));
+        p1.add_op(Operation::map(
+            f2.clone(),
+            0,
+            4096,
+            "PROT_READ|PROT_WRITE".to_string(),
+            "MAP_PRIVATE".to_string(),
+        ));
+
+        // p2 reads both files
+        let mut p2 = Process::new(2);
+        p2.add_op(Operation::read(f1.clone(), 1, 1));
+        p2.add_op(Operation::read(f2.clone(), 1, 1));
+
+        let mut dep_graph_ordered = DependencyGraph::new(vec![p1, p2])?.order()?;
+
+        // after order, p2 depends on p1 through f1:
+        //  p1 --> p2
+
+        let first = dep_graph_ordered.available_set()?;
+        assert_eq!(first.len(), 1);
+        assert_eq!(first[0].pid(), 1);
+        assert_eq!(dep_graph_ordered.available_set()?.len(), 1);
+        assert_eq!(dep_graph_ordered.available_set()?.len(), 0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn pipe_dependencies() -> Result<(), Box<dyn std::error::Error>> {
+        let pipe = Arc::new(File::with_kind("pipe:[1]", FileKind::Pipe));
+
+        // p1 produces into the pipe and p2 consumes from it
+        let mut p1 = Process::new(1);
+        p1.add_op(Operation::write(
+            pipe.clone(),
+            "something".to_string(),
+            9,
+            0,
+        ));
+        let mut p2 = Process::new(2);
+        p2.add_op(Operation::read(pipe.clone(), 9, 0));
+
+        let mut dep_graph_ordered = DependencyGraph::new(vec![p1, p2])?.order()?;
+
+        // after order, we should have a dag including process nodes as follows:
+        //  p1 --> p2 [pipe]
+
+        assert_eq!(dep_graph_ordered.dag.edge_count(), 1);
+        assert!(dep_graph_ordered
+            .dag
+            .edges()
+            .all(|edge| edge.label() == "pipe"));
+
+        assert_eq!(dep_graph_ordered.available_set()?[0].pid(), 1);
+        assert_eq!(dep_graph_ordered.available_set()?[0].pid(), 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn parallelism() -> Result<(), Box<dyn std::error::Error>> {
+        let f1 = Arc::new(File::new("f1"));
+        let f2 = Arc::new(File::new("f2"));
+
+        // p1 and p2 write f1 and f2, and p3 reads both of them. The operations that do not
+        // access files do not count
+        let mut p1 = Process::new(1);
+        p1.add_op(Operation::no_op());
+        p1.add_op(Operation::stat(f1.clone()));
+        p1.add_op(Operation::stat(f1.clone()));
+        p1.add_op(Operation::write(f1.clone(), "".to_string(), 1, 0));
+        let mut p2 = Process::new(2);
+        p2.add_op(Operation::write(f2.clone(), "".to_string(), 1, 0));
+        let mut p3 = Process::new(3);
+        p3.add_op(Operation::read(f1.clone(), 1, 0));
+        p3.add_op(Operation::read(f2.clone(), 1, 0));
+
+        let dep_graph = DependencyGraph::new(vec![p1, p2, p3.clone()])?.order()?;
+        let parallelism = dep_graph.parallelism()?;
+
+        assert_eq!(parallelism.weighting(), Weighting::Ops);
+        assert_eq!(parallelism.levels(), &vec![vec![1, 2], vec![3]]);
+        assert_eq!(parallelism.widths(), vec![2, 1]);
+        assert_eq!(parallelism.max_width(), 2);
+        assert_eq!(parallelism.critical_path(), &vec![1, 3]);
+        assert_eq!(parallelism.critical_path_length(), 5.0);
+        assert_eq!(parallelism.max_speedup(), 6.0 / 5.0);
+
+        // the graph is not changed
+        assert_eq!(dep_graph.dag.node_count(), 3);
+
+        // with the durations, p2 is on the critical path
+        let mut p1 = Process::new(1);
+        p1.add_op(
+            Operation::write(f1.clone(), "".to_string(), 1, 0)
+                .with_timing(None, Some(Duration::from_millis(1))),
+        );
+        let mut p2 = Process::new(2);
+        p2.add_op(
+            Operation::write(f2.clone(), "".to_string(), 1, 0)
+                .with_timing(None, Some(Duration::from_millis(3))),
+        );
+
+        let parallelism = DependencyGraph::new(vec![p1, p2, p3])?
+            .order()?
+            .parallelism()?;
+        assert_eq!(parallelism.weighting(), Weighting::Duration);
+        assert_eq!(parallelism.critical_path(), &vec![2, 3]);
+        assert!((parallelism.critical_path_length() - 0.003).abs() < 1e-9);
+
+        Ok(())
+    }
 }
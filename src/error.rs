@@ -11,6 +11,7 @@ pub enum Error {
     InvalidType(String),
     PoisonError(String),
     NoneValue(String),
+    Replay(String),
 }
 
 impl std::error::Error for Error {}
@@ -31,6 +32,7 @@ impl fmt::Display for Error {
                 write!(f, "could not acquire a lock oh shared object: {}", detail)
             }
             &Error::NoneValue(ref detail) => write!(f, "value is none: {}", detail),
+            Error::Replay(detail) => write!(f, "could not replay {}", detail),
         }
     }
 }
@@ -0,0 +1,591 @@
+use crate::deps::DependencyGraph;
+use crate::error::Error;
+use crate::file::File;
+use crate::op::{Operation, OperationType};
+use crate::parser::FileType;
+use crate::process::Process;
+use crate::snapshot::Snapshot;
+use std::str::FromStr;
+
+///
+/// The language of the exported replay scripts, for the systems the replay cannot run on
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Script {
+    Shell, // a POSIX shell script
+    C,     // a single C source file, for POSIX systems
+}
+
+impl Script {
+    ///
+    /// Generate a program that creates the snapshot under a root directory given as its first
+    /// argument, and runs the operations of each process in the order of the dependency graph.
+    /// The processes of each available set run in parallel, as background jobs in the shell
+    /// script and as forked children in the C program, and the next set starts when all of them
+    /// are done. As in the replay, only the operations on regular files are exported, and the
+    /// failed operations are reported on the standard error, but the written bytes are always
+    /// zeros, whatever the payload generator of the replay.
+    ///
+    pub fn export(
+        &self,
+        snapshot: &Snapshot,
+        dep_graph: &mut DependencyGraph,
+    ) -> Result<String, Box<dyn std::error::Error>> {
+        let mut waves = vec![];
+        let mut available_set = dep_graph.available_set()?;
+        while !available_set.is_empty() {
+            waves.push(available_set);
+            available_set = dep_graph.available_set()?;
+        }
+
+        match self {
+            Script::Shell => Ok(shell(snapshot, &waves)),
+            Script::C => Ok(c(snapshot, &waves)),
+        }
+    }
+}
+
+impl FromStr for Script {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "sh" | "shell" => Ok(Script::Shell),
+            "c" => Ok(Script::C),
+            _ => Err(Error::ParseError(format!("script {}, expected sh or c", s))),
+        }
+    }
+}
+
+const SHELL_HELPERS: &str = r#"ROOT="${1:-.}"
+
+fail() {
+    echo "process $1: could not $2" >&2
+}
+
+# the offsets and lengths are in bytes, but dd counts whole blocks, so the block size is the
+# greatest common divisor of them, halved while it is even and above 1 MiB
+block() {
+    bs=0
+    for n in "$@"; do
+        a=$bs
+        b=$n
+        while [ "$b" -ne 0 ]; do
+            t=$((a % b))
+            a=$b
+            b=$t
+        done
+        bs=$a
+    done
+    [ "$bs" -eq 0 ] && bs=1
+    while [ "$bs" -gt 1048576 ] && [ $((bs % 2)) -eq 0 ]; do
+        bs=$((bs / 2))
+    done
+}
+
+read_at() {
+    block "$2" "$3"
+    dd if="$1" of=/dev/null bs="$bs" skip=$(($2 / bs)) count=$(($3 / bs)) 2>/dev/null
+}
+
+write_at() {
+    block "$2" "$3"
+    [ -f "$1" ] && dd if=/dev/zero of="$1" bs="$bs" seek=$(($2 / bs)) count=$(($3 / bs)) \
+        conv=notrunc 2>/dev/null
+}
+
+copy_at() {
+    block "$2" "$4" "$5"
+    [ -f "$3" ] && dd if="$1" of="$3" bs="$bs" skip=$(($2 / bs)) seek=$(($4 / bs)) \
+        count=$(($5 / bs)) conv=notrunc 2>/dev/null
+}
+
+remove() {
+    if [ -d "$1" ] && [ ! -h "$1" ]; then
+        rmdir "$1" 2>/dev/null
+    else
+        { [ -e "$1" ] || [ -h "$1" ]; } && rm -f "$1"
+    fi
+}
+
+sized() {
+    : > "$1" && dd if=/dev/zero of="$1" bs=1 seek="$2" count=0 2>/dev/null
+}
+"#;
+
+// a POSIX shell script with a function per process
+fn shell(snapshot: &Snapshot, waves: &[Vec<Process>]) -> String {
+    let target = |path: &str| format!("\"$ROOT\"{}", shell_quote(path));
+
+    let mut script = String::from("#!/bin/sh\n# replays a trace parsed by strace-parser\n\n");
+    script.push_str(SHELL_HELPERS);
+
+    script.push_str("\n# the files and directories that existed before the trace started\n");
+    script.push_str("mkdir -p \"$ROOT\"\n");
+    for file in snapshot.files() {
+        match file {
+            FileType::Dir(path, _) => script.push_str(&format!("mkdir -p {}\n", target(path))),
+            FileType::File(path, size) => {
+                script.push_str(&format!("sized {} {}\n", target(path), size))
+            }
+            FileType::Other => {}
+        }
+    }
+
+    for process in waves.iter().flatten() {
+        script.push_str(&format!("\nprocess_{}() {{\n    :\n", process.pid()));
+        for op in replayable(process) {
+            let command = match op.op_type() {
+                OperationType::Read(file, offset, len)
+                | OperationType::Map(file, offset, len, _, _) => format!(
+                    "read_at {} {} {}",
+                    target(path(file)),
+                    (*offset).max(0),
+                    len
+                ),
+                OperationType::Write(file, offset, len, _) => format!(
+                    "write_at {} {} {}",
+                    target(path(file)),
+                    (*offset).max(0),
+                    len
+                ),
+                OperationType::Mkdir(file, _) => {
+                    format!("mkdir {} 2>/dev/null", target(path(file)))
+                }
+                OperationType::Mknod(file) => format!(": >> {} 2>/dev/null", target(path(file))),
+                OperationType::Remove(file) => format!("remove {}", target(path(file))),
+                OperationType::Rename(file, to) => {
+                    format!("mv -f {} {} 2>/dev/null", target(path(file)), target(to))
+                }
+                OperationType::OpenAt(file, _) => {
+                    format!(": < {} 2>/dev/null", target(path(file)))
+                }
+                OperationType::Truncate(file) => {
+                    format!("[ -f {0} ] && : > {0}", target(path(file)))
+                }
+                OperationType::Stat(file)
+                | OperationType::Fstat(file)
+                | OperationType::Statx(file)
+                | OperationType::StatFS(file)
+                | OperationType::Fstatat(file) => format!("[ -e {} ]", target(path(file))),
+                OperationType::Sync(file) => format!("[ -f {} ] && sync", target(path(file))),
+                OperationType::Copy(from, from_offset, to, to_offset, len) => format!(
+                    "copy_at {} {} {} {} {}",
+                    target(path(from)),
+                    (*from_offset).max(0),
+                    target(path(to)),
+                    (*to_offset).max(0),
+                    len
+                ),
+                _ => continue,
+            };
+
+            script.push_str(&format!(
+                "    {} || fail {} {}\n",
+                command,
+                process.pid(),
+                shell_quote(&description(op))
+            ));
+        }
+        script.push_str("}\n");
+    }
+
+    for (index, wave) in waves.iter().enumerate() {
+        script.push_str(&format!("\n# available set {}\n", index + 1));
+        for process in wave {
+            script.push_str(&format!("process_{} &\n", process.pid()));
+        }
+        script.push_str("wait\n");
+    }
+
+    script
+}
+
+const C_HELPERS: &str = r#"#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <sys/stat.h>
+#include <sys/types.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+static const char *root = ".";
+
+/* inline, so the compilers do not warn about the helpers a trace does not use */
+
+static inline char *target(const char *path) {
+    static char buf[8192];
+    snprintf(buf, sizeof(buf), "%s%s", root, path);
+    return buf;
+}
+
+static inline void fail(int pid, const char *op) {
+    fprintf(stderr, "process %d: could not %s: %s\n", pid, op, strerror(errno));
+}
+
+static inline int read_at(const char *path, off_t offset, size_t len) {
+    int fd = open(target(path), O_RDONLY);
+    if (fd < 0) return -1;
+    char *buf = malloc(len > 0 ? len : 1);
+    ssize_t read = pread(fd, buf, len, offset);
+    free(buf);
+    close(fd);
+    return read < 0 ? -1 : 0;
+}
+
+static inline int write_at(const char *path, off_t offset, size_t len) {
+    int fd = open(target(path), O_WRONLY);
+    if (fd < 0) return -1;
+    char *buf = calloc(len > 0 ? len : 1, 1);
+    size_t written = 0;
+    while (written < len) {
+        ssize_t n = pwrite(fd, buf + written, len - written, offset + written);
+        if (n <= 0) break;
+        written += n;
+    }
+    free(buf);
+    close(fd);
+    return written < len ? -1 : 0;
+}
+
+static inline int copy_at(const char *from, off_t from_offset, const char *to, off_t to_offset,
+                          size_t len) {
+    int source = open(target(from), O_RDONLY);
+    if (source < 0) return -1;
+    char *buf = malloc(len > 0 ? len : 1);
+    ssize_t read = pread(source, buf, len, from_offset);
+    close(source);
+    int destination = read < 0 ? -1 : open(target(to), O_WRONLY);
+    ssize_t written = destination < 0 ? -1 : pwrite(destination, buf, read, to_offset);
+    if (destination >= 0) close(destination);
+    free(buf);
+    return written < read ? -1 : 0;
+}
+
+static inline int create(const char *path) {
+    int fd = open(target(path), O_WRONLY | O_CREAT, 0666);
+    if (fd < 0) return -1;
+    return close(fd);
+}
+
+static inline int open_file(const char *path) {
+    int fd = open(target(path), O_RDONLY);
+    if (fd < 0) return -1;
+    return close(fd);
+}
+
+static inline int remove_path(const char *path) {
+    struct stat st;
+    if (lstat(target(path), &st) < 0) return -1;
+    return S_ISDIR(st.st_mode) ? rmdir(target(path)) : unlink(target(path));
+}
+
+static inline int rename_path(const char *from, const char *to) {
+    char source[8192];
+    snprintf(source, sizeof(source), "%s", target(from));
+    return rename(source, target(to));
+}
+
+static inline int stat_path(const char *path) {
+    struct stat st;
+    return stat(target(path), &st);
+}
+
+static inline int sync_file(const char *path) {
+    int fd = open(target(path), O_WRONLY);
+    if (fd < 0) return -1;
+    int synced = fsync(fd);
+    close(fd);
+    return synced;
+}
+
+static inline void sized(const char *path, off_t size) {
+    int fd = open(target(path), O_WRONLY | O_CREAT | O_TRUNC, 0666);
+    if (fd < 0 || ftruncate(fd, size) < 0) perror(target(path));
+    if (fd >= 0) close(fd);
+}
+
+static inline void spawn(void (*process)(void)) {
+    pid_t child = fork();
+    if (child < 0) {
+        perror("fork");
+        exit(1);
+    }
+    if (child == 0) {
+        process();
+        _exit(0);
+    }
+}
+
+static inline void wait_all(void) {
+    while (wait(NULL) > 0) {
+    }
+}
+"#;
+
+// a C program with a function per process
+fn c(snapshot: &Snapshot, waves: &[Vec<Process>]) -> String {
+    let mut program = String::from("/* replays a trace parsed by strace-parser */\n\n");
+    program.push_str(C_HELPERS);
+
+    for process in waves.iter().flatten() {
+        program.push_str(&format!(
+            "\nstatic void process_{}(void) {{\n",
+            process.pid()
+        ));
+        for op in replayable(process) {
+            let call = match op.op_type() {
+                OperationType::Read(file, offset, len)
+                | OperationType::Map(file, offset, len, _, _) => format!(
+                    "read_at({}, {}, {})",
+                    c_quote(path(file)),
+                    (*offset).max(0),
+                    len
+                ),
+                OperationType::Write(file, offset, len, _) => format!(
+                    "write_at({}, {}, {})",
+                    c_quote(path(file)),
+                    (*offset).max(0),
+                    len
+                ),
+                OperationType::Mkdir(file, _) => {
+                    format!("mkdir(target({}), 0777)", c_quote(path(file)))
+                }
+                OperationType::Mknod(file) => format!("create({})", c_quote(path(file))),
+                OperationType::Remove(file) => format!("remove_path({})", c_quote(path(file))),
+                OperationType::Rename(file, to) => {
+                    format!("rename_path({}, {})", c_quote(path(file)), c_quote(to))
+                }
+                OperationType::OpenAt(file, _) => format!("open_file({})", c_quote(path(file))),
+                OperationType::Truncate(file) => {
+                    format!("truncate(target({}), 0)", c_quote(path(file)))
+                }
+                OperationType::Stat(file)
+                | OperationType::Fstat(file)
+                | OperationType::Statx(file)
+                | OperationType::StatFS(file)
+                | OperationType::Fstatat(file) => format!("stat_path({})", c_quote(path(file))),
+                OperationType::Sync(file) => format!("sync_file({})", c_quote(path(file))),
+                OperationType::Copy(from, from_offset, to, to_offset, len) => format!(
+                    "copy_at({}, {}, {}, {}, {})",
+                    c_quote(path(from)),
+                    (*from_offset).max(0),
+                    c_quote(path(to)),
+                    (*to_offset).max(0),
+                    len
+                ),
+                _ => continue,
+            };
+
+            program.push_str(&format!(
+                "    if ({} < 0) fail({}, {});\n",
+                call,
+                process.pid(),
+                c_quote(&description(op))
+            ));
+        }
+        program.push_str("}\n");
+    }
+
+    program.push_str("\nint main(int argc, char **argv) {\n");
+    program.push_str("    if (argc > 1) root = argv[1];\n");
+    program
+        .push_str("\n    /* the files and directories that existed before the trace started */\n");
+    program.push_str("    mkdir(root, 0777);\n");
+    for file in snapshot.files() {
+        match file {
+            FileType::Dir(path, _) => {
+                program.push_str(&format!("    mkdir(target({}), 0777);\n", c_quote(path)))
+            }
+            FileType::File(path, size) => {
+                program.push_str(&format!("    sized({}, {});\n", c_quote(path), size))
+            }
+            FileType::Other => {}
+        }
+    }
+
+    for (index, wave) in waves.iter().enumerate() {
+        program.push_str(&format!("\n    /* available set {} */\n", index + 1));
+        for process in wave {
+            program.push_str(&format!("    spawn(process_{});\n", process.pid()));
+        }
+        program.push_str("    wait_all();\n");
+    }
+    program.push_str("    return 0;\n}\n");
+
+    program
+}
+
+// the operations of the process the replay would execute
+fn replayable(process: &Process) -> impl Iterator<Item = &Operation> {
+    process.ops().iter().filter(|op| {
+        let accesses = op.accesses();
+        !accesses.is_empty() && accesses.iter().all(|(file, _)| file.is_regular())
+    })
+}
+
+fn path(file: &File) -> &str {
+    file.path().unwrap_or("")
+}
+
+// the operation and its files, as shown in the failure messages
+fn description(op: &Operation) -> String {
+    let paths: Vec<String> = op
+        .accesses()
+        .iter()
+        .map(|(file, _)| path(file).to_string())
+        .collect();
+    match paths.is_empty() {
+        true => op.name(),
+        false => format!("{} {}", op.name(), paths.join(" to ")),
+    }
+}
+
+fn shell_quote(s: &str) -> String {
+    format!("'{}'", s.replace("'", "'\\''"))
+}
+
+fn c_quote(s: &str) -> String {
+    let mut quoted = String::from("\"");
+    for byte in s.bytes() {
+        match byte {
+            b'"' => quoted.push_str("\\\""),
+            b'\\' => quoted.push_str("\\\\"),
+            // octal escapes of three digits, so the following digits are not part of them
+            0x20..=0x7e => quoted.push(byte as char),
+            _ => quoted.push_str(&format!("\\{:03o}", byte)),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+#[cfg(test)]
+mod test {
+    use crate::deps::DependencyGraph;
+    use crate::export::{c_quote, shell_quote, Script};
+    use crate::file::File;
+    use crate::op::Operation;
+    use crate::parser::FileType;
+    use crate::process::Process;
+    use crate::snapshot::Snapshot;
+    use std::sync::Arc;
+
+    fn dep_graph() -> Result<DependencyGraph, Box<dyn std::error::Error>> {
+        let input = Arc::new(File::new("/data/input"));
+        let output = Arc::new(File::new("/data/it's"));
+
+        let mut writer = Process::new(1);
+        writer.add_op(Operation::read(input.clone(), 4, 0));
+        writer.add_op(Operation::mknod(output.clone()));
+        writer.add_op(Operation::write(output.clone(), "\"ab\"".to_string(), 5, 0));
+        writer.add_op(Operation::copy(input.clone(), 0, output.clone(), 1, 4));
+
+        let mut reader = Process::new(2);
+        reader.add_op(Operation::read(output.clone(), 5, 0));
+        reader.add_op(Operation::read(Arc::new(File::new("/data/missing")), 5, 0));
+
+        Ok(DependencyGraph::new(vec![writer, reader])?.order()?)
+    }
+
+    fn snapshot() -> Snapshot {
+        Snapshot::new(vec![FileType::File("/data/input".to_string(), 4)])
+    }
+
+    #[test]
+    fn quote() {
+        assert_eq!(shell_quote("/a/it's"), "'/a/it'\\''s'");
+        assert_eq!(c_quote("/a/\"b\"\\\n1"), "\"/a/\\\"b\\\"\\\\\\0121\"");
+    }
+
+    #[test]
+    fn shell() -> Result<(), Box<dyn std::error::Error>> {
+        let script = Script::Shell.export(&snapshot(), &mut dep_graph()?)?;
+
+        assert!(script.starts_with("#!/bin/sh\n"));
+        assert!(script.contains("sized \"$ROOT\"'/data/input' 4\n"));
+        assert!(script.contains(
+            "    write_at \"$ROOT\"'/data/it'\\''s' 0 5 || fail 1 'Write /data/it'\\''s'\n"
+        ));
+        assert!(script.contains(
+            "    copy_at \"$ROOT\"'/data/input' 0 \"$ROOT\"'/data/it'\\''s' 1 4 || fail 1 'Copy /data/input to /data/it'\\''s'\n"
+        ));
+        assert!(script.contains("\n# available set 1\nprocess_1 &\nwait\n"));
+        assert!(script.contains("\n# available set 2\nprocess_2 &\nwait\n"));
+
+        // run the script, if there is a shell
+        let root =
+            std::env::temp_dir().join(format!("strace-parser-export-sh-{}", std::process::id()));
+        let output = match std::process::Command::new("sh")
+            .arg("-c")
+            .arg(&script)
+            .arg("sh")
+            .arg(&root)
+            .output()
+        {
+            Ok(output) => output,
+            Err(_) => return Ok(()),
+        };
+
+        assert_eq!(std::fs::metadata(root.join("data/input"))?.len(), 4);
+        assert_eq!(std::fs::read(root.join("data/it's"))?, vec![0; 5]);
+        assert_eq!(
+            String::from_utf8(output.stderr)?,
+            "process 2: could not Read /data/missing\n"
+        );
+
+        std::fs::remove_dir_all(&root)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn c() -> Result<(), Box<dyn std::error::Error>> {
+        let program = Script::C.export(&snapshot(), &mut dep_graph()?)?;
+
+        assert!(program.contains("    sized(\"/data/input\", 4);\n"));
+        assert!(program.contains(
+            "    if (write_at(\"/data/it's\", 0, 5) < 0) fail(1, \"Write /data/it's\");\n"
+        ));
+        assert!(program.contains("fail(1, \"Copy /data/input to /data/it's\");\n"));
+        assert!(program
+            .contains("\n    /* available set 1 */\n    spawn(process_1);\n    wait_all();\n"));
+        assert!(program.ends_with("    return 0;\n}\n"));
+
+        // compile the program, if there is a compiler
+        let source =
+            std::env::temp_dir().join(format!("strace-parser-export-{}.c", std::process::id()));
+        let binary = source.with_extension("out");
+        std::fs::write(&source, &program)?;
+        let output = std::process::Command::new("cc")
+            .args(["-Wall", "-Werror", "-o"])
+            .arg(&binary)
+            .arg(&source)
+            .output();
+        std::fs::remove_file(&source)?;
+        let output = match output {
+            Ok(output) => output,
+            Err(_) => return Ok(()),
+        };
+
+        assert!(
+            output.status.success(),
+            "{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        std::fs::remove_file(&binary)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn parse() -> Result<(), Box<dyn std::error::Error>> {
+        assert_eq!("sh".parse::<Script>()?, Script::Shell);
+        assert_eq!("c".parse::<Script>()?, Script::C);
+        assert!("python".parse::<Script>().is_err());
+
+        Ok(())
+    }
+}
@@ -1,16 +1,115 @@
 use crate::error::Error;
 use std::fmt::Formatter;
+use std::hash::{Hash, Hasher};
 use std::path::{Path, PathBuf};
 
-#[derive(Debug, PartialEq, Hash, Eq)]
+///
+/// The kind of object a file descriptor refers to
+///
+#[derive(Debug, PartialEq, Hash, Eq, Clone, Copy)]
+pub enum FileKind {
+    Regular, // a file or directory in the file system
+    Pipe,
+    Socket,
+    EventFd,
+    MemFd,
+}
+
+impl FileKind {
+    ///
+    /// Find the kind of the object behind a path, which is either a file system path, or
+    /// the name strace gives to a non-file descriptor, like pipe:[1234] or socket:[1234]
+    ///
+    pub fn from_path(path: &str) -> Self {
+        if path.starts_with("pipe:[") {
+            FileKind::Pipe
+        } else if path.starts_with("anon_inode:[eventfd]") || path.starts_with("eventfd:[") {
+            FileKind::EventFd
+        } else if path.starts_with("/memfd:") || path.starts_with("memfd:") {
+            FileKind::MemFd
+        } else if path.starts_with("/") {
+            FileKind::Regular
+        } else if path.starts_with("socket:[")
+            || path
+                .split(":[")
+                .next()
+                .map(|protocol| {
+                    // with -yy, sockets are decorated with their protocol, like TCP:[...]
+                    path.contains(":[")
+                        && !protocol.is_empty()
+                        && protocol
+                            .chars()
+                            .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '-')
+                })
+                .unwrap_or(false)
+        {
+            FileKind::Socket
+        } else {
+            FileKind::Regular
+        }
+    }
+}
+
+///
+/// A file accessed by processes. Two files are the same if they have the same canonical path
+/// and generation, even if they were accessed through different paths, like a symlink and its
+/// target, or the old and new paths of a renamed file
+///
+#[derive(Debug)]
 pub struct File {
-    path: PathBuf,
+    path: PathBuf,      // the path the file was accessed through
+    canonical: PathBuf, // the path that identifies the file
+    generation: usize, // the number of files that had the canonical path before, and were removed or renamed
+    kind: FileKind,
 }
 
 impl File {
     pub fn new<P: AsRef<Path>>(path: P) -> Self {
+        Self::with_kind(path, FileKind::Regular)
+    }
+
+    pub fn with_kind<P: AsRef<Path>>(path: P, kind: FileKind) -> Self {
         Self {
             path: path.as_ref().to_path_buf(),
+            canonical: path.as_ref().to_path_buf(),
+            generation: 0,
+            kind,
+        }
+    }
+
+    ///
+    /// The same file accessed through another path
+    ///
+    pub fn with_path<P: AsRef<Path>>(&self, path: P) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+            canonical: self.canonical.clone(),
+            generation: self.generation,
+            kind: self.kind,
+        }
+    }
+
+    ///
+    /// A file that reuses the path of the removed or renamed files before it
+    ///
+    pub fn with_generation(&self, generation: usize) -> Self {
+        Self {
+            path: self.path.clone(),
+            canonical: self.canonical.clone(),
+            generation,
+            kind: self.kind,
+        }
+    }
+
+    ///
+    /// The same file accessed through the same path, but identified by another canonical path
+    ///
+    pub fn with_canonical<P: AsRef<Path>>(&self, canonical: P) -> Self {
+        Self {
+            path: self.path.clone(),
+            canonical: canonical.as_ref().to_path_buf(),
+            generation: self.generation,
+            kind: self.kind,
         }
     }
 
@@ -22,6 +121,49 @@ impl File {
                 "failed to convert PathBuf to String".to_string(),
             )))
     }
+
+    pub fn canonical(&self) -> Result<&str, Box<dyn std::error::Error>> {
+        self.canonical
+            .as_os_str()
+            .to_str()
+            .ok_or(Box::new(Error::ParseError(
+                "failed to convert PathBuf to String".to_string(),
+            )))
+    }
+
+    pub fn generation(&self) -> usize {
+        self.generation
+    }
+
+    pub fn kind(&self) -> FileKind {
+        self.kind
+    }
+
+    ///
+    /// Whether the file lives in the file system, rather than being a pipe, socket, or any
+    /// other descriptor-only object
+    ///
+    pub fn is_regular(&self) -> bool {
+        self.kind == FileKind::Regular
+    }
+}
+
+impl PartialEq for File {
+    fn eq(&self, other: &Self) -> bool {
+        self.canonical == other.canonical
+            && self.generation == other.generation
+            && self.kind == other.kind
+    }
+}
+
+impl Eq for File {}
+
+impl Hash for File {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.canonical.hash(state);
+        self.generation.hash(state);
+        self.kind.hash(state);
+    }
 }
 
 impl std::fmt::Display for File {
@@ -29,3 +171,62 @@ impl std::fmt::Display for File {
         write!(f, "file({})", self.path().unwrap_or(""))
     }
 }
+
+#[cfg(test)]
+mod test {
+    use crate::file::{File, FileKind};
+
+    #[test]
+    fn file_kind() {
+        assert_eq!(FileKind::from_path("/tmp/foo"), FileKind::Regular);
+        assert_eq!(FileKind::from_path("pipe:[1234]"), FileKind::Pipe);
+        assert_eq!(FileKind::from_path("socket:[1234]"), FileKind::Socket);
+        assert_eq!(
+            FileKind::from_path("TCP:[1.2.3.4:5->6.7.8.9:10]"),
+            FileKind::Socket
+        );
+        assert_eq!(FileKind::from_path("UNIX:[1234]"), FileKind::Socket);
+        assert_eq!(
+            FileKind::from_path("anon_inode:[eventfd]"),
+            FileKind::EventFd
+        );
+        assert_eq!(FileKind::from_path("/memfd:buffer"), FileKind::MemFd);
+    }
+
+    #[test]
+    fn canonical_identity() -> Result<(), Box<dyn std::error::Error>> {
+        let target = File::new("/usr/lib/x.so");
+        let link = File::new("/lib/x.so");
+        assert_ne!(link, target);
+
+        // the symlink is the same file as its target, but keeps its own access path
+        let link = link.with_canonical("/usr/lib/x.so");
+        assert_eq!(link, target);
+        assert_eq!(link.path()?, "/lib/x.so");
+        assert_eq!(link.canonical()?, "/usr/lib/x.so");
+
+        assert_ne!(
+            File::with_kind("pipe:[1]", FileKind::Pipe),
+            File::new("pipe:[1]")
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn generation_identity() -> Result<(), Box<dyn std::error::Error>> {
+        let file = File::new("/tmp/a");
+
+        // a renamed file is the same file under its new path
+        let renamed = file.with_path("/tmp/b");
+        assert_eq!(renamed, file);
+        assert_eq!(renamed.path()?, "/tmp/b");
+
+        // a file created after the previous one was removed is another file
+        let recreated = file.with_generation(1);
+        assert_ne!(recreated, file);
+        assert_eq!(recreated.path()?, "/tmp/a");
+
+        Ok(())
+    }
+}
@@ -0,0 +1,619 @@
+use crate::error::Error;
+use crate::op::{Operation, OperationType};
+use crate::process::Process;
+use regex::Regex;
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::time::Duration;
+
+///
+/// The attributes of the operations the filters compare
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Field {
+    Op,       // the name of the operation, like write or stat, in any case
+    Path,     // the paths of the files the operation accesses
+    Pid,      // the pid of the process
+    Ancestor, // the pid of the process, or of the processes it is cloned from
+    Size,     // the bytes read, written, copied or mapped by the operation
+    Time,     // the seconds from the first traced operation to the operation
+}
+
+///
+/// How a field is compared to a value
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Comparison {
+    Eq,    // =
+    Ne,    // !=
+    Lt,    // <
+    Le,    // <=
+    Gt,    // >
+    Ge,    // >=
+    In,    // in (a, b, ...)
+    Glob,  // ~, a glob where * and ? do not match /, and ** matches anything
+    Regex, // =~, a regular expression
+}
+
+#[derive(Debug, Clone)]
+pub enum Value {
+    Number(f64),
+    Text(String),
+    Pattern(Regex), // a glob or regex, compiled
+    List(Vec<Value>),
+}
+
+///
+/// A boolean expression selecting the operations of the processes, like
+/// `op = write and path ~ /tmp/** and ancestor = 909190`. The predicates compare a field to a
+/// value, and are combined with and, or, not, and parentheses.
+///
+#[derive(Debug, Clone)]
+pub enum Filter {
+    And(Box<Filter>, Box<Filter>),
+    Or(Box<Filter>, Box<Filter>),
+    Not(Box<Filter>),
+    Predicate(Field, Comparison, Value),
+}
+
+// what the predicates need to know about all the processes
+struct Context {
+    parents: HashMap<usize, usize>, // the process each process is cloned from
+    start: Option<Duration>,        // the timestamp of the first traced operation
+}
+
+impl Filter {
+    ///
+    /// Keep the operations the filter selects, and the processes left with any operation
+    ///
+    pub fn apply(&self, processes: Vec<Process>) -> Vec<Process> {
+        let mut parents = HashMap::new();
+        for process in &processes {
+            for op in process.ops() {
+                if let OperationType::Clone(child) = op.op_type() {
+                    parents.insert(*child, process.pid());
+                }
+            }
+        }
+
+        self.apply_with_parents(processes, parents)
+    }
+
+    ///
+    /// Keep the operations the filter selects, with the process each process is cloned from,
+    /// for when the processes no longer have their clone operations
+    ///
+    pub fn apply_with_parents(
+        &self,
+        processes: Vec<Process>,
+        parents: HashMap<usize, usize>,
+    ) -> Vec<Process> {
+        let context = Context::new(&processes, parents);
+
+        processes
+            .into_iter()
+            .filter_map(|mut process| {
+                let pid = process.pid();
+                process
+                    .ops_mut()
+                    .retain(|op| self.selects(pid, op, &context));
+                match process.ops().is_empty() {
+                    true => None,
+                    false => Some(process),
+                }
+            })
+            .collect()
+    }
+
+    fn selects(&self, pid: usize, op: &Operation, context: &Context) -> bool {
+        match self {
+            Filter::And(left, right) => {
+                left.selects(pid, op, context) && right.selects(pid, op, context)
+            }
+            Filter::Or(left, right) => {
+                left.selects(pid, op, context) || right.selects(pid, op, context)
+            }
+            Filter::Not(filter) => !filter.selects(pid, op, context),
+            Filter::Predicate(field, comparison, value) => match field {
+                Field::Op => text(&op.name().to_lowercase(), *comparison, value),
+                Field::Path => {
+                    let paths = paths(op);
+                    match comparison {
+                        // none of the paths is the value
+                        Comparison::Ne => {
+                            paths.iter().all(|path| text(path, Comparison::Ne, value))
+                        }
+                        _ => paths.iter().any(|path| text(path, *comparison, value)),
+                    }
+                }
+                Field::Pid => number(pid as f64, *comparison, value),
+                Field::Ancestor => {
+                    let mut ancestor = Some(pid);
+                    let mut seen = 0;
+                    while let Some(pid) = ancestor {
+                        if number(pid as f64, *comparison, value) {
+                            return true;
+                        }
+                        // the parents can not loop, but the processes can be many
+                        seen += 1;
+                        if seen > context.parents.len() {
+                            break;
+                        }
+                        ancestor = context.parents.get(&pid).cloned();
+                    }
+                    false
+                }
+                Field::Size => match size(op) {
+                    Some(size) => number(size as f64, *comparison, value),
+                    None => false,
+                },
+                Field::Time => match (op.timestamp(), context.start) {
+                    (Some(timestamp), Some(start)) => number(
+                        timestamp.saturating_sub(start).as_secs_f64(),
+                        *comparison,
+                        value,
+                    ),
+                    _ => false,
+                },
+            },
+        }
+    }
+}
+
+impl Context {
+    fn new(processes: &[Process], parents: HashMap<usize, usize>) -> Self {
+        let start = processes
+            .iter()
+            .flat_map(|process| process.ops().iter().filter_map(|op| op.timestamp()))
+            .min();
+
+        Context { parents, start }
+    }
+}
+
+// the paths of the files the operation accesses, and the path a file is renamed to
+fn paths(op: &Operation) -> Vec<String> {
+    let mut paths: Vec<String> = op
+        .accesses()
+        .iter()
+        .filter_map(|(file, _)| file.path().ok().map(|path| path.to_string()))
+        .collect();
+    if let OperationType::Rename(_, to) = op.op_type() {
+        paths.push(to.clone());
+    }
+    paths
+}
+
+fn size(op: &Operation) -> Option<usize> {
+    match op.op_type() {
+        OperationType::Read(_, _, len)
+        | OperationType::Write(_, _, len, _)
+        | OperationType::Map(_, _, len, _, _)
+        | OperationType::Copy(_, _, _, _, len)
+        | OperationType::GetRandom(len) => Some(*len),
+        _ => None,
+    }
+}
+
+fn text(text: &str, comparison: Comparison, value: &Value) -> bool {
+    match (comparison, value) {
+        (Comparison::Eq, Value::Text(value)) => text == value,
+        (Comparison::Ne, Value::Text(value)) => text != value,
+        (Comparison::In, Value::List(values)) => values
+            .iter()
+            .any(|value| self::text(text, Comparison::Eq, value)),
+        (Comparison::Glob, Value::Pattern(pattern))
+        | (Comparison::Regex, Value::Pattern(pattern)) => pattern.is_match(text),
+        _ => false,
+    }
+}
+
+fn number(number: f64, comparison: Comparison, value: &Value) -> bool {
+    match (comparison, value) {
+        (Comparison::Eq, Value::Number(value)) => number == *value,
+        (Comparison::Ne, Value::Number(value)) => number != *value,
+        (Comparison::Lt, Value::Number(value)) => number < *value,
+        (Comparison::Le, Value::Number(value)) => number <= *value,
+        (Comparison::Gt, Value::Number(value)) => number > *value,
+        (Comparison::Ge, Value::Number(value)) => number >= *value,
+        (Comparison::In, Value::List(values)) => values
+            .iter()
+            .any(|value| self::number(number, Comparison::Eq, value)),
+        _ => false,
+    }
+}
+
+///
+/// A regular expression matching the whole text the glob matches
+///
+pub fn glob(glob: &str) -> Result<Regex, Error> {
+    let mut pattern = String::from("^");
+    let mut chars = glob.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' if chars.peek() == Some(&'*') => {
+                chars.next();
+                pattern.push_str(".*");
+            }
+            '*' => pattern.push_str("[^/]*"),
+            '?' => pattern.push_str("[^/]"),
+            c => pattern.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    pattern.push('$');
+
+    Regex::new(&pattern).map_err(|e| Error::ParseError(format!("glob {}: {}", glob, e)))
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),   // a keyword, field, or unquoted value
+    Quoted(String), // a quoted value
+    Symbol(String), // a comparison, a parenthesis, or a comma
+}
+
+fn tokenize(s: &str) -> Result<Vec<Token>, Error> {
+    let mut tokens = vec![];
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {}
+            '(' | ')' | ',' => tokens.push(Token::Symbol(c.to_string())),
+            '=' | '!' | '<' | '>' | '~' => {
+                let mut symbol = c.to_string();
+                if let Some(next) = chars.peek().filter(|next| **next == '=' || **next == '~') {
+                    symbol.push(*next);
+                    chars.next();
+                }
+                tokens.push(Token::Symbol(symbol));
+            }
+            '"' | '\'' => {
+                let mut quoted = String::new();
+                loop {
+                    match chars.next() {
+                        Some('\\') => quoted.extend(chars.next()),
+                        Some(q) if q == c => break,
+                        Some(other) => quoted.push(other),
+                        None => {
+                            return Err(Error::ParseError(format!(
+                                "filter {}, unterminated quote",
+                                s
+                            )))
+                        }
+                    }
+                }
+                tokens.push(Token::Quoted(quoted));
+            }
+            c => {
+                let mut word = c.to_string();
+                while let Some(next) = chars.peek() {
+                    if next.is_whitespace() || "()=!<>~,\"'".contains(*next) {
+                        break;
+                    }
+                    word.push(*next);
+                    chars.next();
+                }
+                tokens.push(Token::Word(word));
+            }
+        }
+    }
+
+    Ok(tokens)
+}
+
+// a recursive descent parser of the filter expressions
+struct FilterParser<'a> {
+    s: &'a str,
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl<'a> FilterParser<'a> {
+    fn error(&self, expected: &str) -> Error {
+        let found = match self.tokens.get(self.position) {
+            Some(Token::Word(word)) | Some(Token::Symbol(word)) => word.clone(),
+            Some(Token::Quoted(quoted)) => format!("\"{}\"", quoted),
+            None => "the end".to_string(),
+        };
+        Error::ParseError(format!(
+            "filter {}, expected {}, found {}",
+            self.s, expected, found
+        ))
+    }
+
+    fn peek_word(&self, word: &str) -> bool {
+        matches!(self.tokens.get(self.position), Some(Token::Word(w)) if w.eq_ignore_ascii_case(word))
+    }
+
+    fn peek_symbol(&self, symbol: &str) -> bool {
+        matches!(self.tokens.get(self.position), Some(Token::Symbol(s)) if s == symbol)
+    }
+
+    fn or(&mut self) -> Result<Filter, Error> {
+        let mut filter = self.and()?;
+        while self.peek_word("or") {
+            self.position += 1;
+            filter = Filter::Or(Box::new(filter), Box::new(self.and()?));
+        }
+        Ok(filter)
+    }
+
+    fn and(&mut self) -> Result<Filter, Error> {
+        let mut filter = self.not()?;
+        while self.peek_word("and") {
+            self.position += 1;
+            filter = Filter::And(Box::new(filter), Box::new(self.not()?));
+        }
+        Ok(filter)
+    }
+
+    fn not(&mut self) -> Result<Filter, Error> {
+        if self.peek_word("not") {
+            self.position += 1;
+            return Ok(Filter::Not(Box::new(self.not()?)));
+        }
+
+        if self.peek_symbol("(") {
+            self.position += 1;
+            let filter = self.or()?;
+            if !self.peek_symbol(")") {
+                return Err(self.error(")"));
+            }
+            self.position += 1;
+            return Ok(filter);
+        }
+
+        self.predicate()
+    }
+
+    fn predicate(&mut self) -> Result<Filter, Error> {
+        let field = match self.tokens.get(self.position) {
+            Some(Token::Word(word)) => match word.to_ascii_lowercase().as_str() {
+                "op" => Field::Op,
+                "path" => Field::Path,
+                "pid" => Field::Pid,
+                "ancestor" => Field::Ancestor,
+                "size" => Field::Size,
+                "time" => Field::Time,
+                _ => return Err(self.error("op, path, pid, ancestor, size, or time")),
+            },
+            _ => return Err(self.error("op, path, pid, ancestor, size, or time")),
+        };
+        self.position += 1;
+
+        let comparison = match self.tokens.get(self.position) {
+            Some(Token::Symbol(symbol)) => match symbol.as_str() {
+                "=" => Comparison::Eq,
+                "!=" => Comparison::Ne,
+                "<" => Comparison::Lt,
+                "<=" => Comparison::Le,
+                ">" => Comparison::Gt,
+                ">=" => Comparison::Ge,
+                "~" => Comparison::Glob,
+                "=~" => Comparison::Regex,
+                _ => return Err(self.error("a comparison")),
+            },
+            Some(Token::Word(word)) if word.eq_ignore_ascii_case("in") => Comparison::In,
+            _ => return Err(self.error("a comparison")),
+        };
+        self.position += 1;
+
+        let numeric = !matches!(field, Field::Op | Field::Path);
+        let allowed = match (numeric, comparison) {
+            (_, Comparison::Eq) | (_, Comparison::Ne) | (_, Comparison::In) => true,
+            (true, Comparison::Lt)
+            | (true, Comparison::Le)
+            | (true, Comparison::Gt)
+            | (true, Comparison::Ge) => true,
+            (false, Comparison::Glob) | (false, Comparison::Regex) => field == Field::Path,
+            _ => false,
+        };
+        if !allowed
+            || (field == Field::Ancestor
+                && comparison != Comparison::Eq
+                && comparison != Comparison::In)
+        {
+            self.position -= 1;
+            return Err(self.error("a comparison of the field"));
+        }
+
+        let value = match comparison {
+            Comparison::In => {
+                if !self.peek_symbol("(") {
+                    return Err(self.error("("));
+                }
+                self.position += 1;
+                let mut values = vec![self.value(numeric)?];
+                while self.peek_symbol(",") {
+                    self.position += 1;
+                    values.push(self.value(numeric)?);
+                }
+                if !self.peek_symbol(")") {
+                    return Err(self.error(")"));
+                }
+                self.position += 1;
+                Value::List(values)
+            }
+            Comparison::Glob => match self.value(false)? {
+                Value::Text(text) => Value::Pattern(glob(&text)?),
+                value => value,
+            },
+            Comparison::Regex => match self.value(false)? {
+                Value::Text(text) => Value::Pattern(
+                    Regex::new(&text)
+                        .map_err(|e| Error::ParseError(format!("regex {}: {}", text, e)))?,
+                ),
+                value => value,
+            },
+            _ => self.value(numeric)?,
+        };
+
+        // the names of the operations are compared in any case
+        let value = match (field, value) {
+            (Field::Op, Value::Text(text)) => Value::Text(text.to_lowercase()),
+            (Field::Op, Value::List(values)) => Value::List(
+                values
+                    .into_iter()
+                    .map(|value| match value {
+                        Value::Text(text) => Value::Text(text.to_lowercase()),
+                        value => value,
+                    })
+                    .collect(),
+            ),
+            (_, value) => value,
+        };
+
+        Ok(Filter::Predicate(field, comparison, value))
+    }
+
+    fn value(&mut self, numeric: bool) -> Result<Value, Error> {
+        let text = match self.tokens.get(self.position) {
+            Some(Token::Word(word)) => word.clone(),
+            Some(Token::Quoted(quoted)) => quoted.clone(),
+            _ => return Err(self.error("a value")),
+        };
+
+        let value = match numeric {
+            true => Value::Number(text.parse().map_err(|_| self.error("a number"))?),
+            false => Value::Text(text),
+        };
+        self.position += 1;
+        Ok(value)
+    }
+}
+
+impl FromStr for Filter {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parser = FilterParser {
+            s,
+            tokens: tokenize(s)?,
+            position: 0,
+        };
+
+        let filter = parser.or()?;
+        if parser.position < parser.tokens.len() {
+            return Err(parser.error("and, or, or the end"));
+        }
+        Ok(filter)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::file::{File, FileKind};
+    use crate::filter::{glob, Filter};
+    use crate::op::Operation;
+    use crate::process::Process;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    fn processes() -> Vec<Process> {
+        let tmp = Arc::new(File::new("/tmp/build/out.o"));
+        let src = Arc::new(File::new("/src/main.c"));
+        let pipe = Arc::new(File::with_kind("pipe:[1]", FileKind::Pipe));
+
+        let at = |op: Operation, secs: u64| op.with_timing(Some(Duration::from_secs(secs)), None);
+
+        let mut parent = Process::new(10);
+        parent.add_op(at(Operation::clone_op(11), 100));
+        parent.add_op(at(Operation::write(tmp.clone(), String::new(), 10, 0), 101));
+        parent.add_op(at(Operation::write(pipe.clone(), String::new(), 1, 0), 102));
+
+        let mut child = Process::new(11);
+        child.add_op(at(Operation::read(src.clone(), 8192, 0), 103));
+        child.add_op(at(
+            Operation::write(tmp.clone(), String::new(), 4096, 10),
+            104,
+        ));
+        child.add_op(at(Operation::stat(src.clone()), 105));
+        child.add_op(at(
+            Operation::copy(src.clone(), 0, tmp.clone(), 0, 100),
+            105,
+        ));
+
+        let mut other = Process::new(20);
+        other.add_op(at(Operation::write(tmp.clone(), String::new(), 1, 0), 106));
+
+        vec![parent, child, other]
+    }
+
+    fn selected(filter: &str) -> Result<Vec<(usize, String)>, Box<dyn std::error::Error>> {
+        let filter: Filter = filter.parse()?;
+        Ok(filter
+            .apply(processes())
+            .iter()
+            .flat_map(|p| p.ops().iter().map(move |op| (p.pid(), op.name())))
+            .collect())
+    }
+
+    #[test]
+    fn filters() -> Result<(), Box<dyn std::error::Error>> {
+        let write = |pid: usize| (pid, "Write".to_string());
+
+        assert_eq!(
+            selected("op = write and path ~ /tmp/** and ancestor = 10")?,
+            vec![write(10), write(11)]
+        );
+        assert_eq!(
+            selected("path = /src/main.c")?,
+            vec![
+                (11, "Read".to_string()),
+                (11, "Stat".to_string()),
+                (11, "Copy".to_string())
+            ]
+        );
+        assert_eq!(
+            selected("op in (Read, stat) or pid = 20")?,
+            vec![
+                (11, "Read".to_string()),
+                (11, "Stat".to_string()),
+                write(20)
+            ]
+        );
+        assert_eq!(
+            selected("size >= 4096")?,
+            vec![(11, "Read".to_string()), write(11)]
+        );
+        assert_eq!(
+            selected("not (pid != 10) and path =~ '^pipe:'")?,
+            vec![write(10)]
+        );
+        assert_eq!(selected("time > 3 and time <= 4")?, vec![write(11)]);
+        assert_eq!(
+            selected("path != /tmp/build/out.o and op = write")?,
+            vec![write(10)]
+        );
+        assert_eq!(selected("pid = 30")?, vec![]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn errors() {
+        assert!("".parse::<Filter>().is_err());
+        assert!("op".parse::<Filter>().is_err());
+        assert!("owner = me".parse::<Filter>().is_err());
+        assert!("size = big".parse::<Filter>().is_err());
+        assert!("op > write".parse::<Filter>().is_err());
+        assert!("ancestor > 1".parse::<Filter>().is_err());
+        assert!("pid ~ 1*".parse::<Filter>().is_err());
+        assert!("(op = write".parse::<Filter>().is_err());
+        assert!("op = write pid = 1".parse::<Filter>().is_err());
+        assert!("path = \"/tmp".parse::<Filter>().is_err());
+        assert!("path =~ (".parse::<Filter>().is_err());
+    }
+
+    #[test]
+    fn globs() -> Result<(), Box<dyn std::error::Error>> {
+        assert!(glob("/tmp/*")?.is_match("/tmp/a"));
+        assert!(!glob("/tmp/*")?.is_match("/tmp/a/b"));
+        assert!(glob("/tmp/**")?.is_match("/tmp/a/b"));
+        assert!(glob("/tmp/?.c")?.is_match("/tmp/a.c"));
+        assert!(!glob("/tmp/?.c")?.is_match("/tmp/ab.c"));
+        assert!(glob("/a+b/*.(c)")?.is_match("/a+b/x.(c)"));
+
+        Ok(())
+    }
+}
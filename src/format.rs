@@ -0,0 +1,352 @@
+use crate::deps::{DependencyGraph, GraphNode};
+use crate::error::Error;
+use crate::op::{Operation, OperationType};
+use crate::process::Process;
+use std::str::FromStr;
+
+///
+/// The formats the ordered operations and the dependency graph are written in
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    Text,  // the operations as strace-parser prints them, and the graph in the Graphviz dot language
+    Jsonl, // a JSON object per line, for each operation, or each process and edge of the graph
+    Csv,   // a row per operation, or per edge of the graph, after a header row
+    Binary, // the operations as a compact binary trace, written by BinaryWriter rather than here
+}
+
+// the columns of the operations in the csv format, and the keys in the jsonl format
+const OP_COLUMNS: [&str; 9] = [
+    "pid",
+    "wave",
+    "op",
+    "path",
+    "offset",
+    "len",
+    "timestamp",
+    "duration",
+    "operation",
+];
+
+impl Format {
+    ///
+    /// Write the operations of the processes of each available set of the dependency graph, in
+    /// order. The wave of an operation is the number of its available set, starting from 1
+    ///
+    pub fn ops(
+        &self,
+        dep_graph: &mut DependencyGraph,
+    ) -> Result<String, Box<dyn std::error::Error>> {
+        if *self == Format::Binary {
+            return Err(Box::new(Error::InvalidType(
+                "the binary operations are written by BinaryWriter".to_string(),
+            )));
+        }
+
+        let mut output = String::new();
+        if *self == Format::Csv {
+            output.push_str(&OP_COLUMNS.join(","));
+            output.push('\n');
+        }
+
+        let mut wave = 1;
+        let mut available_set = dep_graph.available_set()?;
+        while !available_set.is_empty() {
+            for process in available_set.iter() {
+                match self {
+                    Format::Text => output.push_str(&process.to_string()),
+                    _ => {
+                        for op in process.ops() {
+                            output.push_str(&self.row(&fields(process, wave, op)));
+                        }
+                    }
+                }
+            }
+
+            wave += 1;
+            available_set = dep_graph.available_set()?;
+        }
+
+        Ok(output)
+    }
+
+    ///
+    /// Write the dependency graph itself: its processes, with their number of operations, and
+    /// the edges from each process to the processes that depend on it
+    ///
+    pub fn graph(&self, dep_graph: &DependencyGraph) -> Result<String, Box<dyn std::error::Error>> {
+        let mut processes = vec![];
+        for node in dep_graph.dag.nodes() {
+            if let GraphNode::Process(process) = &*node.data() {
+                processes.push((process.pid(), process.ops().len()));
+            }
+        }
+        processes.sort();
+
+        let mut edges = vec![];
+        for edge in dep_graph.dag.edges() {
+            let from = edge.source().data().process()?.pid();
+            let to = edge.target().data().process()?.pid();
+            edges.push((from, to, edge.label()));
+        }
+        edges.sort();
+        edges.dedup();
+
+        let mut output = String::new();
+        match self {
+            Format::Text => {
+                output.push_str("digraph dependencies {\n");
+                for (pid, ops) in processes.iter() {
+                    output.push_str(&format!("    {} [label=\"{}\\n{} ops\"];\n", pid, pid, ops));
+                }
+                for (from, to, label) in edges.iter() {
+                    match label.is_empty() {
+                        true => output.push_str(&format!("    {} -> {};\n", from, to)),
+                        false => output
+                            .push_str(&format!("    {} -> {} [label=\"{}\"];\n", from, to, label)),
+                    }
+                }
+                output.push_str("}\n");
+            }
+            Format::Jsonl => {
+                for (pid, ops) in processes.iter() {
+                    output.push_str(&format!(
+                        "{{\"type\":\"process\",\"pid\":{},\"ops\":{}}}\n",
+                        pid, ops
+                    ));
+                }
+                for (from, to, label) in edges.iter() {
+                    output.push_str(&format!(
+                        "{{\"type\":\"edge\",\"from\":{},\"to\":{},\"label\":{}}}\n",
+                        from,
+                        to,
+                        json_string(label)
+                    ));
+                }
+            }
+            Format::Binary => {
+                return Err(Box::new(Error::InvalidType(
+                    "the dependency graph has no binary format".to_string(),
+                )))
+            }
+            Format::Csv => {
+                // the processes without any edge are rows without a target
+                output.push_str("from,to,label\n");
+                for (pid, _) in processes.iter() {
+                    if !edges.iter().any(|(from, to, _)| from == pid || to == pid) {
+                        output.push_str(&format!("{},,\n", pid));
+                    }
+                }
+                for (from, to, label) in edges.iter() {
+                    output.push_str(&format!("{},{},{}\n", from, to, csv_field(label)));
+                }
+            }
+        }
+
+        Ok(output)
+    }
+
+    fn row(&self, fields: &[Option<Field>]) -> String {
+        match self {
+            Format::Jsonl => {
+                let pairs: Vec<String> = OP_COLUMNS
+                    .iter()
+                    .zip(fields.iter())
+                    .map(|(column, field)| {
+                        let value = match field {
+                            Some(Field::Number(number)) => number.clone(),
+                            Some(Field::Text(text)) => json_string(text),
+                            None => "null".to_string(),
+                        };
+                        format!("{}:{}", json_string(column), value)
+                    })
+                    .collect();
+                format!("{{{}}}\n", pairs.join(","))
+            }
+            _ => {
+                let values: Vec<String> = fields
+                    .iter()
+                    .map(|field| match field {
+                        Some(Field::Number(number)) => number.clone(),
+                        Some(Field::Text(text)) => csv_field(text),
+                        None => String::new(),
+                    })
+                    .collect();
+                format!("{}\n", values.join(","))
+            }
+        }
+    }
+}
+
+impl FromStr for Format {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(Format::Text),
+            "jsonl" => Ok(Format::Jsonl),
+            "csv" => Ok(Format::Csv),
+            "binary" => Ok(Format::Binary),
+            _ => Err(Error::ParseError(format!(
+                "format {}, expected text, jsonl, csv, or binary",
+                s
+            ))),
+        }
+    }
+}
+
+// a value of an operation, written as is if a number, or quoted if a text
+enum Field {
+    Number(String),
+    Text(String),
+}
+
+// the values of an operation in the order of the columns
+fn fields(process: &Process, wave: usize, op: &Operation) -> Vec<Option<Field>> {
+    let number = |n: String| Some(Field::Number(n));
+
+    let path = match op.op_type() {
+        OperationType::Chdir(path) => Some(path.clone()),
+        _ => op
+            .file()
+            .and_then(|file| file.path().ok().map(|path| path.to_string())),
+    };
+
+    let (offset, len) = match op.op_type() {
+        OperationType::Read(_, offset, len)
+        | OperationType::Write(_, offset, len, _)
+        | OperationType::Map(_, offset, len, _, _)
+        | OperationType::Copy(_, _, _, offset, len) => (Some(*offset), Some(*len)),
+        OperationType::OpenAt(_, offset) => (Some(*offset), None),
+        OperationType::GetRandom(len) => (None, Some(*len)),
+        _ => (None, None),
+    };
+
+    vec![
+        number(process.pid().to_string()),
+        number(wave.to_string()),
+        Some(Field::Text(op.name())),
+        path.map(Field::Text),
+        offset.and_then(|offset| number(offset.to_string())),
+        len.and_then(|len| number(len.to_string())),
+        op.timestamp()
+            .and_then(|timestamp| number(format!("{:.6}", timestamp.as_secs_f64()))),
+        op.duration()
+            .and_then(|duration| number(format!("{:.6}", duration.as_secs_f64()))),
+        Some(Field::Text(op.to_string())),
+    ]
+}
+
+fn json_string(s: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if (c as u32) < 0x20 => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+// quote the field if it has a separator, a quote, or a line break
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::deps::DependencyGraph;
+    use crate::file::{File, FileKind};
+    use crate::format::{csv_field, json_string, Format};
+    use crate::op::Operation;
+    use crate::process::Process;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    fn dep_graph() -> Result<DependencyGraph, Box<dyn std::error::Error>> {
+        let output = Arc::new(File::new("/data/a,\"b\""));
+        let pipe = Arc::new(File::with_kind("pipe:[1]", FileKind::Pipe));
+
+        let mut writer = Process::new(1);
+        writer.add_op(
+            Operation::write(output.clone(), String::new(), 5, 0)
+                .with_timing(Some(Duration::from_millis(1500)), None),
+        );
+        writer.add_op(Operation::write(pipe.clone(), String::new(), 1, 0));
+
+        let mut reader = Process::new(2);
+        reader.add_op(Operation::read(output.clone(), 5, 0));
+        reader.add_op(Operation::read(pipe.clone(), 1, 0));
+
+        let mut other = Process::new(3);
+        other.add_op(Operation::read(Arc::new(File::new("/data/input")), 4, 0));
+
+        Ok(DependencyGraph::new(vec![writer, reader, other])?.order()?)
+    }
+
+    #[test]
+    fn ops() -> Result<(), Box<dyn std::error::Error>> {
+        let text = Format::Text.ops(&mut dep_graph()?)?;
+        assert!(text.starts_with("1 write(file(/data/a,\"b\"), 0, 5, )\n"));
+
+        let jsonl = Format::Jsonl.ops(&mut dep_graph()?)?;
+        let lines: Vec<&str> = jsonl.lines().collect();
+        assert_eq!(
+            lines[0],
+            "{\"pid\":1,\"wave\":1,\"op\":\"Write\",\"path\":\"/data/a,\\\"b\\\"\",\"offset\":0,\"len\":5,\"timestamp\":1.500000,\"duration\":null,\"operation\":\"write(file(/data/a,\\\"b\\\"), 0, 5, )\"}"
+        );
+        assert!(lines[2].starts_with("{\"pid\":3,\"wave\":1,\"op\":\"Read\""));
+        assert!(lines[3].starts_with("{\"pid\":2,\"wave\":2,\"op\":\"Read\""));
+
+        let csv = Format::Csv.ops(&mut dep_graph()?)?;
+        let rows: Vec<&str> = csv.lines().collect();
+        assert_eq!(
+            rows[0],
+            "pid,wave,op,path,offset,len,timestamp,duration,operation"
+        );
+        assert_eq!(rows[1], "1,1,Write,\"/data/a,\"\"b\"\"\",0,5,1.500000,,\"write(file(/data/a,\"\"b\"\"), 0, 5, )\"");
+        assert!(rows.contains(&"3,1,Read,/data/input,0,4,,,\"read(file(/data/input), 0, 4)\""));
+
+        Ok(())
+    }
+
+    #[test]
+    fn graph() -> Result<(), Box<dyn std::error::Error>> {
+        let dot = Format::Text.graph(&dep_graph()?)?;
+        assert!(dot.starts_with("digraph dependencies {\n"));
+        assert!(dot.contains("    1 [label=\"1\\n2 ops\"];\n"));
+        assert!(dot.contains("    1 -> 2;\n"));
+        assert!(dot.contains("    1 -> 2 [label=\"pipe\"];\n"));
+
+        let jsonl = Format::Jsonl.graph(&dep_graph()?)?;
+        assert!(jsonl.contains("{\"type\":\"process\",\"pid\":3,\"ops\":1}\n"));
+        assert!(jsonl.contains("{\"type\":\"edge\",\"from\":1,\"to\":2,\"label\":\"pipe\"}\n"));
+
+        let csv = Format::Csv.graph(&dep_graph()?)?;
+        assert_eq!(csv, "from,to,label\n3,,\n1,2,\n1,2,pipe\n");
+
+        Ok(())
+    }
+
+    #[test]
+    fn quote() -> Result<(), Box<dyn std::error::Error>> {
+        assert_eq!(json_string("a\"\\\n\u{1}"), "\"a\\\"\\\\\\n\\u0001\"");
+        assert_eq!(csv_field("a"), "a");
+        assert_eq!(csv_field("a,\"b\""), "\"a,\"\"b\"\"\"");
+        assert_eq!("jsonl".parse::<Format>()?, Format::Jsonl);
+        assert!("json".parse::<Format>().is_err());
+        assert!(Format::Binary.graph(&dep_graph()?).is_err());
+
+        Ok(())
+    }
+}
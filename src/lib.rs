@@ -1,12 +1,44 @@
+mod binary;
+mod config;
 mod dag;
 mod deps;
 mod error;
+mod export;
 mod file;
+mod filter;
+mod format;
+mod lifetime;
 mod op;
 mod parser;
+mod path;
+mod pattern;
+mod payload;
 mod process;
+mod reader;
+mod replay;
+mod rules;
+mod snapshot;
+mod stats;
+mod verify;
+mod workload;
 
 // re-export the required modules
+pub use binary::{BinaryTrace, BinaryWriter};
+pub use config::{Config, ExportConfig, PathsConfig, ReplayConfig, WorkloadConfig};
+pub use deps::{DependencyGraph, Parallelism, Weighting};
+pub use export::Script;
+pub use filter::Filter;
+pub use format::Format;
+pub use lifetime::{FileLifetime, Lifetimes};
 pub use op::{Operation, OperationType};
-pub use parser::{FileType, Parser};
+pub use parser::{FdDiscrepancy, FileType, Parser, UnresolvedPath};
+pub use pattern::{AccessPatterns, FilePattern, Pattern};
+pub use payload::{Payload, PayloadGenerator};
 pub use process::Process;
+pub use reader::Reader;
+pub use replay::{Pacing, Replay, ReplayReport, ReplayedOp};
+pub use rules::{PathRules, Preset};
+pub use snapshot::Snapshot;
+pub use stats::{Stats, StatsReport};
+pub use verify::{ExpectedState, Mismatch};
+pub use workload::{FileProfile, IoSummary, ProcessProfile, Workload};
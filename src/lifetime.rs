@@ -0,0 +1,456 @@
+use crate::op::OperationType;
+use crate::parser::FileType;
+use crate::process::Process;
+use crate::snapshot::Snapshot;
+use std::collections::{HashMap, HashSet};
+use std::fmt::Formatter;
+use std::time::Duration;
+
+///
+/// The life of a regular file during the trace, from its creation, or the start of the trace if
+/// it existed before, to its removal, or the end of the trace if it is not removed. The times
+/// are the indices of the operations in the order of the trace, and their timestamps if the
+/// trace has them.
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileLifetime {
+    paths: Vec<String>, // the path the file is created or first accessed at, and the paths it is renamed to
+    created: Option<(usize, Option<Duration>)>, // when the file is created, if it is created in the trace
+    removed: Option<(usize, Option<Duration>)>, // when the file is removed or replaced, if it is
+    size: usize,                                // the size of the file, as far as the trace shows
+    peak_size: usize,                           // the largest size of the file
+}
+
+impl FileLifetime {
+    fn new(path: &str, created: Option<(usize, Option<Duration>)>, size: usize) -> Self {
+        FileLifetime {
+            paths: vec![path.to_string()],
+            created,
+            removed: None,
+            size,
+            peak_size: size,
+        }
+    }
+
+    ///
+    /// The path the file is created or first accessed at
+    ///
+    pub fn path(&self) -> &str {
+        &self.paths[0]
+    }
+
+    ///
+    /// All the paths of the file, including the ones it is renamed to
+    ///
+    pub fn paths(&self) -> &Vec<String> {
+        &self.paths
+    }
+
+    pub fn created(&self) -> Option<usize> {
+        self.created.map(|(index, _)| index)
+    }
+
+    pub fn removed(&self) -> Option<usize> {
+        self.removed.map(|(index, _)| index)
+    }
+
+    pub fn peak_size(&self) -> usize {
+        self.peak_size
+    }
+
+    ///
+    /// Whether the file is created and removed within the trace
+    ///
+    pub fn is_temporary(&self) -> bool {
+        self.created.is_some() && self.removed.is_some()
+    }
+
+    ///
+    /// The number of operations between the creation and the removal of a temporary file
+    ///
+    pub fn lifetime_ops(&self) -> Option<usize> {
+        match (self.created, self.removed) {
+            (Some((created, _)), Some((removed, _))) => Some(removed - created),
+            _ => None,
+        }
+    }
+
+    ///
+    /// The time between the creation and the removal of a temporary file, if the trace has the
+    /// timestamps
+    ///
+    pub fn lifetime(&self) -> Option<Duration> {
+        match (self.created, self.removed) {
+            (Some((_, Some(created))), Some((_, Some(removed)))) => {
+                Some(removed.saturating_sub(created))
+            }
+            _ => None,
+        }
+    }
+
+    fn resize(&mut self, size: usize) -> i64 {
+        let delta = size as i64 - self.size as i64;
+        self.size = size;
+        self.peak_size = self.peak_size.max(size);
+        delta
+    }
+}
+
+impl std::fmt::Display for FileLifetime {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.paths.join(" -> "))?;
+        match self.created {
+            Some((index, _)) => write!(f, ": created at {}", index)?,
+            None => write!(f, ": existed")?,
+        }
+        match self.removed {
+            Some((index, _)) => write!(f, ", removed at {}", index)?,
+            None => write!(f, ", live at the end")?,
+        }
+        if let Some(lifetime) = self.lifetime() {
+            write!(f, ", lived {:.6}s", lifetime.as_secs_f64())?;
+        }
+        writeln!(f, ", peak size {} bytes", self.peak_size)
+    }
+}
+
+///
+/// The lifetimes of the regular files, and the peak working set, which is the most bytes in
+/// the files live at the same time. The operations of the processes are ordered by their
+/// timestamps if all of them have one, and by the order of the processes otherwise. The files
+/// of the snapshot are live from the start, with their sizes.
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct Lifetimes {
+    files: Vec<FileLifetime>,
+    peak_bytes: usize, // the most bytes live at the same time
+    peak_at: usize,    // the index of the operation the peak is reached at
+}
+
+impl Lifetimes {
+    pub fn new(processes: &[Process], snapshot: &Snapshot) -> Self {
+        let mut ops: Vec<_> = processes.iter().flat_map(|p| p.ops().iter()).collect();
+        if ops.iter().all(|op| op.timestamp().is_some()) {
+            ops.sort_by_key(|op| op.timestamp());
+        }
+
+        let mut lifetimes = Lifetimes {
+            files: vec![],
+            peak_bytes: 0,
+            peak_at: 0,
+        };
+        let mut live: HashMap<String, usize> = HashMap::new();
+        let mut dirs: HashSet<String> = HashSet::new();
+        let mut bytes: i64 = 0;
+
+        for file in snapshot.files() {
+            match file {
+                FileType::File(path, size) => {
+                    live.insert(path.clone(), lifetimes.files.len());
+                    lifetimes.files.push(FileLifetime::new(path, None, *size));
+                    bytes += *size as i64;
+                }
+                FileType::Dir(path, _) => {
+                    dirs.insert(path.clone());
+                }
+                FileType::Other => {}
+            }
+        }
+        lifetimes.peak(bytes, 0);
+
+        for (index, op) in ops.into_iter().enumerate() {
+            let when = (index, op.timestamp());
+            let files = &mut lifetimes.files;
+
+            match op.op_type() {
+                OperationType::Mknod(file) => {
+                    if let Some(path) = path(file) {
+                        if !live.contains_key(path) {
+                            live.insert(path.to_string(), files.len());
+                            files.push(FileLifetime::new(path, Some(when), 0));
+                        }
+                    }
+                }
+                OperationType::Write(file, offset, len, _) => {
+                    if let Some(path) = path(file) {
+                        let i = file_at(&mut live, files, path);
+                        let size = files[i].size.max((*offset).max(0) as usize + len);
+                        bytes += files[i].resize(size);
+                    }
+                }
+                OperationType::Copy(from, _, to, offset, len) => {
+                    // the source is left as is, but it is known to exist
+                    if let Some(path) = path(from) {
+                        file_at(&mut live, files, path);
+                    }
+                    if let Some(path) = path(to) {
+                        let i = file_at(&mut live, files, path);
+                        let size = files[i].size.max((*offset).max(0) as usize + len);
+                        bytes += files[i].resize(size);
+                    }
+                }
+                OperationType::Truncate(file) => {
+                    if let Some(path) = path(file) {
+                        let i = file_at(&mut live, files, path);
+                        bytes += files[i].resize(0);
+                    }
+                }
+                OperationType::Remove(file) => {
+                    if let Some(i) = path(file).and_then(|path| live.remove(path)) {
+                        bytes -= files[i].size as i64;
+                        files[i].removed = Some(when);
+                    }
+                }
+                OperationType::Mkdir(file, _) => {
+                    if let Some(path) = path(file) {
+                        dirs.insert(path.to_string());
+                    }
+                    continue;
+                }
+                OperationType::Rename(file, to) => {
+                    let from = match path(file) {
+                        Some(from) if from != to => from,
+                        _ => continue,
+                    };
+
+                    let renamed: Vec<String> = dirs
+                        .iter()
+                        .filter(|dir| is_under(dir, from))
+                        .cloned()
+                        .collect();
+                    let is_dir = !renamed.is_empty();
+                    for dir in renamed {
+                        dirs.remove(&dir);
+                        dirs.insert(format!("{}{}", to, &dir[from.len()..]));
+                    }
+
+                    // the file, or the files in the directory, moved to the new path
+                    let moved: Vec<(String, String)> = live
+                        .keys()
+                        .filter(|path| is_under(path, from))
+                        .map(|path| (path.clone(), format!("{}{}", to, &path[from.len()..])))
+                        .collect();
+                    if moved.is_empty() && !is_dir {
+                        // a file not seen yet, which existed before the trace
+                        let i = file_at(&mut live, files, from);
+                        live.remove(from);
+                        if let Some(replaced) = live.remove(to.as_str()) {
+                            bytes -= files[replaced].size as i64;
+                            files[replaced].removed = Some(when);
+                        }
+                        files[i].paths.push(to.clone());
+                        live.insert(to.clone(), i);
+                        lifetimes.peak(bytes, index);
+                        continue;
+                    }
+
+                    let moved: Vec<(usize, String)> = moved
+                        .into_iter()
+                        .filter_map(|(from, to)| live.remove(&from).map(|i| (i, to)))
+                        .collect();
+                    for (i, to) in moved {
+                        // the file at the new path is replaced
+                        if let Some(replaced) = live.remove(&to) {
+                            bytes -= files[replaced].size as i64;
+                            files[replaced].removed = Some(when);
+                        }
+                        files[i].paths.push(to.clone());
+                        live.insert(to, i);
+                    }
+                }
+                _ => continue,
+            }
+
+            lifetimes.peak(bytes, index);
+        }
+
+        lifetimes
+    }
+
+    fn peak(&mut self, bytes: i64, index: usize) {
+        if bytes > self.peak_bytes as i64 {
+            self.peak_bytes = bytes as usize;
+            self.peak_at = index;
+        }
+    }
+
+    pub fn files(&self) -> &Vec<FileLifetime> {
+        &self.files
+    }
+
+    ///
+    /// The files created and removed within the trace, from the shortest lived
+    ///
+    pub fn temporary_files(&self) -> Vec<&FileLifetime> {
+        let mut temporary: Vec<&FileLifetime> =
+            self.files.iter().filter(|f| f.is_temporary()).collect();
+        temporary.sort_by_key(|f| (f.lifetime(), f.lifetime_ops()));
+        temporary
+    }
+
+    pub fn peak_bytes(&self) -> usize {
+        self.peak_bytes
+    }
+
+    pub fn peak_at(&self) -> usize {
+        self.peak_at
+    }
+}
+
+impl std::fmt::Display for Lifetimes {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "peak working set: {} bytes, at operation {}",
+            self.peak_bytes, self.peak_at
+        )?;
+
+        let temporary = self.temporary_files();
+        writeln!(f, "temporary files: {}", temporary.len())?;
+        for file in temporary {
+            write!(f, "  {}", file)?;
+        }
+
+        writeln!(f, "files: {}", self.files.len())?;
+        for file in self.files.iter() {
+            write!(f, "  {}", file)?;
+        }
+
+        Ok(())
+    }
+}
+
+// the file at the path, which existed before the trace if it is not seen yet
+fn file_at(live: &mut HashMap<String, usize>, files: &mut Vec<FileLifetime>, path: &str) -> usize {
+    *live.entry(path.to_string()).or_insert_with(|| {
+        files.push(FileLifetime::new(path, None, 0));
+        files.len() - 1
+    })
+}
+
+// whether the entry is the path, or is in the directory at the path
+fn is_under(entry: &str, path: &str) -> bool {
+    entry
+        .strip_prefix(path)
+        .map(|rest| rest.is_empty() || rest.starts_with('/'))
+        .unwrap_or(false)
+}
+
+// the path of a regular file that can still be reached by it
+fn path(file: &crate::file::File) -> Option<&str> {
+    file.path()
+        .ok()
+        .filter(|path| file.is_regular() && !path.ends_with(" (deleted)"))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::file::File;
+    use crate::lifetime::Lifetimes;
+    use crate::op::Operation;
+    use crate::parser::FileType;
+    use crate::process::Process;
+    use crate::snapshot::Snapshot;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    #[test]
+    fn lifetimes() {
+        let tmp = Arc::new(File::new("/data/.save.tmp"));
+        let scratch = Arc::new(File::new("/data/scratch"));
+        let config = Arc::new(File::new("/data/config"));
+
+        let mut process = Process::new(1);
+        let ops = vec![
+            // an atomic save: write a temporary file, and rename it over the config
+            Operation::mknod(tmp.clone()),
+            Operation::write(tmp.clone(), String::new(), 100, 0),
+            Operation::rename(tmp.clone(), "/data/config".to_string()),
+            // a scratch file, created and removed
+            Operation::mknod(scratch.clone()),
+            Operation::write(scratch.clone(), String::new(), 5000, 0),
+            Operation::truncate(scratch.clone()),
+            Operation::remove(scratch.clone()),
+            Operation::write(config.clone(), String::new(), 50, 100),
+        ];
+        for (i, op) in ops.into_iter().enumerate() {
+            process.add_op(op.with_timing(Some(Duration::from_millis(i as u64)), None));
+        }
+
+        let snapshot = Snapshot::new(vec![FileType::File("/data/config".to_string(), 10)]);
+        let lifetimes = Lifetimes::new(&[process], &snapshot);
+
+        let files = lifetimes.files();
+        assert_eq!(files.len(), 3);
+
+        // the old config is replaced by the rename
+        assert_eq!(files[0].path(), "/data/config");
+        assert_eq!(files[0].created(), None);
+        assert_eq!(files[0].removed(), Some(2));
+        assert!(!files[0].is_temporary());
+
+        assert_eq!(
+            files[1].paths(),
+            &vec!["/data/.save.tmp".to_string(), "/data/config".to_string()]
+        );
+        assert_eq!(files[1].removed(), None);
+        assert_eq!(files[1].peak_size(), 150);
+
+        let temporary = lifetimes.temporary_files();
+        assert_eq!(temporary.len(), 1);
+        assert_eq!(temporary[0].path(), "/data/scratch");
+        assert_eq!(temporary[0].lifetime_ops(), Some(3));
+        assert_eq!(temporary[0].lifetime(), Some(Duration::from_millis(3)));
+        assert_eq!(temporary[0].peak_size(), 5000);
+
+        // the config, the temporary file, and the scratch file
+        assert_eq!(lifetimes.peak_bytes(), 10 + 100 + 5000 - 10);
+        assert_eq!(lifetimes.peak_at(), 4);
+    }
+
+    #[test]
+    fn renamed_directory() {
+        let file = Arc::new(File::new("/data/out/file"));
+
+        let mut process = Process::new(1);
+        process.add_op(Operation::mknod(file.clone()));
+        process.add_op(Operation::write(file.clone(), String::new(), 10, 0));
+        process.add_op(Operation::rename(
+            Arc::new(File::new("/data/out")),
+            "/data/done".to_string(),
+        ));
+        process.add_op(Operation::remove(Arc::new(File::new("/data/done/file"))));
+
+        let lifetimes = Lifetimes::new(&[process], &Snapshot::new(vec![]));
+
+        assert_eq!(lifetimes.files().len(), 1);
+        assert_eq!(
+            lifetimes.files()[0].paths(),
+            &vec!["/data/out/file".to_string(), "/data/done/file".to_string()]
+        );
+        assert_eq!(lifetimes.temporary_files().len(), 1);
+        assert_eq!(lifetimes.files()[0].lifetime(), None);
+        assert_eq!(lifetimes.peak_bytes(), 10);
+    }
+
+    #[test]
+    fn copied() {
+        let input = Arc::new(File::new("/data/input"));
+        let output = Arc::new(File::new("/data/output"));
+
+        let mut process = Process::new(1);
+        process.add_op(Operation::mknod(output.clone()));
+        process.add_op(Operation::copy(input, 0, output, 0, 10));
+
+        let lifetimes = Lifetimes::new(&[process], &Snapshot::new(vec![]));
+
+        // the source existed before the trace, and is left as is
+        let files = lifetimes.files();
+        assert_eq!(files.len(), 2);
+        assert_eq!(files[0].path(), "/data/output");
+        assert_eq!(files[0].peak_size(), 10);
+        assert_eq!(files[1].path(), "/data/input");
+        assert_eq!(files[1].created(), None);
+        assert_eq!(files[1].removed(), None);
+    }
+}
@@ -3,39 +3,60 @@ use std::fmt;
 use std::fmt::{Display, Formatter};
 use std::hash::Hash;
 use std::sync::Arc;
+use std::time::Duration;
 
 #[derive(Debug, PartialEq, Clone, Hash, Eq)]
 pub enum OperationType {
-    Read(Arc<File>, i32, usize),          // args: FileDir, offset, len
-    Write(Arc<File>, i32, usize, String), // args: FileDir, offset, len, content
-    Mkdir(Arc<File>, String),             // args: path, mode
-    Mknod(Arc<File>),                     // args: path
-    Remove(Arc<File>),                    // args: FileDir
-    Rename(Arc<File>, String),            // args: FileDir, new_name
-    OpenAt(Arc<File>, i32),               // args: FileDir, offset
-    Truncate(Arc<File>),                  // args: FileDir
-    GetRandom(usize),                     // args: len
-    Stat(Arc<File>),                      // args: path
-    Fstat(Arc<File>),                     // args: path
-    Statx(Arc<File>),                     // args: path
-    StatFS(Arc<File>),                    // args: path
-    Fstatat(Arc<File>),                   // args: path
-    Clone(usize),                         // args: process id of the cloned process
-    Chdir(String),                        // args: the current directory path
+    Read(Arc<File>, i32, usize),                 // args: FileDir, offset, len
+    Write(Arc<File>, i32, usize, String),        // args: FileDir, offset, len, content
+    Mkdir(Arc<File>, String),                    // args: path, mode
+    Mknod(Arc<File>),                            // args: path
+    Remove(Arc<File>),                           // args: FileDir
+    Rename(Arc<File>, String),                   // args: FileDir, new_name
+    OpenAt(Arc<File>, i32),                      // args: FileDir, offset
+    Truncate(Arc<File>),                         // args: FileDir
+    GetRandom(usize),                            // args: len
+    Stat(Arc<File>),                             // args: path
+    Fstat(Arc<File>),                            // args: path
+    Statx(Arc<File>),                            // args: path
+    StatFS(Arc<File>),                           // args: path
+    Fstatat(Arc<File>),                          // args: path
+    Clone(usize),                                // args: process id of the cloned process
+    Chdir(String),                               // args: the current directory path
+    Map(Arc<File>, i32, usize, String, String),  // args: FileDir, offset, len, protection, flags
+    Sync(Arc<File>),                             // args: FileDir
+    Copy(Arc<File>, i32, Arc<File>, i32, usize), // args: source, source offset, destination, destination offset, len
     NoOp,
 }
 
 ///
 /// The operation that is done by a process
 ///
-#[derive(Clone, Hash, PartialEq, Eq)]
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
 pub struct Operation {
-    op_type: OperationType, // type of operation
+    op_type: OperationType,      // type of operation
+    timestamp: Option<Duration>, // when the operation started, if strace printed timestamps (-t, -tt, or -ttt)
+    duration: Option<Duration>,  // the time spent in the operation, if strace printed it (-T)
 }
 
 impl Operation {
     pub fn new(op_type: OperationType) -> Self {
-        Operation { op_type }
+        Operation {
+            op_type,
+            timestamp: None,
+            duration: None,
+        }
+    }
+
+    ///
+    /// The same operation, started at the timestamp and taking the duration
+    ///
+    pub fn with_timing(self, timestamp: Option<Duration>, duration: Option<Duration>) -> Self {
+        Operation {
+            op_type: self.op_type,
+            timestamp,
+            duration,
+        }
     }
 
     pub fn read(file: Arc<File>, len: usize, offset: i32) -> Self {
@@ -106,6 +127,24 @@ impl Operation {
         Self::new(OperationType::Chdir(path))
     }
 
+    pub fn map(file: Arc<File>, offset: i32, len: usize, prot: String, flags: String) -> Self {
+        Self::new(OperationType::Map(file, offset, len, prot, flags))
+    }
+
+    pub fn sync(file: Arc<File>) -> Self {
+        Self::new(OperationType::Sync(file))
+    }
+
+    pub fn copy(
+        from: Arc<File>,
+        from_offset: i32,
+        to: Arc<File>,
+        to_offset: i32,
+        len: usize,
+    ) -> Self {
+        Self::new(OperationType::Copy(from, from_offset, to, to_offset, len))
+    }
+
     ///
     /// Get the OperationType
     ///
@@ -113,6 +152,14 @@ impl Operation {
         &self.op_type
     }
 
+    pub fn timestamp(&self) -> Option<Duration> {
+        self.timestamp
+    }
+
+    pub fn duration(&self) -> Option<Duration> {
+        self.duration
+    }
+
     ///
     /// Get the file accessed by this operation
     ///
@@ -134,10 +181,75 @@ impl Operation {
             OperationType::Rename(file, _) => Some(file.clone()),
             OperationType::Clone(_) => None,
             OperationType::Chdir(_) => None,
+            OperationType::Map(file, _, _, _, _) => Some(file.clone()),
+            OperationType::Sync(file) => Some(file.clone()),
+            OperationType::Copy(_, _, to, _, _) => Some(to.clone()),
             OperationType::NoOp => None,
         }
     }
 
+    ///
+    /// Replace the files accessed by this operation with the ones returned by the input function
+    ///
+    pub fn map_files<F: FnMut(&Arc<File>) -> Arc<File>>(&self, mut f: F) -> Operation {
+        let op_type = match &self.op_type {
+            OperationType::Read(file, offset, len) => OperationType::Read(f(file), *offset, *len),
+            OperationType::Write(file, offset, len, content) => {
+                OperationType::Write(f(file), *offset, *len, content.clone())
+            }
+            OperationType::Mkdir(file, mode) => OperationType::Mkdir(f(file), mode.clone()),
+            OperationType::Mknod(file) => OperationType::Mknod(f(file)),
+            OperationType::Remove(file) => OperationType::Remove(f(file)),
+            OperationType::Rename(file, to) => OperationType::Rename(f(file), to.clone()),
+            OperationType::OpenAt(file, offset) => OperationType::OpenAt(f(file), *offset),
+            OperationType::Truncate(file) => OperationType::Truncate(f(file)),
+            OperationType::Stat(file) => OperationType::Stat(f(file)),
+            OperationType::Fstat(file) => OperationType::Fstat(f(file)),
+            OperationType::Statx(file) => OperationType::Statx(f(file)),
+            OperationType::StatFS(file) => OperationType::StatFS(f(file)),
+            OperationType::Fstatat(file) => OperationType::Fstatat(f(file)),
+            OperationType::Map(file, offset, len, prot, flags) => {
+                OperationType::Map(f(file), *offset, *len, prot.clone(), flags.clone())
+            }
+            OperationType::Sync(file) => OperationType::Sync(f(file)),
+            OperationType::Copy(from, from_offset, to, to_offset, len) => {
+                OperationType::Copy(f(from), *from_offset, f(to), *to_offset, *len)
+            }
+            op_type => op_type.clone(),
+        };
+
+        Operation::new(op_type).with_timing(self.timestamp, self.duration)
+    }
+
+    ///
+    /// Get the files accessed by this operation, each labeled with the kind of access that
+    /// relates the process to the file in the dependency graph
+    ///
+    pub fn accesses(&self) -> Vec<(Arc<File>, String)> {
+        match &self.op_type {
+            OperationType::Map(file, _, _, prot, flags) => {
+                // changes to a shared, writable mapping are carried through to the file
+                if flags.contains("MAP_SHARED") && prot.contains("PROT_WRITE") {
+                    vec![(file.clone(), "Write".to_string())]
+                } else {
+                    vec![(file.clone(), self.name())]
+                }
+            }
+            OperationType::Copy(from, _, to, _, _) => {
+                // the data moves from the source to the destination without passing through
+                // the process, but it still depends on the source and modifies the destination
+                vec![
+                    (from.clone(), "Read".to_string()),
+                    (to.clone(), "Write".to_string()),
+                ]
+            }
+            _ => match self.file() {
+                Some(file) => vec![(file, self.name())],
+                None => vec![],
+            },
+        }
+    }
+
     ///
     /// Name of the operation
     ///
@@ -159,6 +271,9 @@ impl Operation {
             &OperationType::Rename(_, _) => "Rename".to_string(),
             &OperationType::Clone(_) => "Clone".to_string(),
             &OperationType::Chdir(_) => "Chdir".to_string(),
+            &OperationType::Map(_, _, _, _, _) => "Map".to_string(),
+            &OperationType::Sync(_) => "Sync".to_string(),
+            &OperationType::Copy(_, _, _, _, _) => "Copy".to_string(),
             &OperationType::NoOp => "NoOp".to_string(),
         }
     }
@@ -191,6 +306,17 @@ impl fmt::Display for OperationType {
             }
             &OperationType::Clone(ref pid) => write!(f, "clone({})", pid),
             &OperationType::Chdir(ref path) => write!(f, "chdir({})", path),
+            OperationType::Map(file, offset, len, prot, flags) => {
+                write!(f, "map({}, {}, {}, {}, {})", file, offset, len, prot, flags)
+            }
+            OperationType::Sync(file) => write!(f, "sync({})", file),
+            OperationType::Copy(from, from_offset, to, to_offset, len) => {
+                write!(
+                    f,
+                    "copy({}, {}, {}, {}, {})",
+                    from, from_offset, to, to_offset, len
+                )
+            }
             &OperationType::NoOp => write!(f, "no-op"),
         }
     }
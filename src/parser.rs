@@ -1,14 +1,19 @@
 use crate::deps::DependencyGraph;
 use crate::error::Error;
-use crate::file::File;
-use crate::op::Operation;
+use crate::file::{File, FileKind};
+use crate::filter::Filter;
+use crate::op::{Operation, OperationType};
+use crate::path::{fd_annotation, join, normalize, normalize_relative};
 use crate::process::Process;
+use crate::rules::PathRules;
+use crate::snapshot::Snapshot;
 use regex::Regex;
 use std::collections::{HashMap, HashSet};
 use std::fmt::Formatter;
 use std::io::{BufRead, BufReader};
 use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::Duration;
 
 pub struct Parser {
     log_file: PathBuf,
@@ -18,6 +23,24 @@ pub struct Parser {
     existing_files: HashSet<FileType>, // keep existing files info
     accessed_files: HashMap<String, Arc<File>>, // all the files and directories accessed by processes
     ongoing_ops: HashMap<String, String>, // keeping the unfinished operations for each process
+    mapped_regions: HashMap<(usize, usize), MappedRegion>, // a map from process's mapped address to a file region
+    descriptors: usize, // the number of pipes, sockets, and other non-file descriptors created so far
+    fd_discrepancies: Vec<FdDiscrepancy>, // the fds whose tracked path differ from the path decorated by strace
+    unresolved_paths: Vec<UnresolvedPath>, // the relative paths passed with an unknown directory fd
+    identities: HashMap<String, String>, // a map from a path to another path of the same file, like a symlink to its target
+    inodes: HashMap<String, String>, // a map from the device and inode numbers of a file to the first path seen with them
+    generations: HashMap<String, usize>, // the number of times a path stopped naming a file, after removing or renaming it
+    initial_files: HashMap<String, FileType>, // the files and directories that existed before the trace started
+    created_files: HashSet<String>,           // the paths created during the trace
+    modified_files: HashSet<String>,          // the existing files modified during the trace
+    removed_files: HashSet<String>, // the paths of the files above that were removed or renamed since
+    parents: HashMap<usize, usize>, // the process each process is cloned from, even if clone isn't kept
+    started: HashMap<usize, Duration>, // the timestamp of the unfinished operation of each process
+    last_timestamp: Duration, // the last timestamp seen, to detect the wall clock passing midnight
+    days: u64,                // the number of times the wall clock passed midnight
+    filter: Option<Filter>,   // the operations kept in the dependency graph, or all if none
+    rules: PathRules,         // the paths excluded from the trace, and how the paths are rewritten
+    syscalls: Option<HashSet<String>>, // the system calls whose operations are kept, or all if none
 }
 
 #[derive(Debug, Clone, Eq, Hash, PartialEq)]
@@ -55,6 +78,100 @@ impl std::fmt::Display for FileType {
     }
 }
 
+///
+/// A file descriptor whose path, as tracked by the parser, differs from the path strace
+/// decorated it with (strace -y)
+///
+#[derive(Debug, Clone, Eq, Hash, PartialEq)]
+pub struct FdDiscrepancy {
+    pid: usize,
+    fd: i32,
+    tracked: String,
+    decorated: String,
+}
+
+impl FdDiscrepancy {
+    pub fn new(pid: usize, fd: i32, tracked: String, decorated: String) -> Self {
+        FdDiscrepancy {
+            pid,
+            fd,
+            tracked,
+            decorated,
+        }
+    }
+
+    pub fn pid(&self) -> usize {
+        self.pid
+    }
+
+    pub fn fd(&self) -> i32 {
+        self.fd
+    }
+
+    pub fn tracked(&self) -> &str {
+        &self.tracked
+    }
+
+    pub fn decorated(&self) -> &str {
+        &self.decorated
+    }
+}
+
+impl std::fmt::Display for FdDiscrepancy {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "process {} fd {} is tracked as {}, but strace decorated it as {}",
+            self.pid, self.fd, self.tracked, self.decorated
+        )
+    }
+}
+
+///
+/// A relative path passed with a directory file descriptor the parser does not know, and
+/// strace did not decorate, so the path is left relative
+///
+#[derive(Debug, Clone, Eq, Hash, PartialEq)]
+pub struct UnresolvedPath {
+    pid: usize,
+    dirfd: i32,
+    path: String,
+}
+
+impl UnresolvedPath {
+    pub fn new(pid: usize, dirfd: i32, path: String) -> Self {
+        UnresolvedPath { pid, dirfd, path }
+    }
+
+    pub fn pid(&self) -> usize {
+        self.pid
+    }
+
+    pub fn dirfd(&self) -> i32 {
+        self.dirfd
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+}
+
+impl std::fmt::Display for UnresolvedPath {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "process {} used the unknown directory fd {} for {}, so it is left relative",
+            self.pid, self.dirfd, self.path
+        )
+    }
+}
+
+// a file descriptor with the path strace decorated it with, if there is any
+type DecoratedFd = (i32, Option<String>);
+
+// the timestamp of an operation, and the time spent in it, if strace printed them
+type Timing = (Option<Duration>, Option<Duration>);
+
 #[derive(Debug, PartialEq)]
 enum Parts {
     Unfinished(usize, String),
@@ -74,6 +191,18 @@ impl OpenedFile {
     }
 }
 
+#[derive(Debug)]
+struct MappedRegion {
+    path: String,
+    len: usize,
+}
+
+impl MappedRegion {
+    pub fn new(path: String, len: usize) -> Self {
+        MappedRegion { path, len }
+    }
+}
+
 impl Parser {
     pub fn new(log_file: PathBuf) -> Self {
         Parser {
@@ -84,9 +213,56 @@ impl Parser {
             existing_files: HashSet::new(),
             accessed_files: HashMap::new(),
             ongoing_ops: HashMap::new(),
+            mapped_regions: HashMap::new(),
+            descriptors: 0,
+            fd_discrepancies: vec![],
+            unresolved_paths: vec![],
+            identities: HashMap::new(),
+            inodes: HashMap::new(),
+            generations: HashMap::new(),
+            initial_files: HashMap::new(),
+            created_files: HashSet::new(),
+            modified_files: HashSet::new(),
+            removed_files: HashSet::new(),
+            parents: HashMap::new(),
+            started: HashMap::new(),
+            last_timestamp: Duration::ZERO,
+            days: 0,
+            filter: None,
+            rules: PathRules::new(),
+            syscalls: None,
         }
     }
 
+    ///
+    /// Keep only the operations of the system calls, like openat or read. The other system calls
+    /// are still parsed to track the file descriptors and the files
+    ///
+    pub fn set_syscalls<S: AsRef<str>>(&mut self, syscalls: &[S]) {
+        self.syscalls = Some(
+            syscalls
+                .iter()
+                .map(|syscall| syscall.as_ref().to_string())
+                .collect(),
+        );
+    }
+
+    ///
+    /// Drop the operations on the paths the rules exclude, and rewrite the paths of the parsed
+    /// files and of the snapshot
+    ///
+    pub fn set_rules(&mut self, rules: PathRules) {
+        self.rules = rules;
+    }
+
+    ///
+    /// Keep only the operations the filter selects in the dependency graph. The snapshot still
+    /// has all the files that existed before the trace started
+    ///
+    pub fn set_filter(&mut self, filter: Filter) {
+        self.filter = Some(filter);
+    }
+
     pub fn parse(&mut self) -> Result<DependencyGraph, Box<dyn std::error::Error>> {
         let mut processes: Vec<Process> = vec![];
 
@@ -101,10 +277,12 @@ impl Parser {
                 return Err(Box::new(Error::ParseError(line.to_string())));
             }
 
+            // strip the timestamp and the time spent in the operation, if strace printed them
+            let (line, (timestamp, duration)) = self.timing(&line)?;
+
             // filter out the operations
             if line.contains("= -1") || // ops with error result
                 line.starts_with("close") || // close op
-                line.starts_with("readlink") || // readlink op
                 line.contains("--- ") ||
                 line.contains("+++ exited")
             {
@@ -112,8 +290,20 @@ impl Parser {
             }
 
             match self.parts(&line)? {
-                Parts::Unfinished(_, _) => continue,
+                Parts::Unfinished(pid, _) => {
+                    // the operation started when the unfinished line was logged
+                    if let Some(timestamp) = timestamp {
+                        self.started.insert(pid, timestamp);
+                    }
+                    continue;
+                }
                 Parts::Finished(pid, op, args, ret) => {
+                    let timestamp = if line.contains("resumed") {
+                        self.started.remove(&pid).or(timestamp)
+                    } else {
+                        timestamp
+                    };
+
                     if processes.iter().find(|p| p.pid() == pid).is_none() {
                         // generate the process for the first time and add it to the list of processes
                         processes.push(Process::new(pid));
@@ -124,70 +314,136 @@ impl Parser {
                         .find(|p| p.pid() == pid)
                         .ok_or(Error::NotFound(format!("pid {}", pid)))?;
 
-                    match op.as_ref() {
-                        "openat" => {
-                            for operation in self.openat(pid, args, ret)? {
-                                process.add_op(operation.into());
-                            }
-                        }
+                    let operations = match op.as_ref() {
+                        "openat" => self.openat(pid, args, ret)?,
                         "fcntl" => {
-                            process.add_op(self.fcntl(pid, args, ret)?.into());
+                            vec![self.fcntl(pid, args, ret)?]
                         }
                         "read" => {
                             // read op updates the file offset
-                            process.add_op(self.read(pid, args)?.into());
+                            vec![self.read(pid, args)?]
                         }
                         "stat" => {
-                            process.add_op(self.stat(args)?.into());
+                            vec![self.stat(pid, args)?]
                         }
                         "fstat" => {
-                            process.add_op(self.fstat(pid, args)?.into());
+                            vec![self.fstat(pid, args)?]
                         }
                         "statx" => {
-                            process.add_op(self.statx(pid, args)?.into());
+                            vec![self.statx(pid, args)?]
                         }
                         "statfs" => {
-                            process.add_op(self.statfs(args)?.into());
+                            vec![self.statfs(pid, args)?]
                         }
                         op if op == "fstatat64" || op == "newfstatat" || op == "fstatat" => {
-                            process.add_op(self.fstatat(pid, args)?.into());
+                            vec![self.fstatat(pid, args)?]
                         }
                         "pread" => {
-                            process.add_op(self.pread(pid, args)?.into());
+                            vec![self.pread(pid, args)?]
                         }
                         "getrandom" => {
-                            process.add_op(self.get_random(args)?.into());
+                            vec![self.get_random(args)?]
                         }
                         "write" => {
-                            process.add_op(self.write(pid, args)?.into());
+                            vec![self.write(pid, args)?]
                         }
                         "mkdir" => {
-                            process.add_op(self.mkdir(args)?.into());
+                            vec![self.mkdir(pid, args)?]
                         }
                         "unlinkat" => {
-                            process.add_op(self.unlink(pid, args)?.into());
+                            vec![self.unlink(pid, args)?]
                         }
                         "rename" => {
-                            process.add_op(self.rename(args)?.into());
+                            vec![self.rename(pid, args)?]
                         }
                         op if op == "renameat" || op == "renameat2" => {
-                            process.add_op(self.renameat(pid, args)?.into());
+                            vec![self.renameat(pid, args)?]
                         }
-                        "clone" => {
-                            process.add_op(self.clone(ret)?.into());
+                        op if op == "clone" || op == "clone3" || op == "fork" || op == "vfork" => {
+                            vec![self.clone(pid, ret)?]
                         }
                         "chdir" => {
-                            process.add_op(self.chdir(pid, args)?.into());
+                            vec![self.chdir(pid, args)?]
+                        }
+                        "fchdir" => {
+                            vec![self.fchdir(pid, args)?]
                         }
                         "getcwd" => {
-                            process.add_op(self.getcwd(args)?.into());
+                            vec![self.getcwd(pid, args)?]
+                        }
+                        op if op == "mmap" || op == "mmap2" => self.mmap(pid, args, ret)?,
+                        "munmap" => self.munmap(pid, args)?,
+                        "msync" => {
+                            vec![self.msync(pid, args)?]
+                        }
+                        op if op == "dup" || op == "dup2" || op == "dup3" => {
+                            vec![self.dup(pid, args, ret)?]
+                        }
+                        op if op == "pipe" || op == "pipe2" => {
+                            vec![self.pipe(pid, args)?]
                         }
-                        _ => {}
+                        "socketpair" => {
+                            vec![self.socketpair(pid, args)?]
+                        }
+                        "socket" => {
+                            vec![self.socket(pid, ret)?]
+                        }
+                        "connect" => {
+                            vec![self.connect(pid, args)?]
+                        }
+                        op if op == "accept" || op == "accept4" => {
+                            vec![self.accept(pid, ret)?]
+                        }
+                        op if op == "eventfd" || op == "eventfd2" => {
+                            vec![self.eventfd(pid, ret)?]
+                        }
+                        "memfd_create" => {
+                            vec![self.memfd_create(pid, ret)?]
+                        }
+                        "copy_file_range" => {
+                            vec![self.copy_file_range(pid, args, ret)?]
+                        }
+                        op if op == "sendfile" || op == "sendfile64" => {
+                            vec![self.sendfile(pid, args, ret)?]
+                        }
+                        "splice" => {
+                            vec![self.splice(pid, args, ret)?]
+                        }
+                        "readlink" => {
+                            vec![self.readlink(pid, args)?]
+                        }
+                        "readlinkat" => {
+                            vec![self.readlinkat(pid, args)?]
+                        }
+                        _ => vec![],
+                    };
+
+                    if let Some(syscalls) = &self.syscalls {
+                        if !syscalls.contains(&op) {
+                            continue;
+                        }
+                    }
+
+                    for operation in operations {
+                        let operation = match self.kept(operation) {
+                            Some(operation) => operation,
+                            None => continue,
+                        };
+                        self.observe(&operation);
+                        process.add_op(operation.with_timing(timestamp, duration));
                     }
                 }
             }
         }
 
+        self.canonicalize(&mut processes)?;
+        self.rewrite(&mut processes)?;
+
+        // the ancestors are known even when the system calls kept leave out the clones
+        if let Some(filter) = &self.filter {
+            processes = filter.apply_with_parents(processes, self.parents.clone());
+        }
+
         let dep_graph = DependencyGraph::new(processes)?.order()?;
 
         Ok(dep_graph)
@@ -223,38 +479,16 @@ impl Parser {
         //                   open to fail.  This flag was added in kernel version 2.1.126, to avoid
         //                   denial-of-service problems if opendir(3) is called on a FIFO or tape device.
 
-        let fd = ret.trim().parse::<i32>()?;
+        let (fd, decorated) = fd_annotation(&ret)?;
 
         // extract the path from input arguments
-        let mut path = self.path(&args, "openat")?;
-
-        if !PathBuf::from(path.clone()).is_absolute() {
-            // extract the dirfd
-            let dirfd = args
-                .split_at(
-                    args.find(",")
-                        .ok_or(Error::NotFound(", from openat line".to_string()))?,
-                )
-                .0;
-            if dirfd.contains("AT_FDCWD") {
-                // get the current working directory of the calling process
-                let cwd = match self.process_cwd.get(&pid) {
-                    Some(cwd) => cwd,
-                    None => {
-                        // there is no cwd set for the calling process, so its cwd is the global cwd
-                        self.cwd.as_str()
-                    }
-                };
-                path = if cwd.ends_with("/") {
-                    format!("{}{}", cwd, path)
-                } else {
-                    format!("{}/{}", cwd, path)
-                };
-            }
-            if !dirfd.contains("AT_FDCWD") {
-                // dirfd should be a valid file descriptor, so the input path is a relative path.
-                path = self.relative_to_absolute(pid, dirfd, &path)?;
-            }
+        let path = self.path(&args, "openat")?;
+        let dirfd = self.dirfd(&args, "openat")?;
+        let mut path = self.resolve(pid, Some(dirfd), &path)?;
+
+        if let Some(decorated) = decorated {
+            // the path decorated by strace is the ground truth for the opened file
+            path = self.correct(pid, fd, Some(path), decorated);
         }
 
         let flags_mode = args
@@ -280,6 +514,8 @@ impl Parser {
         let mut operations = vec![];
 
         if flags.contains("O_DIRECTORY") {
+            self.initial_file(FileType::Dir(path.clone(), 0));
+
             // the path is a directory, so don't check other file-related flags
             operations.push(Operation::open_at(self.file(&path).clone(), 0));
             // the opened file is a directory with no size and offset
@@ -288,7 +524,8 @@ impl Parser {
             return Ok(operations);
         }
 
-        if flags.contains("O_CREAT") {
+        // opening an existing file with O_CREAT doesn't create it
+        if flags.contains("O_CREAT") && !self.exists(&path) {
             operations.push(Operation::mknod(self.file(&path).clone()));
         }
 
@@ -337,12 +574,42 @@ impl Parser {
 
         // extract the path from input arguments
         let path = self.path(&args, "chdir")?;
+        let path = self.resolve(pid, None, &path)?;
         self.process_cwd.insert(pid, path.clone());
 
         Ok(Operation::chdir(path))
     }
 
-    fn getcwd(&mut self, args: String) -> Result<Operation, Box<dyn std::error::Error>> {
+    fn fchdir(
+        &mut self,
+        pid: usize,
+        args: String,
+    ) -> Result<Operation, Box<dyn std::error::Error>> {
+        //
+        // int fchdir(int fd);
+        // identical to chdir(), the only difference is that the directory is given as an open
+        // file descriptor.
+        //
+
+        let fd = self.fd(pid, &args)?;
+
+        // find the directory path based on the file descriptor
+        match self.fd_map.get(&(pid, fd)) {
+            Some(opened_file) => {
+                let path = opened_file.path.clone();
+                self.process_cwd.insert(pid, path.clone());
+
+                Ok(Operation::chdir(path))
+            }
+            None => Ok(Operation::no_op()),
+        }
+    }
+
+    fn getcwd(
+        &mut self,
+        pid: usize,
+        args: String,
+    ) -> Result<Operation, Box<dyn std::error::Error>> {
         //
         // char *getcwd(char *buf, size_t size);
         // These  functions return a null-terminated string containing an absolute pathname that is
@@ -356,6 +623,7 @@ impl Parser {
 
         // extract the path from input arguments
         let path = self.path(&args, "getcwd")?;
+        self.process_cwd.insert(pid, path.clone());
         self.cwd = path;
 
         Ok(Operation::no_op())
@@ -384,11 +652,15 @@ impl Parser {
         //  - If the flag is 'F_SETFD', the file descriptor fd is set to the value specified by arg.
         //
 
-        // the returned file descriptor is after '='
-        let new_fd = ret.trim().parse::<i32>()?;
+        // the returned file descriptor is after '='. Some commands, like F_GETFL, return
+        // hexadecimal flags instead
+        let new_fd = match fd_annotation(&ret) {
+            Ok((new_fd, _)) => new_fd,
+            Err(_) => self.number(&ret)? as i32,
+        };
 
         let parts: Vec<&str> = args.split(",").collect();
-        let fd = parts[0].trim().parse::<i32>()?;
+        let fd = self.fd(pid, parts[0])?;
 
         if parts
             .iter()
@@ -423,7 +695,7 @@ impl Parser {
         //
 
         let parts: Vec<&str> = args.split(",").collect();
-        let fd = parts[0].trim().parse::<i32>()?;
+        let fd = self.fd(pid, parts[0])?;
         let _buf = parts[1].trim().to_string();
         let len = parts[parts.len() - 1].trim().parse::<usize>()?;
 
@@ -452,7 +724,7 @@ impl Parser {
     }
 
     // parse a stat line
-    fn stat(&mut self, args: String) -> Result<Operation, Box<dyn std::error::Error>> {
+    fn stat(&mut self, pid: usize, args: String) -> Result<Operation, Box<dyn std::error::Error>> {
         // int stat(const char *pathname, struct stat *statbuf);
         // display file or file system status.
         //
@@ -463,12 +735,15 @@ impl Parser {
 
         // extract the path from input arguments
         let path = self.path(&args, "stat")?;
+        let path = self.resolve(pid, None, &path)?;
 
         let file_type = self.file_type(&args, &path, &format!("stat: {}", args))?;
         if file_type == FileType::Other {
             return Ok(Operation::no_op());
         } else {
+            self.initial_file(file_type.clone());
             self.existing_files.insert(file_type);
+            self.inode(&args, &path)?;
             Ok(Operation::stat(self.file(&path).clone()))
         }
     }
@@ -486,7 +761,7 @@ impl Parser {
         let fd = args
             .split_at(args.find(",").ok_or(Error::NotFound("(".to_string()))?)
             .0;
-        let fd = fd.trim().parse::<i32>()?;
+        let fd = self.fd(pid, fd)?;
 
         // find the path based on the file descriptor
         match self.fd_map.get(&(pid, fd)) {
@@ -497,7 +772,9 @@ impl Parser {
                 if file_type == FileType::Other {
                     Ok(Operation::no_op())
                 } else {
+                    self.initial_file(file_type.clone());
                     self.existing_files.insert(file_type);
+                    self.inode(&args, &path)?;
                     Ok(Operation::fstat(self.file(&path).clone()))
                 }
             }
@@ -525,27 +802,17 @@ impl Parser {
         // the file information such as stx_size is available between {}
 
         // extract the path from input arguments
-        let mut path = self.path(&args, "statx")?;
-
-        if !PathBuf::from(path.clone()).is_absolute() {
-            // extract the dirfd
-            let dirfd = args
-                .split_at(
-                    args.find(",")
-                        .ok_or(Error::NotFound(", from openat line".to_string()))?,
-                )
-                .0;
-            if !dirfd.contains("AT_FDCWD") {
-                // dirfd should be a valid file descriptor, so the input path is a relative path.
-                path = self.relative_to_absolute(pid, dirfd, &path)?;
-            }
-        }
+        let path = self.path(&args, "statx")?;
+        let dirfd = self.dirfd(&args, "statx")?;
+        let path = self.resolve(pid, Some(dirfd), &path)?;
 
         let file_type = self.file_type(&args, &path, &format!("statx: {}", args))?;
         if file_type == FileType::Other {
             Ok(Operation::no_op())
         } else {
+            self.initial_file(file_type.clone());
             self.existing_files.insert(file_type);
+            self.inode(&args, &path)?;
             Ok(Operation::statx(self.file(&path).clone()))
         }
     }
@@ -574,33 +841,27 @@ impl Parser {
         // the file information such as st_size is available between {}
 
         // extract the path from input arguments
-        let mut path = self.path(&args, "fstatat")?;
-
-        if !PathBuf::from(path.clone()).is_absolute() {
-            // extract the dirfd
-            let dirfd = args
-                .split_at(
-                    args.find(",")
-                        .ok_or(Error::NotFound(", from openat line".to_string()))?,
-                )
-                .0;
-            if !dirfd.contains("AT_FDCWD") {
-                // dirfd should be a valid file descriptor, so the input path is a relative path.
-                path = self.relative_to_absolute(pid, dirfd, &path)?;
-            }
-        }
+        let path = self.path(&args, "fstatat")?;
+        let dirfd = self.dirfd(&args, "fstatat")?;
+        let path = self.resolve(pid, Some(dirfd), &path)?;
 
         let file_type = self.file_type(&args, &path, &format!("fstatat: {}", args))?;
         if file_type == FileType::Other {
             Ok(Operation::no_op())
         } else {
+            self.initial_file(file_type.clone());
             self.existing_files.insert(file_type);
+            self.inode(&args, &path)?;
             Ok(Operation::fstatat(self.file(&path).clone()))
         }
     }
 
     // parse a statfs line
-    fn statfs(&mut self, args: String) -> Result<Operation, Box<dyn std::error::Error>> {
+    fn statfs(
+        &mut self,
+        pid: usize,
+        args: String,
+    ) -> Result<Operation, Box<dyn std::error::Error>> {
         // int statfs(const char *path, struct statfs *buf);
         // returns information about a mounted filesystem
         //
@@ -614,6 +875,7 @@ impl Parser {
 
         // extract the path from input arguments
         let path = self.path(&args, "statfs")?;
+        let path = self.resolve(pid, None, &path)?;
 
         Ok(Operation::statfs(self.file(&path).clone()))
     }
@@ -630,7 +892,7 @@ impl Parser {
         // the operation reads len bytes from input offset and does not change the opened file offset after read
 
         let parts: Vec<&str> = args.split(",").collect();
-        let fd = parts[0].trim().parse::<i32>()?;
+        let fd = self.fd(pid, parts[0])?;
         let _buf = parts[1].trim().to_string();
         let len = parts[2].trim().parse::<usize>()?;
         let offset = parts[parts.len() - 1].trim().parse::<i32>()?;
@@ -659,15 +921,10 @@ impl Parser {
         //  write(fd, "a-string", len) = write_len
 
         let parts: Vec<&str> = args.split(",").collect();
-        let fd = parts[0].trim().parse::<i32>()?;
+        let fd = self.fd(pid, parts[0])?;
         let content = parts[1].trim().to_string();
         let len = parts[parts.len() - 1].trim().parse::<usize>()?;
 
-        if fd == 0 || fd == 1 || fd == 2 {
-            // write to stdin, or stdout, or stderr
-            return Ok(Operation::no_op());
-        }
-
         // find the write path based on the file descriptor
         match self.fd_map.get(&(pid, fd)) {
             Some(of) => {
@@ -687,14 +944,14 @@ impl Parser {
             }
             None => {
                 // as the file descriptor does not exist, the write operation is probably writing
-                // to the STDOUT, so do not generate an operation
+                // to the inherited STDOUT or STDERR, so do not generate an operation
                 Ok(Operation::no_op())
             }
         }
     }
 
     // parse a write line
-    fn mkdir(&mut self, args: String) -> Result<Operation, Box<dyn std::error::Error>> {
+    fn mkdir(&mut self, pid: usize, args: String) -> Result<Operation, Box<dyn std::error::Error>> {
         // int mkdir(const char *pathname, mode_t mode);
         // attempts to create a directory named pathname.
         //
@@ -703,6 +960,7 @@ impl Parser {
 
         // extract the path from input arguments
         let path = self.path(&args, "mkdir")?;
+        let path = self.resolve(pid, None, &path)?;
 
         let mode = args
             .split_at(args.rfind(",").ok_or(Error::NotFound("\"".to_string()))? + 1)
@@ -736,27 +994,26 @@ impl Parser {
         //
 
         // extract the path from input arguments
-        let mut path = self.path(&args, "unlink")?;
-
-        if !PathBuf::from(path.clone()).is_absolute() {
-            // extract the dirfd
-            let dirfd = args
-                .split_at(
-                    args.find(",")
-                        .ok_or(Error::NotFound(", from openat line".to_string()))?,
-                )
-                .0;
-            if !dirfd.contains("AT_FDCWD") {
-                // dirfd should be a valid file descriptor, so the input path is a relative path.
-                path = self.relative_to_absolute(pid, dirfd, &path)?;
-            }
+        let path = self.path(&args, "unlink")?;
+        let dirfd = self.dirfd(&args, "unlink")?;
+        let path = self.resolve(pid, Some(dirfd), &path)?;
+
+        if args.contains("AT_REMOVEDIR") {
+            self.initial_file(FileType::Dir(path.clone(), 0));
         }
 
-        Ok(Operation::remove(self.file(&path).clone()))
+        let operation = Operation::remove(self.file(&path).clone());
+        self.remove_path(&path);
+
+        Ok(operation)
     }
 
     // parse a rename line
-    fn rename(&mut self, args: String) -> Result<Operation, Box<dyn std::error::Error>> {
+    fn rename(
+        &mut self,
+        pid: usize,
+        args: String,
+    ) -> Result<Operation, Box<dyn std::error::Error>> {
         // int rename(const char *oldpath, const char *newpath);
         // renames  a  file,  moving it between directories if required.  Any other hard links to
         // the file (as created using link(2)) are unaffected.  Open file descriptors for oldpath
@@ -772,9 +1029,14 @@ impl Parser {
         );
 
         let old = self.path(&old, "rename")?;
+        let old = self.resolve(pid, None, &old)?;
         let new = self.path(&new, "rename")?;
+        let new = self.resolve(pid, None, &new)?;
+
+        let operation = Operation::rename(self.file(&old).clone(), new.clone());
+        self.move_path(&old, &new);
 
-        Ok(Operation::rename(self.file(&old).clone(), new))
+        Ok(operation)
     }
 
     // parse a renameat line
@@ -801,23 +1063,18 @@ impl Parser {
         let parts: Vec<&str> = args.split(",").collect();
         let dirfd1 = parts[0];
         let old = parts[1];
-        let mut old = self.path(&old, "renameat")?;
+        let old = self.path(old, "renameat")?;
+        let old = self.resolve(pid, Some(dirfd1), &old)?;
 
         let dirfd2 = parts[2];
         let new = parts[3];
-        let mut new = self.path(&new, "renameat")?;
-
-        if !PathBuf::from(old.clone()).is_absolute() && !dirfd1.contains("AT_FDCWD") {
-            // dirfd should be a valid file descriptor, so the input path is a relative path.
-            old = self.relative_to_absolute(pid, dirfd1, &old)?;
-        }
+        let new = self.path(new, "renameat")?;
+        let new = self.resolve(pid, Some(dirfd2), &new)?;
 
-        if !PathBuf::from(new.clone()).is_absolute() && !dirfd2.contains("AT_FDCWD") {
-            // dirfd should be a valid file descriptor, so the input path is a relative path.
-            new = self.relative_to_absolute(pid, dirfd2, &new)?;
-        }
+        let operation = Operation::rename(self.file(&old).clone(), new.clone());
+        self.move_path(&old, &new);
 
-        Ok(Operation::rename(self.file(&old).clone(), new))
+        Ok(operation)
     }
 
     // parse a getrandom line
@@ -837,7 +1094,7 @@ impl Parser {
     }
 
     // parse a clone line
-    fn clone(&mut self, ret: String) -> Result<Operation, Box<dyn std::error::Error>> {
+    fn clone(&mut self, pid: usize, ret: String) -> Result<Operation, Box<dyn std::error::Error>> {
         // int clone(int (*fn)(void *), void *stack, int flags, void *arg, ...
         //                  /* pid_t *parent_tid, void *tls, pid_t *child_tid */ );
         // create a new ("child") process, in a manner similar to fork(2).
@@ -847,400 +1104,2192 @@ impl Parser {
         //        flags=CLONE_VM|CLONE_FS|CLONE_FILES|CLONE_SIGHAND|CLONE_THREAD|CLONE_SYSVSEM|
         //        CLONE_SETTLS|CLONE_PARENT_SETTID|CLONE_CHILD_CLEARTID,
         //        parent_tid=[909193], tls=0x7fb239307700, child_tidptr=0x7fb2393079d0) = 909193
+        //
+        // The child process inherits the file descriptors and the current working directory of
+        // the parent, so the pipes created by the parent can connect it to the child.
 
         let ret = ret.trim().parse::<usize>()?;
-        Ok(Operation::clone_op(ret))
-    }
+        self.parents.insert(ret, pid);
 
-    fn file(&mut self, path: &str) -> Arc<File> {
-        match self.accessed_files.get(path) {
-            Some(f) => f.clone(),
-            None => {
-                let file = Arc::new(File::new(path));
-                self.accessed_files.insert(path.to_string(), file.clone());
-                file
-            }
+        let inherited = self
+            .fd_map
+            .iter()
+            .filter(|((fd_pid, _), _)| *fd_pid == pid)
+            .map(|((_, fd), of)| (*fd, OpenedFile::new(of.path.clone(), of.offset, of.size)))
+            .collect::<Vec<_>>();
+        for (fd, opened_file) in inherited {
+            // the child may have already opened its own files
+            self.fd_map.entry((ret, fd)).or_insert(opened_file);
+        }
+
+        // the child also starts in the current working directory of the parent
+        if let Some(cwd) = self.process_cwd.get(&pid).cloned() {
+            self.process_cwd.entry(ret).or_insert(cwd);
         }
+
+        Ok(Operation::clone_op(ret))
     }
 
-    // extract process id, operation name, input args in-between ( and ), and return value from the input string
-    fn parts(&mut self, str: &str) -> Result<Parts, Box<dyn std::error::Error>> {
-        // If a system call is being executed and meanwhile another one is being called from a
-        // different thread/process then strace will try to preserve the  order  of  those events
-        // and mark the ongoing call as being unfinished.
-        // When the call returns it will be marked as resumed.
+    // parse a dup, dup2, or dup3 line
+    fn dup(
+        &mut self,
+        pid: usize,
+        args: String,
+        ret: String,
+    ) -> Result<Operation, Box<dyn std::error::Error>> {
+        // int dup(int oldfd);
+        // int dup2(int oldfd, int newfd);
+        // int dup3(int oldfd, int newfd, int flags);
+        // allocate a new file descriptor that refers to the same open file description as oldfd.
+        //
+        // Example:
+        //      dup2(7, 1) = 1
+        //
+        // A shell pipeline uses dup2 to move the pipe ends to STDIN and STDOUT.
 
-        if str.contains("unfinished") {
-            let re = Regex::new(r"^(?P<pid>\d+) (?P<remaining>.+)$")?;
-            assert!(re.is_match(str));
+        let (new_fd, _) = fd_annotation(&ret)?;
 
-            let cap = re.captures(str).ok_or(Error::ParseError(str.to_string()))?;
+        let parts: Vec<&str> = args.split(",").collect();
+        let fd = self.fd(pid, parts[0])?;
 
-            let pid = cap["pid"].parse::<usize>()?;
-            let unfinished_line = cap["remaining"].to_string();
+        if let Some(fd_of) = self.fd_map.get(&(pid, fd)) {
+            let fd_path = fd_of.path.clone();
+            let offset = fd_of.offset;
+            let size = fd_of.size;
 
-            let unfinished_re = Regex::new(r"^(?P<op>[^\(]*)\((?P<args>.*) <unfinished ...>$")?;
-            assert!(unfinished_re.is_match(&unfinished_line));
+            // add the duplicated fd to the map
+            self.fd_map
+                .insert((pid, new_fd), OpenedFile::new(fd_path, offset, size));
+        } else {
+            // the new fd does not refer to the previously opened file anymore
+            self.fd_map.remove(&(pid, new_fd));
+        }
 
-            let cap = unfinished_re
-                .captures(&unfinished_line)
-                .ok_or(Error::ParseError(str.to_string()))?;
+        Ok(Operation::no_op())
+    }
 
-            let unfinished_op = cap["op"].to_string();
+    // parse a pipe or pipe2 line
+    fn pipe(&mut self, pid: usize, args: String) -> Result<Operation, Box<dyn std::error::Error>> {
+        // int pipe(int pipefd[2]);
+        // int pipe2(int pipefd[2], int flags);
+        // creates a pipe, a unidirectional data channel that can be used for interprocess
+        // communication. pipefd[0] refers to the read end of the pipe. pipefd[1] refers to the
+        // write end of the pipe.
+        //
+        // Example:
+        //      pipe2([6, 7], O_CLOEXEC) = 0
+        //
+        // Both ends refer to the same pipe, so the writer and the reader of the pipe access the
+        // same file.
 
-            // keep the unfinished line until we see the resumed line
-            self.ongoing_ops.insert(
-                format!("{}:{}", pid, unfinished_op),
-                unfinished_line.clone(),
-            );
+        let fds = self.fd_pair(&args, "pipe")?;
+        let path = self.pair_path(&fds, FileKind::Pipe);
+        for (fd, _) in fds {
+            self.fd_map
+                .insert((pid, fd), OpenedFile::new(path.clone(), 0, 0));
+        }
 
-            return Ok(Parts::Unfinished(pid, unfinished_line));
-        } else if str.contains("resumed") {
-            // this is a resumed line, so extract the pid and find the corresponding unfinished
-            // line in the ongoing_ops map
+        Ok(Operation::no_op())
+    }
 
-            let resumed_re = Regex::new(
-                r"^(?P<pid>\d+)\s<... (?P<op>[^\(]+) resumed>(?P<args_remained>.*)\)\s+=\s+(?P<ret>\d+|-\d+|\?)\s*.*",
-            )?;
-            assert!(resumed_re.is_match(str));
+    // parse a socketpair line
+    fn socketpair(
+        &mut self,
+        pid: usize,
+        args: String,
+    ) -> Result<Operation, Box<dyn std::error::Error>> {
+        // int socketpair(int domain, int type, int protocol, int sv[2]);
+        // creates an unnamed pair of connected sockets
+        //
+        // Example:
+        //      socketpair(AF_UNIX, SOCK_STREAM, 0, [3, 5]) = 0
 
-            let cap = resumed_re
-                .captures(str)
-                .ok_or(Error::ParseError(str.to_string()))?;
+        let fds = self.fd_pair(&args, "socketpair")?;
+        let path = self.pair_path(&fds, FileKind::Socket);
+        for (fd, _) in fds {
+            self.fd_map
+                .insert((pid, fd), OpenedFile::new(path.clone(), 0, 0));
+        }
 
-            let pid = cap["pid"].parse::<usize>()?;
-            let resumed_op = cap["op"].to_string();
-            let ret = cap["ret"].to_string();
-            let args_remained = cap["args_remained"].to_string();
+        Ok(Operation::no_op())
+    }
 
-            // get the unfinished line
-            let unfinished_line = self
-                .ongoing_ops
-                .get(&format!("{}:{}", pid, resumed_op))
-                .ok_or(Error::NotFound(format!("{}:{}", pid, resumed_op)))?;
+    // parse a socket line
+    fn socket(&mut self, pid: usize, ret: String) -> Result<Operation, Box<dyn std::error::Error>> {
+        // int socket(int domain, int type, int protocol);
+        // creates an endpoint for communication and returns a file descriptor that refers to
+        // that endpoint.
+        //
+        // Example:
+        //      socket(AF_INET6, SOCK_DGRAM, IPPROTO_IP) = 3
 
-            let unfinished_re = Regex::new(r"^(?P<op>[^\(]*)\((?P<args>.*) <unfinished ...>$")?;
-            assert!(unfinished_re.is_match(unfinished_line));
+        self.new_descriptor(pid, &ret, FileKind::Socket)?;
 
-            let cap = unfinished_re
-                .captures(unfinished_line)
-                .ok_or(Error::ParseError(str.to_string()))?;
+        Ok(Operation::no_op())
+    }
 
-            let unfinished_op = cap["op"].to_string();
-            // the operation names from both unfinished and resumed line should be the same
-            assert_eq!(unfinished_op, resumed_op);
+    // parse a connect line
+    fn connect(
+        &mut self,
+        pid: usize,
+        args: String,
+    ) -> Result<Operation, Box<dyn std::error::Error>> {
+        // int connect(int sockfd, const struct sockaddr *addr, socklen_t addrlen);
+        // connects the socket referred to by the file descriptor sockfd to the address
+        // specified by addr.
+        //
+        // Example:
+        //      connect(3, {sa_family=AF_UNIX, sun_path="/var/run/nscd/socket"}, 110) = 0
+        //
+        // The socket may have been created before the trace started, so make sure it is tracked.
 
-            let args = format!("{}{}", cap["args"].to_string(), args_remained);
+        let parts: Vec<&str> = args.split(",").collect();
+        let fd = self.fd(pid, parts[0])?;
 
-            return Ok(Parts::Finished(pid, unfinished_op, args, ret));
-        } else {
-            // this is an un-interrupted operation line
-            let re = Regex::new(
-                r"^(?P<pid>\d+) (?P<op>[^\(]+)\((?P<args>.*)\)\s+=\s+(?P<ret>\d+|-\d+|\?)\s*.*$",
-            )?;
+        if !self.fd_map.contains_key(&(pid, fd)) {
+            let path = self.descriptor(FileKind::Socket);
+            self.fd_map.insert((pid, fd), OpenedFile::new(path, 0, 0));
+        }
 
-            assert!(re.is_match(str));
+        Ok(Operation::no_op())
+    }
 
-            let cap = re.captures(str).ok_or(Error::ParseError(str.to_string()))?;
+    // parse an accept or accept4 line
+    fn accept(&mut self, pid: usize, ret: String) -> Result<Operation, Box<dyn std::error::Error>> {
+        // int accept(int sockfd, struct sockaddr *addr, socklen_t *addrlen);
+        // extracts the first connection request on the queue of pending connections for the
+        // listening socket, creates a new connected socket, and returns a new file descriptor
+        // referring to that socket.
+        //
+        // Example:
+        //      accept4(3, {sa_family=AF_INET, sin_port=htons(40522), ...}, [16], SOCK_CLOEXEC) = 4
 
-            return Ok(Parts::Finished(
-                cap["pid"].parse::<usize>()?,
-                cap["op"].to_string(),
-                cap["args"].to_string(),
-                cap["ret"].to_string(),
-            ));
+        self.new_descriptor(pid, &ret, FileKind::Socket)?;
+
+        Ok(Operation::no_op())
+    }
+
+    // parse an eventfd or eventfd2 line
+    fn eventfd(
+        &mut self,
+        pid: usize,
+        ret: String,
+    ) -> Result<Operation, Box<dyn std::error::Error>> {
+        // int eventfd(unsigned int initval, int flags);
+        // creates an "eventfd object" that can be used as an event wait/notify mechanism by
+        // user-space applications.
+        //
+        // Example:
+        //      eventfd2(0, EFD_CLOEXEC|EFD_NONBLOCK) = 3
+
+        self.new_descriptor(pid, &ret, FileKind::EventFd)?;
+
+        Ok(Operation::no_op())
+    }
+
+    // parse a memfd_create line
+    fn memfd_create(
+        &mut self,
+        pid: usize,
+        ret: String,
+    ) -> Result<Operation, Box<dyn std::error::Error>> {
+        // int memfd_create(const char *name, unsigned int flags);
+        // creates an anonymous file and returns a file descriptor that refers to it. The file
+        // behaves like a regular file, but lives in RAM.
+        //
+        // Example:
+        //      memfd_create("a-name", MFD_CLOEXEC) = 3
+
+        self.new_descriptor(pid, &ret, FileKind::MemFd)?;
+
+        Ok(Operation::no_op())
+    }
+
+    // parse a copy_file_range line
+    fn copy_file_range(
+        &mut self,
+        pid: usize,
+        args: String,
+        ret: String,
+    ) -> Result<Operation, Box<dyn std::error::Error>> {
+        // ssize_t copy_file_range(int fd_in, off64_t *off_in, int fd_out, off64_t *off_out,
+        //                         size_t len, unsigned int flags);
+        // copies up to len bytes of data from the source file descriptor fd_in to the target
+        // file descriptor fd_out, without the additional cost of transferring data from the
+        // kernel to user space and then back into the kernel.
+        //
+        // Example:
+        //      copy_file_range(3, NULL, 4, NULL, 9223372035781033984, 0) = 1335
+        // or
+        //      copy_file_range(3, [0 => 1335], 4, [0 => 1335], 1335, 0) = 1335
+        //
+        // If off_in or off_out is NULL, the file offset of the descriptor is used and adjusted,
+        // otherwise the given offset is used and the file offset is not changed.
+
+        let len = ret.trim().parse::<usize>()?;
+
+        let parts: Vec<&str> = args.split(",").collect();
+        let fd_in = self.fd(pid, parts[0])?;
+        let off_in = self.offset_pointer(parts[1])?;
+        let fd_out = self.fd(pid, parts[2])?;
+        let off_out = self.offset_pointer(parts[3])?;
+
+        self.copy(pid, (fd_in, off_in), (fd_out, off_out), len)
+    }
+
+    // parse a sendfile line
+    fn sendfile(
+        &mut self,
+        pid: usize,
+        args: String,
+        ret: String,
+    ) -> Result<Operation, Box<dyn std::error::Error>> {
+        // ssize_t sendfile(int out_fd, int in_fd, off_t *offset, size_t count);
+        // copies data between one file descriptor and another within the kernel.
+        //
+        // Example:
+        //      sendfile(4, 3, NULL, 1335) = 1335
+        // or
+        //      sendfile(4, 3, [0] => [1335], 1335) = 1335
+        //
+        // If offset is NULL, data is read from in_fd starting at its file offset, and the file
+        // offset is updated. The out_fd offset is always adjusted.
+
+        let len = ret.trim().parse::<usize>()?;
+
+        let parts: Vec<&str> = args.split(",").collect();
+        let fd_out = self.fd(pid, parts[0])?;
+        let fd_in = self.fd(pid, parts[1])?;
+        let off_in = self.offset_pointer(parts[2])?;
+
+        self.copy(pid, (fd_in, off_in), (fd_out, None), len)
+    }
+
+    // parse a splice line
+    fn splice(
+        &mut self,
+        pid: usize,
+        args: String,
+        ret: String,
+    ) -> Result<Operation, Box<dyn std::error::Error>> {
+        // ssize_t splice(int fd_in, off64_t *off_in, int fd_out, off64_t *off_out, size_t len,
+        //                unsigned int flags);
+        // moves data between two file descriptors without copying between kernel address space
+        // and user address space. One of the file descriptors must refer to a pipe.
+        //
+        // Example:
+        //      splice(3, NULL, 6, [4096], 65536, SPLICE_F_MOVE) = 4096
+
+        let len = ret.trim().parse::<usize>()?;
+
+        let parts: Vec<&str> = args.split(",").collect();
+        let fd_in = self.fd(pid, parts[0])?;
+        let off_in = self.offset_pointer(parts[1])?;
+        let fd_out = self.fd(pid, parts[2])?;
+        let off_out = self.offset_pointer(parts[3])?;
+
+        self.copy(pid, (fd_in, off_in), (fd_out, off_out), len)
+    }
+
+    // generate the operation that moves len bytes between two file descriptors
+    fn copy(
+        &mut self,
+        pid: usize,
+        from: (i32, Option<i32>),
+        to: (i32, Option<i32>),
+        len: usize,
+    ) -> Result<Operation, Box<dyn std::error::Error>> {
+        let from = self.copy_endpoint(pid, from.0, from.1, len);
+        let to = self.copy_endpoint(pid, to.0, to.1, len);
+
+        match (from, to) {
+            (Some((from, from_offset)), Some((to, to_offset))) => Ok(Operation::copy(
+                self.file(&from).clone(),
+                from_offset,
+                self.file(&to).clone(),
+                to_offset,
+                len,
+            )),
+            (Some((from, from_offset)), None) => {
+                // the destination is not tracked, e.g., the inherited STDOUT
+                Ok(Operation::read(self.file(&from).clone(), len, from_offset))
+            }
+            (None, Some((to, to_offset))) => Ok(Operation::write(
+                self.file(&to).clone(),
+                String::new(),
+                len,
+                to_offset,
+            )),
+            (None, None) => Ok(Operation::no_op()),
         }
     }
 
-    // check the existance of the process id in the beginning of a line
-    fn has_pid(&self, str: &str) -> Result<bool, Box<dyn std::error::Error>> {
-        let re = Regex::new(r"^(?P<pid>\d+) (?P<remaining>.+)$")?;
-        Ok(re.is_match(str))
+    // find the path and offset of one side of a copy, and adjust the file offset if the copy
+    // uses it
+    fn copy_endpoint(
+        &mut self,
+        pid: usize,
+        fd: i32,
+        offset: Option<i32>,
+        len: usize,
+    ) -> Option<(String, i32)> {
+        let opened_file = self.fd_map.get_mut(&(pid, fd))?;
+        match offset {
+            Some(offset) => Some((opened_file.path.clone(), offset)),
+            None => {
+                let offset = opened_file.offset;
+                opened_file.offset += len as i32;
+                Some((opened_file.path.clone(), offset))
+            }
+        }
     }
 
-    // extract a path in-between " and " from the input string
-    fn path(&self, str: &str, callee: &str) -> Result<String, Box<dyn std::error::Error>> {
-        let path = str
-            .split_at(
-                str.find("\"")
-                    .ok_or(Error::NotFound(format!("\" from {} line", callee)))?
-                    + 1,
-            )
-            .1;
-        Ok(path
-            .split_at(
-                path.rfind("\"")
-                    .ok_or(Error::NotFound(format!("\" from {} line", callee)))?,
-            )
-            .0
-            .to_string())
+    // extract the offset from an offset pointer argument, like NULL, [0], [0 => 4096], or
+    // [0] => [4096]. The value before the call is the offset used by the operation.
+    fn offset_pointer(&self, str: &str) -> Result<Option<i32>, Box<dyn std::error::Error>> {
+        let str = str.trim();
+        if str == "NULL" {
+            return Ok(None);
+        }
+
+        let offset = str
+            .trim_start_matches("[")
+            .split([']', ' '])
+            .next()
+            .ok_or(Error::ParseError(format!("offset pointer {}", str)))?;
+
+        Ok(Some(offset.parse::<i32>()?))
     }
 
-    // convert a relative path to absolute
-    fn relative_to_absolute(
-        &self,
+    // create a new non-file descriptor object and return its unique path
+    fn descriptor(&mut self, kind: FileKind) -> String {
+        self.descriptors += 1;
+        let name = match kind {
+            FileKind::Pipe => "pipe",
+            FileKind::Socket => "socket",
+            FileKind::EventFd => "eventfd",
+            FileKind::MemFd => "memfd",
+            FileKind::Regular => "file",
+        };
+        let path = format!("{}:[{}]", name, self.descriptors);
+
+        self.accessed_files
+            .insert(path.clone(), Arc::new(File::with_kind(&path, kind)));
+
+        path
+    }
+
+    // register the file descriptor returned by a syscall that creates a non-file object
+    fn new_descriptor(
+        &mut self,
         pid: usize,
-        dirfd: &str,
-        relative: &str,
-    ) -> Result<String, Box<dyn std::error::Error>> {
-        // dirfd should be a valid file descriptor
-        let dirfd = dirfd.trim().parse::<i32>()?;
-        let dirfd_path = self
-            .fd_map
-            .get(&(pid, dirfd))
-            .ok_or(Error::NotFound(format!("file descriptor {}", dirfd)))?
-            .path
-            .clone();
-        // create the absolute path
-        Ok(format!("{}{}", dirfd_path, relative))
+        ret: &str,
+        kind: FileKind,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let (fd, decorated) = fd_annotation(ret)?;
+        let path = match decorated {
+            Some(decorated) => self.decorated_path(decorated),
+            None => self.descriptor(kind),
+        };
+        self.fd_map.insert((pid, fd), OpenedFile::new(path, 0, 0));
+
+        Ok(())
     }
 
-    fn file_type(
+    // the path of the object shared by a pair of file descriptors
+    fn pair_path(&mut self, fds: &[DecoratedFd], kind: FileKind) -> String {
+        match fds.iter().find_map(|(_, decorated)| decorated.clone()) {
+            Some(decorated) => self.decorated_path(&decorated),
+            None => self.descriptor(kind),
+        }
+    }
+
+    // extract the pair of file descriptors in-between [ and ] from the input string, like
+    // [6, 7] or [6<pipe:[1234]>, 7<pipe:[1234]>]
+    fn fd_pair(
         &self,
         str: &str,
-        path: &str,
         callee: &str,
-    ) -> Result<FileType, Box<dyn std::error::Error>> {
-        // extract the file type
-        let st_mode: String = str
-            .trim()
-            .split(",")
-            .filter(|stat| stat.contains("st_mode") || stat.contains("stx_mode"))
-            .map(|str| str.to_string())
-            .collect();
-        let file_type = st_mode
+    ) -> Result<Vec<DecoratedFd>, Box<dyn std::error::Error>> {
+        let pair = str
             .split_at(
-                st_mode
-                    .find("=")
-                    .ok_or(Error::NotFound(format!("= from {}", callee)))?
+                str.find("[")
+                    .ok_or(Error::NotFound(format!("[ from {} line", callee)))?
                     + 1,
             )
-            .1
-            .trim();
+            .1;
 
-        // the mode is not a file or directory
-        if !file_type.contains("S_IFREG") && !file_type.contains("S_IFDIR") {
-            return Ok(FileType::Other);
+        // the decorated paths can include brackets and commas, so skip everything in-between
+        // < and >
+        let mut entries = vec![];
+        let mut entry = String::new();
+        let mut decoration = 0;
+        for c in pair.chars() {
+            match c {
+                '<' => decoration += 1,
+                '>' => decoration -= 1,
+                ']' | ',' if decoration == 0 => {
+                    entries.push(entry.clone());
+                    entry.clear();
+                    if c == ']' {
+                        break;
+                    }
+                    continue;
+                }
+                _ => {}
+            }
+            entry.push(c);
         }
 
-        // extract the file size
-        let st_size: String = str
-            .trim()
-            .split(",")
-            .filter(|stat| stat.contains("st_size") || stat.contains("stx_size"))
-            .map(|str| str.to_string())
-            .collect();
-        let file_size = st_size
-            .split_at(
-                st_size
-                    .find("=")
-                    .ok_or(Error::NotFound(format!("= from {}", callee)))?
-                    + 1,
-            )
-            .1
-            .trim()
-            .parse::<usize>()?;
+        let mut fds = vec![];
+        for entry in entries {
+            let (fd, decorated) = fd_annotation(&entry)?;
+            fds.push((fd, decorated.map(|decorated| decorated.to_string())));
+        }
+
+        Ok(fds)
+    }
+
+    // parse a file descriptor argument. When strace decorates the file descriptor with its
+    // path (strace -y), the decorated path is the ground truth, so it populates or corrects
+    // the fd_map.
+    fn fd(&mut self, pid: usize, arg: &str) -> Result<i32, Box<dyn std::error::Error>> {
+        let (fd, decorated) = fd_annotation(arg)?;
+
+        if let Some(decorated) = decorated {
+            let tracked = self
+                .fd_map
+                .get(&(pid, fd))
+                .map(|opened_file| opened_file.path.clone());
+            let decorated = self.correct(pid, fd, tracked, decorated);
+            match self.fd_map.get_mut(&(pid, fd)) {
+                Some(opened_file) => opened_file.path = decorated,
+                None => {
+                    self.fd_map
+                        .insert((pid, fd), OpenedFile::new(decorated, 0, 0));
+                }
+            }
+        }
+
+        Ok(fd)
+    }
+
+    // correct the path tracked for a file descriptor with the path decorated by strace, and
+    // return it. The tracked path is kept as another path of the same file
+    fn correct(&mut self, pid: usize, fd: i32, tracked: Option<String>, decorated: &str) -> String {
+        let decorated = self.decorated_path(decorated);
+        if let Some(tracked) = tracked {
+            if tracked != decorated {
+                self.alias(&tracked, &decorated);
+                self.discrepancy(pid, fd, tracked, decorated.clone());
+            }
+        }
+
+        decorated
+    }
+
+    // register the object behind a path decorated by strace, and return the path
+    fn decorated_path(&mut self, decorated: &str) -> String {
+        // the unlinked files are decorated with a (deleted) suffix. The parser keeps the removed
+        // regular files under the same name, but memfds are never linked in the first place
+        let kind = FileKind::from_path(decorated);
+        let path = match kind {
+            FileKind::Regular => normalize(decorated),
+            _ => decorated.trim_end_matches(" (deleted)").to_string(),
+        };
+
+        if kind != FileKind::Regular && !self.accessed_files.contains_key(&path) {
+            self.accessed_files
+                .insert(path.clone(), Arc::new(File::with_kind(&path, kind)));
+        }
+
+        path
+    }
+
+    // keep the discrepancy between the tracked and the decorated path of a file descriptor.
+    // The paths generated for pipes, sockets, and other non-file descriptors are not real
+    // paths, so they always differ from the decorated ones.
+    fn discrepancy(&mut self, pid: usize, fd: i32, tracked: String, decorated: String) {
+        let generated = self
+            .accessed_files
+            .get(&tracked)
+            .map(|file| !file.is_regular())
+            .unwrap_or(false);
+
+        if !generated {
+            self.fd_discrepancies
+                .push(FdDiscrepancy::new(pid, fd, tracked, decorated));
+        }
+    }
+
+    // parse a mmap line
+    fn mmap(
+        &mut self,
+        pid: usize,
+        args: String,
+        ret: String,
+    ) -> Result<Vec<Operation>, Box<dyn std::error::Error>> {
+        // void *mmap(void *addr, size_t length, int prot, int flags, int fd, off_t offset);
+        // creates a new mapping in the virtual address space of the calling process. The contents
+        // of a file mapping are initialized using length bytes starting at offset in the file
+        // referred to by the file descriptor fd.
+        //
+        // Example:
+        //      mmap(NULL, 107592, PROT_READ, MAP_PRIVATE|MAP_DENYWRITE, 3, 0) = 0x7fdfc831d000
+        // or
+        //      mmap(NULL, 8192, PROT_READ|PROT_WRITE, MAP_PRIVATE|MAP_ANONYMOUS, -1, 0) = 0x7fdfc8338000
+        //
+        // Anonymous mappings are not backed by a file, so they do not generate an operation, and
+        // neither does unmapping a region.
+        // With MAP_SHARED, the updates to the mapping are carried through to the underlying file.
+
+        let parts: Vec<&str> = args.split(",").collect();
+        let len = parts[1].trim().parse::<usize>()?;
+        let prot = parts[2].trim().to_string();
+        let flags = parts[3].trim().to_string();
+        let fd = self.fd(pid, parts[4])?;
+        let offset = self.number(parts[5])?;
+        // the offsets of the operations do not reach 2 GiB
+        let offset = i32::try_from(offset)
+            .map_err(|_| Error::ParseError(format!("mmap offset {}", parts[5].trim())))?;
+
+        if flags.contains("MAP_ANONYMOUS") || fd < 0 {
+            return Ok(vec![]);
+        }
+
+        // find the mapped path based on the file descriptor
+        let path = match self.fd_map.get(&(pid, fd)) {
+            Some(opened_file) => opened_file.path.clone(),
+            None => return Ok(vec![]),
+        };
+
+        // keep the mapped region, so the later msync and munmap calls can find the file
+        let addr = self.number(&ret)?;
+        self.mapped_regions
+            .insert((pid, addr), MappedRegion::new(path.clone(), len));
+
+        Ok(vec![Operation::map(
+            self.file(&path).clone(),
+            offset,
+            len,
+            prot,
+            flags,
+        )])
+    }
+
+    // parse a munmap line
+    fn munmap(
+        &mut self,
+        pid: usize,
+        args: String,
+    ) -> Result<Vec<Operation>, Box<dyn std::error::Error>> {
+        // int munmap(void *addr, size_t length);
+        // deletes the mappings for the specified address range.
+        //
+        // Example:
+        //      munmap(0x7fdfc833a000, 95921) = 0
+
+        let parts: Vec<&str> = args.split(",").collect();
+        let addr = self.number(parts[0])?;
+        let len = parts[1].trim().parse::<usize>()?;
+
+        // remove the mapped regions that are entirely inside the unmapped range
+        self.mapped_regions
+            .retain(|&(region_pid, region_addr), region| {
+                region_pid != pid || region_addr < addr || region_addr + region.len > addr + len
+            });
+
+        Ok(vec![])
+    }
+
+    // parse a msync line
+    fn msync(&mut self, pid: usize, args: String) -> Result<Operation, Box<dyn std::error::Error>> {
+        // int msync(void *addr, size_t length, int flags);
+        // flushes changes made to the in-core copy of a file that was mapped into memory using
+        // mmap back to the filesystem.
+        //
+        // Example:
+        //      msync(0x7f2a1c000000, 4096, MS_SYNC) = 0
+
+        let parts: Vec<&str> = args.split(",").collect();
+        let addr = self.number(parts[0])?;
+
+        // find the mapped region that includes the synced address
+        let path = self
+            .mapped_regions
+            .iter()
+            .find(|(&(region_pid, region_addr), region)| {
+                region_pid == pid && region_addr <= addr && addr < region_addr + region.len
+            })
+            .map(|(_, region)| region.path.clone());
+
+        match path {
+            Some(path) => Ok(Operation::sync(self.file(&path).clone())),
+            None => Ok(Operation::no_op()),
+        }
+    }
+
+    // parse a readlink line
+    fn readlink(
+        &mut self,
+        pid: usize,
+        args: String,
+    ) -> Result<Operation, Box<dyn std::error::Error>> {
+        // ssize_t readlink(const char *pathname, char *buf, size_t bufsiz);
+        // places the contents of the symbolic link pathname in the buffer buf
+        //
+        // Example:
+        //      readlink("/lib/x.so", "x.so.6", 4096) = 6
+        //
+
+        let parts: Vec<&str> = args.split(",").collect();
+        if parts.len() < 2 {
+            return Err(Box::new(Error::ParseError(format!("readlink: {}", args))));
+        }
+
+        let link = self.path(parts[0], "readlink")?;
+        let link = self.resolve(pid, None, &link)?;
+        let target = self.path(parts[1], "readlink")?;
+
+        self.symlink(&link, &target);
+
+        Ok(Operation::no_op())
+    }
+
+    // parse a readlinkat line
+    fn readlinkat(
+        &mut self,
+        pid: usize,
+        args: String,
+    ) -> Result<Operation, Box<dyn std::error::Error>> {
+        // ssize_t readlinkat(int dirfd, const char *pathname, char *buf, size_t bufsiz);
+        // places the contents of the symbolic link pathname in the buffer buf
+        //
+        // Example:
+        //      readlinkat(AT_FDCWD, "/lib/x.so", "x.so.6", 4096) = 6
+        //
+        // If the path is absolute, then dirfd is ignored.
+        // If dirfd is 'AT_FDCWD', the path is interpreted relative to the current working directory
+        // of the calling process.
+        // If dirfd is a file descriptor, then the path is relative to the path of the directory
+        // described by the file descriptor.
+        //
+
+        let parts: Vec<&str> = args.split(",").collect();
+        if parts.len() < 3 {
+            return Err(Box::new(Error::ParseError(format!("readlinkat: {}", args))));
+        }
+
+        let link = self.path(parts[1], "readlinkat")?;
+        let link = self.resolve(pid, Some(parts[0]), &link)?;
+        let target = self.path(parts[2], "readlinkat")?;
+
+        self.symlink(&link, &target);
+
+        Ok(Operation::no_op())
+    }
+
+    // keep a symlink as another path of its target. A relative target is relative to the
+    // directory containing the symlink.
+    fn symlink(&mut self, link: &str, target: &str) {
+        let target = join(&join(link, ".."), target);
+        self.alias(link, &target);
+    }
+
+    // keep the device and inode numbers of a file, if strace printed them (strace -v). The
+    // paths with the same device and inode numbers are the same file.
+    fn inode(&mut self, args: &str, path: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let stat = Regex::new(r"st_dev=(?P<dev>makedev\([^)]*\)|\d+).*st_ino=(?P<ino>\d+)")?;
+        let statx = Regex::new(
+            r"stx_ino=(?P<ino>\d+).*stx_dev_major=(?P<major>\d+), stx_dev_minor=(?P<minor>\d+)",
+        )?;
+
+        let inode = if let Some(cap) = stat.captures(args) {
+            format!("{}:{}", &cap["dev"], &cap["ino"])
+        } else if let Some(cap) = statx.captures(args) {
+            format!(
+                "makedev({}, {}):{}",
+                &cap["major"], &cap["minor"], &cap["ino"]
+            )
+        } else {
+            return Ok(());
+        };
+
+        match self.inodes.get(&inode).cloned() {
+            Some(same) => self.alias(path, &same),
+            None => {
+                self.inodes.insert(inode, path.to_string());
+            }
+        }
+
+        Ok(())
+    }
+
+    // keep that two paths refer to the same file
+    fn alias(&mut self, path: &str, same: &str) {
+        let path = self.canonical_path(path);
+        let same = self.canonical_path(same);
+        if path != same {
+            self.identities.insert(path, same);
+        }
+    }
+
+    // find the path that identifies the file at the input path. Any of the path's ancestors can
+    // be a symlink, like /lib in /lib/x.so, so it is replaced by the path it refers to
+    fn canonical_path(&self, path: &str) -> String {
+        // the relative paths left unresolved stay relative, apart from the absolute ones
+        let mut path = normalize_relative(path);
+
+        // a symlink loop never settles, so stop after replacing every known path once
+        for _ in 0..=self.identities.len() {
+            let aliased = std::path::Path::new(&path)
+                .ancestors()
+                .filter_map(|ancestor| ancestor.to_str())
+                .find(|ancestor| self.identities.contains_key(*ancestor))
+                .map(|ancestor| ancestor.to_string());
+
+            match aliased {
+                Some(ancestor) => {
+                    let rest = path[ancestor.len()..].trim_start_matches("/").to_string();
+                    path = join(&self.identities[&ancestor], &rest);
+                }
+                None => break,
+            }
+        }
+
+        path
+    }
+
+    // identify the files accessed by the processes' operations by their canonical path, so
+    // the files accessed through different paths become the same node of the dependency graph
+    fn canonicalize(&self, processes: &mut [Process]) -> Result<(), Box<dyn std::error::Error>> {
+        // the files are equal when they are the same file, even through different paths, so the
+        // path each file was accessed through is part of the key
+        let mut canonical_files: HashMap<(String, Arc<File>), Arc<File>> = HashMap::new();
+        for process in processes.iter_mut() {
+            for op in process.ops_mut().iter_mut() {
+                let mut error = None;
+                *op = op.map_files(|file| {
+                    if !file.is_regular() {
+                        return file.clone();
+                    }
+
+                    let (accessed, path) = match (file.path(), file.canonical()) {
+                        (Ok(accessed), Ok(path)) => (accessed, path),
+                        (Err(e), _) | (_, Err(e)) => {
+                            error = Some(e);
+                            return file.clone();
+                        }
+                    };
+
+                    // the files renamed or removed over time keep their identity
+                    canonical_files
+                        .entry((accessed.to_string(), file.clone()))
+                        .or_insert_with(|| {
+                            let canonical = self.canonical_path(path);
+                            if canonical == path {
+                                file.clone()
+                            } else {
+                                Arc::new(file.with_canonical(canonical))
+                            }
+                        })
+                        .clone()
+                });
+
+                if let Some(e) = error {
+                    return Err(e);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    // rewrite the paths of the files with the rules, once the files are identified by their
+    // traced paths
+    fn rewrite(&self, processes: &mut [Process]) -> Result<(), Box<dyn std::error::Error>> {
+        for process in processes.iter_mut() {
+            for op in process.ops_mut().iter_mut() {
+                let mut error = None;
+                let rewritten = op.map_files(|file| match file.path() {
+                    Ok(path) if file.is_regular() => {
+                        let rewritten = self.rules.rewritten(path);
+                        match rewritten == path {
+                            true => file.clone(),
+                            false => Arc::new(file.with_path(rewritten)),
+                        }
+                    }
+                    Ok(_) => file.clone(),
+                    Err(e) => {
+                        error = Some(e);
+                        file.clone()
+                    }
+                });
+
+                if let Some(e) = error {
+                    return Err(e);
+                }
+
+                *op = match rewritten.op_type() {
+                    OperationType::Rename(file, to) => {
+                        Operation::rename(file.clone(), self.rules.rewritten(to))
+                            .with_timing(op.timestamp(), op.duration())
+                    }
+                    _ => rewritten,
+                };
+            }
+        }
+
+        Ok(())
+    }
+
+    // keep track of the files that existed before the trace started, and the ones created or
+    // modified during the trace, in the order the operations happened
+    pub(crate) fn observe(&mut self, operation: &Operation) {
+        match operation.op_type() {
+            OperationType::Mknod(file) | OperationType::Mkdir(file, _) => {
+                if let Ok(path) = file.path() {
+                    self.removed_files.remove(path);
+                    if !self.initial_files.contains_key(path) {
+                        self.created_files.insert(path.to_string());
+                        self.parent_existed(path);
+                    }
+                }
+            }
+            OperationType::Rename(file, to) => {
+                self.existed(file, 0);
+                self.created_files.insert(to.clone());
+                self.parent_existed(to);
+            }
+            OperationType::Read(file, offset, len) => {
+                // the file has at least the bytes read from it
+                self.existed(file, (*offset).max(0) as usize + len);
+            }
+            OperationType::Copy(from, from_offset, to, _, len) => {
+                self.existed(from, (*from_offset).max(0) as usize + len);
+                self.existed(to, 0);
+                self.modified(to);
+            }
+            OperationType::Write(file, _, _, _) | OperationType::Truncate(file) => {
+                self.existed(file, 0);
+                self.modified(file);
+            }
+            _ => {
+                for (file, _) in operation.accesses() {
+                    self.existed(&file, 0);
+                }
+            }
+        }
+    }
+
+    // the file existed before the trace started with at least the input size, unless it was
+    // created during the trace
+    fn existed(&mut self, file: &Arc<File>, size: usize) {
+        if let (true, Ok(path)) = (file.is_regular(), file.path()) {
+            self.initial_file(FileType::File(path.to_string(), size));
+        }
+    }
+
+    // the directory a file is created in existed, unless it was created during the trace too
+    fn parent_existed(&mut self, path: &str) {
+        let parent = join(path, "..");
+        if parent != "/" {
+            self.initial_file(FileType::Dir(parent, 0));
+        }
+    }
+
+    // the path is known to name a file, because the file existed before the trace started or was
+    // created during it, and wasn't removed or renamed since
+    fn exists(&self, path: &str) -> bool {
+        (self.initial_files.contains_key(path) || self.created_files.contains(path))
+            && !self.removed_files.contains(path)
+    }
+
+    fn modified(&mut self, file: &Arc<File>) {
+        if let Ok(path) = file.path() {
+            self.modified_files.insert(path.to_string());
+        }
+    }
+
+    // keep what is known about a file or directory that existed before the trace started. The
+    // information observed after the trace modified the file is not about its initial state.
+    fn initial_file(&mut self, file_type: FileType) {
+        let path = file_type.path().to_string();
+        if !path.starts_with("/")
+            || path.ends_with(" (deleted)")
+            || self.created_files.contains(&path)
+        {
+            return;
+        }
+
+        let replace = match (self.initial_files.get(&path), &file_type) {
+            (None, _) => true,
+            (Some(_), _) if self.modified_files.contains(&path) => false,
+            (Some(FileType::File(_, size)), FileType::File(_, new_size)) => new_size > size,
+            // the file was only known to exist, until stat showed it is a directory
+            (Some(FileType::File(_, 0)), FileType::Dir(_, _)) => true,
+            _ => false,
+        };
+
+        if replace {
+            self.initial_files.insert(path, file_type);
+        }
+    }
+
+    // the path stops naming the file it refers to, but the file is still reachable through the
+    // file descriptors opened on it, like strace shows them, with a (deleted) suffix
+    fn remove_path(&mut self, path: &str) {
+        self.move_path(path, &format!("{} (deleted)", path));
+    }
+
+    // the file, or the directory and everything in it, moves from the old path to the new one.
+    // The new path stops naming the file it referred to before, and the old path names nothing,
+    // so the next file created at either of them is another file.
+    fn move_path(&mut self, old: &str, new: &str) {
+        if old == new {
+            return;
+        }
+
+        let moved = |path: &str| -> Option<String> {
+            if path == old {
+                Some(new.to_string())
+            } else {
+                path.strip_prefix(old)
+                    .filter(|rest| rest.starts_with("/"))
+                    .map(|rest| format!("{}{}", new, rest))
+            }
+        };
+
+        // the known files at the old path no longer exist there, nor the ones in the directory
+        // replaced at the new path, while the new path names the moved file
+        let removed: Vec<String> = self
+            .initial_files
+            .keys()
+            .chain(self.created_files.iter())
+            .filter(|path| moved(path).is_some() || path.starts_with(&format!("{}/", new)))
+            .cloned()
+            .collect();
+        self.removed_files.extend(removed);
+        self.removed_files.remove(new);
+
+        // the files at the new path are replaced
+        let replaced: Vec<String> = self
+            .accessed_files
+            .keys()
+            .filter(|path| path.as_str() == new || path.starts_with(&format!("{}/", new)))
+            .cloned()
+            .collect();
+        for path in replaced {
+            self.accessed_files.remove(&path);
+            *self.generations.entry(path).or_insert(0) += 1;
+        }
+
+        let paths: Vec<String> = self
+            .accessed_files
+            .keys()
+            .filter(|path| moved(path).is_some())
+            .cloned()
+            .collect();
+        for path in paths {
+            if let (Some(file), Some(new_path)) = (self.accessed_files.remove(&path), moved(&path))
+            {
+                self.accessed_files
+                    .insert(new_path.clone(), Arc::new(file.with_path(&new_path)));
+            }
+            *self.generations.entry(path).or_insert(0) += 1;
+        }
+
+        // the file descriptors and mapped regions follow the file
+        for opened_file in self.fd_map.values_mut() {
+            if let Some(new_path) = moved(&opened_file.path) {
+                opened_file.path = new_path;
+            }
+        }
+        for region in self.mapped_regions.values_mut() {
+            if let Some(new_path) = moved(&region.path) {
+                region.path = new_path;
+            }
+        }
+    }
+
+    fn file(&mut self, path: &str) -> Arc<File> {
+        match self.accessed_files.get(path) {
+            Some(f) => f.clone(),
+            None => {
+                let generation = *self.generations.get(path).unwrap_or(&0);
+                let file = Arc::new(File::new(path).with_generation(generation));
+                self.accessed_files.insert(path.to_string(), file.clone());
+                file
+            }
+        }
+    }
+
+    // the operation without the files excluded by the rules, or none if nothing is left. A copy
+    // with only one side excluded still reads the source or writes the destination.
+    fn kept(&self, operation: Operation) -> Option<Operation> {
+        let excluded = |file: &Arc<File>| {
+            file.path()
+                .map(|path| self.rules.excludes(path))
+                .unwrap_or(false)
+        };
+
+        if let OperationType::Copy(from, from_offset, to, to_offset, len) = operation.op_type() {
+            return match (excluded(from), excluded(to)) {
+                (false, false) => Some(operation),
+                (true, false) => Some(Operation::write(
+                    to.clone(),
+                    String::new(),
+                    *len,
+                    *to_offset,
+                )),
+                (false, true) => Some(Operation::read(from.clone(), *len, *from_offset)),
+                (true, true) => None,
+            };
+        }
+
+        match operation.accesses().iter().any(|(file, _)| excluded(file)) {
+            true => None,
+            false => Some(operation),
+        }
+    }
+
+    // extract process id, operation name, input args in-between ( and ), and return value from the input string
+    fn parts(&mut self, str: &str) -> Result<Parts, Box<dyn std::error::Error>> {
+        // If a system call is being executed and meanwhile another one is being called from a
+        // different thread/process then strace will try to preserve the  order  of  those events
+        // and mark the ongoing call as being unfinished.
+        // When the call returns it will be marked as resumed.
+
+        if str.contains("unfinished") {
+            let re = Regex::new(r"^(?P<pid>\d+) (?P<remaining>.+)$")?;
+            assert!(re.is_match(str));
+
+            let cap = re.captures(str).ok_or(Error::ParseError(str.to_string()))?;
+
+            let pid = cap["pid"].parse::<usize>()?;
+            let unfinished_line = cap["remaining"].to_string();
+
+            let unfinished_re = Regex::new(r"^(?P<op>[^\(]*)\((?P<args>.*) <unfinished ...>$")?;
+            assert!(unfinished_re.is_match(&unfinished_line));
+
+            let cap = unfinished_re
+                .captures(&unfinished_line)
+                .ok_or(Error::ParseError(str.to_string()))?;
+
+            let unfinished_op = cap["op"].to_string();
+
+            // keep the unfinished line until we see the resumed line
+            self.ongoing_ops.insert(
+                format!("{}:{}", pid, unfinished_op),
+                unfinished_line.clone(),
+            );
+
+            return Ok(Parts::Unfinished(pid, unfinished_line));
+        } else if str.contains("resumed") {
+            // this is a resumed line, so extract the pid and find the corresponding unfinished
+            // line in the ongoing_ops map
+
+            let resumed_re = Regex::new(
+                r"^(?P<pid>\d+)\s<... (?P<op>[^\(]+) resumed>(?P<args_remained>.*)\)\s+=\s+(?P<ret>0x[0-9a-f]+|\d+<.*?>|\d+|-\d+|\?)(\s.*)?$",
+            )?;
+            assert!(resumed_re.is_match(str));
+
+            let cap = resumed_re
+                .captures(str)
+                .ok_or(Error::ParseError(str.to_string()))?;
+
+            let pid = cap["pid"].parse::<usize>()?;
+            let resumed_op = cap["op"].to_string();
+            let ret = cap["ret"].to_string();
+            let args_remained = cap["args_remained"].to_string();
+
+            // get the unfinished line
+            let unfinished_line = self
+                .ongoing_ops
+                .get(&format!("{}:{}", pid, resumed_op))
+                .ok_or(Error::NotFound(format!("{}:{}", pid, resumed_op)))?;
+
+            let unfinished_re = Regex::new(r"^(?P<op>[^\(]*)\((?P<args>.*) <unfinished ...>$")?;
+            assert!(unfinished_re.is_match(unfinished_line));
+
+            let cap = unfinished_re
+                .captures(unfinished_line)
+                .ok_or(Error::ParseError(str.to_string()))?;
+
+            let unfinished_op = cap["op"].to_string();
+            // the operation names from both unfinished and resumed line should be the same
+            assert_eq!(unfinished_op, resumed_op);
+
+            let args = format!("{}{}", cap["args"].to_string(), args_remained);
+
+            return Ok(Parts::Finished(pid, unfinished_op, args, ret));
+        } else {
+            // this is an un-interrupted operation line
+            let re = Regex::new(
+                r"^(?P<pid>\d+) (?P<op>[^\(]+)\((?P<args>.*)\)\s+=\s+(?P<ret>0x[0-9a-f]+|\d+<.*?>|\d+|-\d+|\?)(\s.*)?$",
+            )?;
+
+            assert!(re.is_match(str));
+
+            let cap = re.captures(str).ok_or(Error::ParseError(str.to_string()))?;
+
+            return Ok(Parts::Finished(
+                cap["pid"].parse::<usize>()?,
+                cap["op"].to_string(),
+                cap["args"].to_string(),
+                cap["ret"].to_string(),
+            ));
+        }
+    }
+
+    // check the existance of the process id in the beginning of a line
+    // split the timestamp after the process id (strace -t, -tt, or -ttt) and the time spent in
+    // the operation at the end of the line (strace -T) from the rest of the line
+    //
+    // Example:
+    //      909194 12:34:56.123456 openat(AT_FDCWD, "a-path", O_RDONLY) = 3 <0.000021>
+    // or
+    //      909194 1650000000.123456 openat(AT_FDCWD, "a-path", O_RDONLY) = 3 <0.000021>
+    fn timing(&mut self, str: &str) -> Result<(String, Timing), Box<dyn std::error::Error>> {
+        let re = Regex::new(
+            r"^(?P<pid>\d+)\s+((?P<clock>\d{2}:\d{2}:\d{2}(\.\d+)?)|(?P<epoch>\d+\.\d+))\s+(?P<remaining>.+)$",
+        )?;
+        let duration_re = Regex::new(r"^(?P<remaining>.*\S)\s+<(?P<duration>\d+\.\d+)>$")?;
+
+        let (line, timestamp) = match re.captures(str) {
+            Some(cap) => {
+                let timestamp = if let Some(clock) = cap.name("clock") {
+                    let parts: Vec<&str> = clock.as_str().splitn(3, ":").collect();
+                    let hours = parts[0].parse::<u64>()?;
+                    let minutes = parts[1].parse::<u64>()?;
+                    let clock = Duration::from_secs(hours * 3600 + minutes * 60)
+                        + self.seconds(parts[2])?;
+
+                    // the wall clock starts over at midnight
+                    let day = Duration::from_secs(24 * 3600);
+                    if clock + day * self.days as u32 + day / 2 < self.last_timestamp {
+                        self.days += 1;
+                    }
+                    clock + day * self.days as u32
+                } else {
+                    self.seconds(&cap["epoch"])?
+                };
+                self.last_timestamp = timestamp;
+
+                (
+                    format!("{} {}", &cap["pid"], &cap["remaining"]),
+                    Some(timestamp),
+                )
+            }
+            None => (str.to_string(), None),
+        };
+
+        match duration_re.captures(&line) {
+            Some(cap) => {
+                let duration = self.seconds(&cap["duration"])?;
+                Ok((cap["remaining"].to_string(), (timestamp, Some(duration))))
+            }
+            None => Ok((line, (timestamp, None))),
+        }
+    }
+
+    // parse seconds with an optional fraction, like 56 or 56.123456
+    fn seconds(&self, str: &str) -> Result<Duration, Box<dyn std::error::Error>> {
+        let (secs, fraction) = str.split_once(".").unwrap_or((str, ""));
+        let nanos = format!("{:0<9}", fraction)[..9].parse::<u32>()?;
+
+        Ok(Duration::new(secs.parse::<u64>()?, nanos))
+    }
+
+    fn has_pid(&self, str: &str) -> Result<bool, Box<dyn std::error::Error>> {
+        let re = Regex::new(r"^(?P<pid>\d+) (?P<remaining>.+)$")?;
+        Ok(re.is_match(str))
+    }
+
+    // extract a path in-between " and " from the input string
+    fn path(&self, str: &str, callee: &str) -> Result<String, Box<dyn std::error::Error>> {
+        let path = str
+            .split_at(
+                str.find("\"")
+                    .ok_or(Error::NotFound(format!("\" from {} line", callee)))?
+                    + 1,
+            )
+            .1;
+        Ok(path
+            .split_at(
+                path.rfind("\"")
+                    .ok_or(Error::NotFound(format!("\" from {} line", callee)))?,
+            )
+            .0
+            .to_string())
+    }
+
+    // parse a decimal or hexadecimal (0x prefixed) number, such as an address or a mmap offset
+    fn number(&self, str: &str) -> Result<usize, Box<dyn std::error::Error>> {
+        let str = str.trim();
+        match str.strip_prefix("0x") {
+            Some(hex) => Ok(usize::from_str_radix(hex, 16)?),
+            None => Ok(str.parse::<usize>()?),
+        }
+    }
+
+    // extract the dirfd, which is the first argument of the *at syscalls
+    fn dirfd<'a>(
+        &self,
+        args: &'a str,
+        callee: &str,
+    ) -> Result<&'a str, Box<dyn std::error::Error>> {
+        Ok(args
+            .split_at(
+                args.find(",")
+                    .ok_or(Error::NotFound(format!(", from {} line", callee)))?,
+            )
+            .0)
+    }
+
+    // the current working directory of a process, if it is known
+    fn cwd(&self, pid: usize) -> Option<&str> {
+        let cwd = match self.process_cwd.get(&pid) {
+            Some(cwd) => cwd,
+            None => {
+                // there is no cwd set for the calling process, so its cwd is the global cwd
+                self.cwd.as_str()
+            }
+        };
+        // a cwd that is not absolute was resolved without knowing where the process was
+        match cwd.starts_with("/") {
+            true => Some(cwd),
+            false => None,
+        }
+    }
+
+    // resolve the path passed to a syscall to a normalised absolute path
+    fn resolve(
+        &mut self,
+        pid: usize,
+        dirfd: Option<&str>,
+        path: &str,
+    ) -> Result<String, Box<dyn std::error::Error>> {
+        // - If the path is absolute, then dirfd is ignored.
+        // - If the path is relative and dirfd is the special value AT_FDCWD, or there is no
+        //      dirfd, then the path is interpreted relative to the current working directory of
+        //      the calling process.
+        // - If the path is relative and dirfd is a file descriptor, then the path is relative
+        //      to the path of the directory described by the file descriptor.
+
+        if PathBuf::from(path).is_absolute() {
+            return Ok(normalize(path));
+        }
+
+        match dirfd {
+            Some(dirfd) if !dirfd.contains("AT_FDCWD") => {
+                // dirfd should be a valid file descriptor, so the input path is a relative path.
+                self.relative_to_absolute(pid, dirfd, path)
+            }
+            _ => match self.cwd(pid) {
+                Some(cwd) => Ok(join(cwd, path)),
+                // the cwd is not known, so the path is left relative
+                None => Ok(normalize_relative(path)),
+            },
+        }
+    }
+
+    // convert a relative path to absolute
+    fn relative_to_absolute(
+        &mut self,
+        pid: usize,
+        dirfd: &str,
+        relative: &str,
+    ) -> Result<String, Box<dyn std::error::Error>> {
+        // dirfd should be a valid file descriptor. When it is decorated with its path, the path
+        // populates or corrects the fd_map, like any other file descriptor
+        let dirfd = self.fd(pid, dirfd)?;
+        let dirfd_path = match self.fd_map.get(&(pid, dirfd)) {
+            Some(opened_file) => opened_file.path.clone(),
+            None => {
+                // the directory is not known, so the path is left relative
+                let relative = normalize_relative(relative);
+                self.unresolved_paths
+                    .push(UnresolvedPath::new(pid, dirfd, relative.clone()));
+                return Ok(relative);
+            }
+        };
+
+        // create the absolute path
+        Ok(join(&dirfd_path, relative))
+    }
+
+    fn file_type(
+        &self,
+        str: &str,
+        path: &str,
+        callee: &str,
+    ) -> Result<FileType, Box<dyn std::error::Error>> {
+        // extract the file type
+        let st_mode: String = str
+            .trim()
+            .split(",")
+            .filter(|stat| stat.contains("st_mode") || stat.contains("stx_mode"))
+            .map(|str| str.to_string())
+            .collect();
+        let file_type = st_mode
+            .split_at(
+                st_mode
+                    .find("=")
+                    .ok_or(Error::NotFound(format!("= from {}", callee)))?
+                    + 1,
+            )
+            .1
+            .trim();
+
+        // the mode is not a file or directory
+        if !file_type.contains("S_IFREG") && !file_type.contains("S_IFDIR") {
+            return Ok(FileType::Other);
+        }
+
+        // extract the file size
+        let st_size: String = str
+            .trim()
+            .split(",")
+            .filter(|stat| stat.contains("st_size") || stat.contains("stx_size"))
+            .map(|str| str.to_string())
+            .collect();
+        let file_size = st_size
+            .split_at(
+                st_size
+                    .find("=")
+                    .ok_or(Error::NotFound(format!("= from {}", callee)))?
+                    + 1,
+            )
+            .1
+            .trim()
+            .parse::<usize>()?;
+
+        if file_type.contains("S_IFREG") {
+            Ok(FileType::File(path.to_string(), file_size))
+        } else if file_type.contains("S_IFDIR") {
+            Ok(FileType::Dir(path.to_string(), file_size))
+        } else {
+            Ok(FileType::Other)
+        }
+    }
+
+    // get the list of existing files and directories accessed during log parsing
+    pub fn existing_files(&self) -> Result<HashSet<FileType>, Box<dyn std::error::Error>> {
+        Ok(self.existing_files.clone())
+    }
+
+    // get the files and directories that existed before the trace started
+    pub fn snapshot(&self) -> Result<Snapshot, Box<dyn std::error::Error>> {
+        Ok(Snapshot::new(
+            self.initial_files
+                .values()
+                .filter(|file_type| !self.rules.excludes(file_type.path()))
+                .map(|file_type| match file_type {
+                    FileType::File(path, size) => FileType::File(self.rules.rewritten(path), *size),
+                    FileType::Dir(path, size) => FileType::Dir(self.rules.rewritten(path), *size),
+                    FileType::Other => FileType::Other,
+                })
+                .collect(),
+        ))
+    }
+
+    // get the file descriptors whose tracked path differ from the path decorated by strace
+    pub fn fd_discrepancies(&self) -> Result<Vec<FdDiscrepancy>, Box<dyn std::error::Error>> {
+        Ok(self.fd_discrepancies.clone())
+    }
+
+    // get the relative paths left unresolved, because their directory fd was not known
+    pub fn unresolved_paths(&self) -> Result<Vec<UnresolvedPath>, Box<dyn std::error::Error>> {
+        Ok(self.unresolved_paths.clone())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::deps::DependencyGraph;
+    use crate::file::{File, FileKind};
+    use crate::op::{Operation, OperationType};
+    use crate::parser::{FdDiscrepancy, FileType, OpenedFile, Parser, Parts, UnresolvedPath};
+    use crate::process::Process;
+    use crate::rules::PathRules;
+    use std::path::PathBuf;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    #[test]
+    fn parts() -> Result<(), Box<dyn std::error::Error>> {
+        let mut parser = Parser::new(PathBuf::new());
+        let str = "909194 openat(AT_FDCWD, \"/proc/self/cgroup\", O_RDONLY|O_CLOEXEC) = 3";
+        let parts = parser.parts(str.as_ref())?;
+        assert_eq!(
+            parts,
+            Parts::Finished(
+                909194,
+                "openat".to_string(),
+                "AT_FDCWD, \"/proc/self/cgroup\", O_RDONLY|O_CLOEXEC".to_string(),
+                "3".to_string()
+            )
+        );
+
+        let str = "909194 close(3)                                = 0";
+        let parts = parser.parts(str.as_ref())?;
+        assert_eq!(
+            parts,
+            Parts::Finished(
+                909194,
+                "close".to_string(),
+                "3".to_string(),
+                "0".to_string()
+            )
+        );
+
+        let str =  "909194 statx(AT_FDCWD, \"a-path\", AT_STATX_SYNC_AS_STAT, STATX_ALL, 0x7ffeaceb9e30) = -1 ENOENT (No such file or directory)";
+        let parts = parser.parts(str.as_ref())?;
+        assert_eq!(
+            parts,
+            Parts::Finished(
+                909194,
+                "statx".to_string(),
+                "AT_FDCWD, \"a-path\", AT_STATX_SYNC_AS_STAT, STATX_ALL, 0x7ffeaceb9e30"
+                    .to_string(),
+                "-1".to_string()
+            )
+        );
+
+        let str = "909194 renameat2(AT_FDCWD, \"old_path\", AT_FDCWD, \"new_path\", RENAME_NOREPLACE) = 0";
+        let parts = parser.parts(str.as_ref())?;
+        assert_eq!(
+            parts,
+            Parts::Finished(
+                909194,
+                "renameat2".to_string(),
+                "AT_FDCWD, \"old_path\", AT_FDCWD, \"new_path\", RENAME_NOREPLACE".to_string(),
+                "0".to_string()
+            )
+        );
+
+        let str = "909194 getrandom( <unfinished ...>";
+        let parts = parser.parts(str.as_ref())?;
+        assert_eq!(
+            parts,
+            Parts::Unfinished(909194, "getrandom( <unfinished ...>".to_string())
+        );
+
+        let str = "909194 <... getrandom resumed>NULL, 0, GRND_NONBLOCK) = 0";
+        let parts = parser.parts(str.as_ref())?;
+        assert_eq!(
+            parts,
+            Parts::Finished(
+                909194,
+                "getrandom".to_string(),
+                "NULL, 0, GRND_NONBLOCK".to_string(),
+                "0".to_string()
+            )
+        );
+
+        let str = "909194 openat(AT_FDCWD, \"a-path\", O_WRONLY|O_CREAT|O_APPEND|O_CLOEXEC, 0666 <unfinished ...>";
+        let parts = parser.parts(str.as_ref())?;
+        assert_eq!(parts, Parts::Unfinished(909194, "openat(AT_FDCWD, \"a-path\", O_WRONLY|O_CREAT|O_APPEND|O_CLOEXEC, 0666 <unfinished ...>".to_string()));
+
+        let str = "909194 <... openat resumed>)            = 5";
+        let parts = parser.parts(str.as_ref())?;
+        assert_eq!(
+            parts,
+            Parts::Finished(
+                909194,
+                "openat".to_string(),
+                "AT_FDCWD, \"a-path\", O_WRONLY|O_CREAT|O_APPEND|O_CLOEXEC, 0666".to_string(),
+                "5".to_string()
+            )
+        );
+
+        let str = "909194 write(5, a-(buf, 4096 <unfinished ...>";
+        let parts = parser.parts(str.as_ref())?;
+        assert_eq!(
+            parts,
+            Parts::Unfinished(909194, "write(5, a-(buf, 4096 <unfinished ...>".to_string())
+        );
+
+        let str = "909194 <... write resumed>)             = 4096";
+        let parts = parser.parts(str.as_ref())?;
+        assert_eq!(
+            parts,
+            Parts::Finished(
+                909194,
+                "write".to_string(),
+                "5, a-(buf, 4096".to_string(),
+                "4096".to_string()
+            )
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn openat() -> Result<(), Box<dyn std::error::Error>> {
+        let mut parser = Parser::new(PathBuf::new());
+        let line = "909193 openat(AT_FDCWD, \"/a_path\", O_RDONLY|O_CLOEXEC) = 9".to_string();
+        if let Parts::Finished(pid, _, args, ret) = parser.parts(&line)? {
+            let operations = parser.openat(pid, args, ret)?;
+            assert_eq!(operations.len(), 1);
+            assert_eq!(
+                operations
+                    .first()
+                    .expect("failed to read the first entry of the vector")
+                    .op_type(),
+                &OperationType::OpenAt(Arc::new(File::new("/a_path")), 0)
+            );
+        } else {
+            panic!("{}", format!("could not get the parts from {}", line));
+        }
+
+        let line =
+            "909193 openat(AT_FDCWD, \"/another_path\", O_RDONLY|O_CREAT|O_CLOEXEC, 0666) = 7"
+                .to_string();
+        if let Parts::Finished(pid, _, args, ret) = parser.parts(&line)? {
+            let operations = parser.openat(pid, args, ret)?;
+            assert_eq!(operations.len(), 2);
+            assert_eq!(
+                operations
+                    .first()
+                    .expect("failed to read the first entry of the vector")
+                    .op_type(),
+                &OperationType::Mknod(Arc::new(File::new("/another_path")))
+            );
+            assert_eq!(
+                operations
+                    .get(1)
+                    .expect("failed to read the second entry of the vector")
+                    .op_type(),
+                &OperationType::OpenAt(Arc::new(File::new("/another_path")), 0)
+            );
+        } else {
+            panic!("{}", format!("could not get the parts from {}", line));
+        }
+
+        let line =
+            "909193 openat(AT_FDCWD, \"/another_path\", O_RDONLY|O_CREAT|O_APPEND|O_TRUNC, 0666) = 7"
+                .to_string();
+        if let Parts::Finished(pid, _, args, ret) = parser.parts(&line)? {
+            let operations = parser.openat(pid, args, ret)?;
+            assert_eq!(operations.len(), 3);
+            assert_eq!(
+                operations
+                    .first()
+                    .expect("failed to read the first entry of the vector")
+                    .op_type(),
+                &OperationType::Mknod(Arc::new(File::new("/another_path")))
+            );
+            assert_eq!(
+                operations
+                    .get(1)
+                    .expect("failed to read the second entry of the vector")
+                    .op_type(),
+                &OperationType::Truncate(Arc::new(File::new("/another_path")))
+            );
+            assert_eq!(
+                operations
+                    .get(2)
+                    .expect("failed to read the third entry of the vector")
+                    .op_type(),
+                &OperationType::OpenAt(Arc::new(File::new("/another_path")), 0)
+            );
+        } else {
+            panic!("{}", format!("could not get the parts from {}", line));
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn rules() -> Result<(), Box<dyn std::error::Error>> {
+        let mut parser = Parser::new(PathBuf::new());
+        let mut rules = PathRules::with_presets();
+        rules.rewrite("/home/user/project", "$ROOT");
+        parser.set_rules(rules);
+
+        let lines = [
+            "909193 openat(AT_FDCWD, \"/home/user/project/a.c\", O_RDONLY) = 3",
+            "909193 openat(AT_FDCWD, \"/etc/ld.so.cache\", O_RDONLY|O_CLOEXEC) = 4",
+        ];
+        let mut process = Process::new(909193);
+        for line in lines {
+            if let Parts::Finished(pid, _, args, ret) = parser.parts(line)? {
+                for operation in parser.openat(pid, args, ret)? {
+                    if let Some(operation) = parser.kept(operation) {
+                        parser.observe(&operation);
+                        process.add_op(operation);
+                    }
+                }
+            }
+        }
+        let mut processes = vec![process];
+        parser.rewrite(&mut processes)?;
+
+        assert_eq!(processes[0].ops().len(), 1);
+        let file = processes[0].ops()[0]
+            .file()
+            .expect("failed to get the file of the openat");
+        assert_eq!(file.path()?, "$ROOT/a.c");
+        assert_eq!(
+            parser.snapshot()?.files(),
+            &vec![
+                FileType::Dir("$ROOT".to_string(), 0),
+                FileType::File("$ROOT/a.c".to_string(), 0)
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn excluded_copy() {
+        let mut parser = Parser::new(PathBuf::new());
+        parser.set_rules(PathRules::with_presets());
+
+        let kept = Arc::new(File::new("/home/user/a"));
+        let excluded = Arc::new(File::new("/etc/passwd"));
+        let copy = |from: &Arc<File>, to: &Arc<File>| {
+            parser.kept(Operation::copy(from.clone(), 4, to.clone(), 8, 100))
+        };
+
+        // only the excluded side of the copy is dropped
+        assert_eq!(
+            copy(&excluded, &kept).map(|op| op.op_type().clone()),
+            Some(OperationType::Write(kept.clone(), 8, 100, String::new()))
+        );
+        assert_eq!(
+            copy(&kept, &excluded).map(|op| op.op_type().clone()),
+            Some(OperationType::Read(kept.clone(), 4, 100))
+        );
+        assert!(copy(&excluded, &excluded).is_none());
+        assert!(copy(&kept, &kept).is_some());
+    }
+
+    #[test]
+    fn read() -> Result<(), Box<dyn std::error::Error>> {
+        let mut parser = Parser::new(PathBuf::new());
+        let openat_line =
+            "909196 openat(AT_FDCWD, \"/a_path\", O_RDONLY|O_CLOEXEC) = 3".to_string();
+        if let Parts::Finished(pid, _, args, ret) = parser.parts(&openat_line)? {
+            let _operation = parser.openat(pid, args, ret)?;
+        } else {
+            panic!(
+                "{}",
+                format!("could not get the parts from {}", openat_line)
+            );
+        }
+
+        let read_line1 = "909196 read(3, buf, 50) = 50".to_string();
+        if let Parts::Finished(pid, _, args, _) = parser.parts(&read_line1)? {
+            let read_op1 = parser.read(pid, args)?;
+            assert_eq!(
+                read_op1.op_type(),
+                &OperationType::Read(Arc::new(File::new("/a_path")), 0, 50)
+            );
+        } else {
+            panic!("{}", format!("could not get the parts from {}", read_line1));
+        }
+
+        let read_line2 = "909196 read(3, buf, 20) = 20".to_string();
+        if let Parts::Finished(pid, _, args, _) = parser.parts(&read_line2)? {
+            let read_op2 = parser.read(pid, args)?;
+            assert_eq!(
+                read_op2.op_type(),
+                &OperationType::Read(Arc::new(File::new("/a_path")), 50, 20)
+            );
+        } else {
+            panic!("{}", format!("could not get the parts from {}", read_line2));
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn pread() -> Result<(), Box<dyn std::error::Error>> {
+        let mut parser = Parser::new(PathBuf::new());
+        let openat_line =
+            "909196 openat(AT_FDCWD, \"/a_path\", O_RDONLY|O_CLOEXEC) = 3".to_string();
+        if let Parts::Finished(pid, _, args, ret) = parser.parts(&openat_line)? {
+            let _operation = parser.openat(pid, args, ret)?;
+        } else {
+            panic!(
+                "{}",
+                format!("could not get the parts from {}", openat_line)
+            );
+        }
+
+        let read_line1 = "909196 pread(3, buf, 50, 100) = 50".to_string();
+        if let Parts::Finished(pid, _, args, _) = parser.parts(&read_line1)? {
+            let pread_op1 = parser.pread(pid, args)?;
+            assert_eq!(
+                pread_op1.op_type(),
+                &OperationType::Read(Arc::new(File::new("/a_path")), 100, 50)
+            );
+        } else {
+            panic!("{}", format!("could not get the parts from {}", read_line1));
+        }
+
+        let read_line2 = "909196 pread(3, buf, 20, 500) = 20".to_string();
+        if let Parts::Finished(pid, _, args, _) = parser.parts(&read_line2)? {
+            let pread_op2 = parser.pread(pid, args)?;
+            assert_eq!(
+                pread_op2.op_type(),
+                &OperationType::Read(Arc::new(File::new("/a_path")), 500, 20)
+            );
+        } else {
+            panic!("{}", format!("could not get the parts from {}", read_line2));
+        }
+
+        // the previous pread ops should not update the opened file offset
+        let read_line3 = "909196 read(3, buf, 20) = 20".to_string();
+        if let Parts::Finished(pid, _, args, _) = parser.parts(&read_line3)? {
+            let read_op = parser.read(pid, args)?;
+            assert_eq!(
+                read_op.op_type(),
+                &OperationType::Read(Arc::new(File::new("/a_path")), 0, 20)
+            );
+        } else {
+            panic!("{}", format!("could not get the parts from {}", read_line3));
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn write() -> Result<(), Box<dyn std::error::Error>> {
+        let mut parser = Parser::new(PathBuf::new());
+        let line = "909196 openat(AT_FDCWD, \"/a_path\", O_RDONLY|O_CREAT) = 5".to_string();
+        if let Parts::Finished(pid, _, args, ret) = parser.parts(&line)? {
+            let _operations = parser.openat(pid, args, ret)?;
+        } else {
+            panic!("{}", format!("could not get the parts from {}", line));
+        }
+
+        // first write
+        let write_line1 = "909196 write(5, some content here, 17) = 17".to_string();
+        if let Parts::Finished(pid, _, args, _) = parser.parts(&write_line1)? {
+            let write_op1 = parser.write(pid, args)?;
+            assert_eq!(
+                write_op1.op_type(),
+                &OperationType::Write(
+                    Arc::new(File::new("/a_path")),
+                    0,
+                    17,
+                    "some content here".to_string()
+                )
+            );
+        } else {
+            panic!(
+                "{}",
+                format!("could not get the parts from {}", write_line1)
+            );
+        }
+
+        // second write
+        let write_line2 = "909196 write(5, hello, 5) = 5".to_string();
+        if let Parts::Finished(pid, _, args, _) = parser.parts(&write_line2)? {
+            let write_op2 = parser.write(pid, args)?;
+            assert_eq!(
+                write_op2.op_type(),
+                &OperationType::Write(Arc::new(File::new("/a_path")), 17, 5, "hello".to_string())
+            );
+        } else {
+            panic!(
+                "{}",
+                format!("could not get the parts from {}", write_line2)
+            );
+        }
+
+        // open the file one more time to check the offset
+        let line = "909196 openat(AT_FDCWD, \"/a_path\", O_RDONLY|O_CREAT) = 5".to_string();
+        if let Parts::Finished(pid, _, args, ret) = parser.parts(&line)? {
+            let _operations = parser.openat(pid, args, ret)?;
+        } else {
+            panic!("{}", format!("could not get the parts from {}", line));
+        }
+
+        // now open the file with truncate flag, which should zero the size and offset
+        let line = "909196 openat(AT_FDCWD, \"/a_path\", O_RDONLY|O_TRUNC) = 5".to_string();
+        if let Parts::Finished(pid, _, args, ret) = parser.parts(&line)? {
+            let _operations = parser.openat(pid, args, ret)?;
+        } else {
+            panic!("{}", format!("could not get the parts from {}", line));
+        }
+
+        // write after truncate
+        let write_line2 = "909196 write(5, some other content here, 10) = 10".to_string();
+        if let Parts::Finished(pid, _, args, _) = parser.parts(&write_line2)? {
+            let write_op2 = parser.write(pid, args)?;
+            assert_eq!(
+                write_op2.op_type(),
+                &OperationType::Write(
+                    Arc::new(File::new("/a_path")),
+                    0,
+                    10,
+                    "some other content here".to_string()
+                )
+            );
+        } else {
+            panic!(
+                "{}",
+                format!("could not get the parts from {}", write_line2)
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn get_random() -> Result<(), Box<dyn std::error::Error>> {
+        let mut parser = Parser::new(PathBuf::new());
+        let line = "909196 getrandom(a_buf, 16, GRND_NONBLOCK) = 16".to_string();
+        if let Parts::Finished(_, _, args, _) = parser.parts(&line)? {
+            let operation = parser.get_random(args)?;
+            assert_eq!(operation.op_type(), &OperationType::GetRandom(16));
+        } else {
+            panic!("{}", format!("could not get the parts from {}", line));
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn fstat() -> Result<(), Box<dyn std::error::Error>> {
+        let mut parser = Parser::new(PathBuf::new());
+        let openat_line = "909196 openat(AT_FDCWD, \"a_path\", O_RDONLY|O_CLOEXEC) = 3".to_string();
+        if let Parts::Finished(pid, _, args, ret) = parser.parts(&openat_line)? {
+            let _operation = parser.openat(pid, args, ret)?;
+        } else {
+            panic!(
+                "{}",
+                format!("could not get the parts from {}", openat_line)
+            );
+        }
+
+        let fstat_line =
+            "909196 fstat(3, {st_mode=S_IFREG|0644, st_size=95921, ...}) = 0".to_string();
+        if let Parts::Finished(pid, _, args, _) = parser.parts(&fstat_line)? {
+            let fstat_op = parser.fstat(pid, args)?;
+            assert_eq!(
+                fstat_op.op_type(),
+                &OperationType::Fstat(Arc::new(File::new("a_path")))
+            );
+
+            assert_eq!(parser.existing_files.len(), 1);
+            let file_size = parser
+                .existing_files
+                .iter()
+                .next()
+                .expect("failed to get the first element from hashset")
+                .size();
+            assert_eq!(*file_size, 95921_usize);
+        } else {
+            panic!("{}", format!("could not get the parts from {}", fstat_line));
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn mkdir() -> Result<(), Box<dyn std::error::Error>> {
+        let mut parser = Parser::new(PathBuf::new());
+
+        let mkdir_line = "909196 mkdir(\"a_path\", 0777) = 0".to_string();
+        if let Parts::Finished(pid, _, args, _) = parser.parts(&mkdir_line)? {
+            let mkdir_op = parser.mkdir(pid, args)?;
+            assert_eq!(
+                mkdir_op.op_type(),
+                &OperationType::Mkdir(Arc::new(File::new("a_path")), "0777".to_string())
+            );
+        } else {
+            panic!("{}", format!("could not get the parts from {}", mkdir_line));
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn rename() -> Result<(), Box<dyn std::error::Error>> {
+        let mut parser = Parser::new(PathBuf::new());
+        let line = "909196 rename(\"old_path\", \"new_path\") = 0".to_string();
+        if let Parts::Finished(pid, _, args, _) = parser.parts(&line)? {
+            let operation = parser.rename(pid, args)?;
+            assert_eq!(
+                operation.op_type(),
+                &OperationType::Rename(Arc::new(File::new("old_path")), "new_path".to_string())
+            );
+        } else {
+            panic!("{}", format!("could not get the parts from {}", line));
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn mmap() -> Result<(), Box<dyn std::error::Error>> {
+        let mut parser = Parser::new(PathBuf::new());
+        let openat_line = "909196 openat(AT_FDCWD, \"/a_path\", O_RDWR) = 3".to_string();
+        if let Parts::Finished(pid, _, args, ret) = parser.parts(&openat_line)? {
+            let _operation = parser.openat(pid, args, ret)?;
+        } else {
+            panic!(
+                "{}",
+                format!("could not get the parts from {}", openat_line)
+            );
+        }
+
+        let mmap_line =
+            "909196 mmap(NULL, 8192, PROT_READ|PROT_WRITE, MAP_SHARED, 3, 0x1000) = 0x7fdfc8338000"
+                .to_string();
+        if let Parts::Finished(pid, _, args, ret) = parser.parts(&mmap_line)? {
+            assert_eq!(ret, "0x7fdfc8338000".to_string());
+            let mmap_ops = parser.mmap(pid, args, ret)?;
+            assert_eq!(
+                mmap_ops[0].op_type(),
+                &OperationType::Map(
+                    Arc::new(File::new("/a_path")),
+                    4096,
+                    8192,
+                    "PROT_READ|PROT_WRITE".to_string(),
+                    "MAP_SHARED".to_string()
+                )
+            );
+            assert_eq!(mmap_ops[0].accesses()[0].1, "Write".to_string());
+        } else {
+            panic!("{}", format!("could not get the parts from {}", mmap_line));
+        }
+
+        // anonymous mappings are not backed by a file
+        let mmap_line = "909196 mmap(NULL, 8192, PROT_READ|PROT_WRITE, MAP_PRIVATE|MAP_ANONYMOUS, -1, 0) = 0x7fdfc8336000".to_string();
+        if let Parts::Finished(pid, _, args, ret) = parser.parts(&mmap_line)? {
+            assert!(parser.mmap(pid, args, ret)?.is_empty());
+        } else {
+            panic!("{}", format!("could not get the parts from {}", mmap_line));
+        }
+
+        // msync inside the mapped region syncs the mapped file
+        let msync_line = "909196 msync(0x7fdfc8339000, 4096, MS_SYNC) = 0".to_string();
+        if let Parts::Finished(pid, _, args, _) = parser.parts(&msync_line)? {
+            let msync_op = parser.msync(pid, args)?;
+            assert_eq!(
+                msync_op.op_type(),
+                &OperationType::Sync(Arc::new(File::new("/a_path")))
+            );
+        } else {
+            panic!("{}", format!("could not get the parts from {}", msync_line));
+        }
+
+        // after munmap, the region is not mapped anymore
+        let munmap_line = "909196 munmap(0x7fdfc8338000, 8192) = 0".to_string();
+        if let Parts::Finished(pid, _, args, _) = parser.parts(&munmap_line)? {
+            assert!(parser.munmap(pid, args)?.is_empty());
+        } else {
+            panic!(
+                "{}",
+                format!("could not get the parts from {}", munmap_line)
+            );
+        }
 
-        if file_type.contains("S_IFREG") {
-            Ok(FileType::File(path.to_string(), file_size))
-        } else if file_type.contains("S_IFDIR") {
-            Ok(FileType::Dir(path.to_string(), file_size))
+        if let Parts::Finished(pid, _, args, _) = parser.parts(&msync_line)? {
+            let msync_op = parser.msync(pid, args)?;
+            assert_eq!(msync_op.op_type(), &OperationType::NoOp);
         } else {
-            Ok(FileType::Other)
+            panic!("{}", format!("could not get the parts from {}", msync_line));
         }
-    }
 
-    // get the list of existing files and directories accessed during log parsing
-    pub fn existing_files(&self) -> Result<HashSet<FileType>, Box<dyn std::error::Error>> {
-        Ok(self.existing_files.clone())
-    }
-}
+        // the offsets past 2 GiB do not fit the operations
+        let mmap_line =
+            "909196 mmap(NULL, 4096, PROT_READ, MAP_PRIVATE, 3, 0x80000000) = 0x7fdfc8338000"
+                .to_string();
+        if let Parts::Finished(pid, _, args, ret) = parser.parts(&mmap_line)? {
+            assert!(parser.mmap(pid, args, ret).is_err());
+        } else {
+            panic!("{}", format!("could not get the parts from {}", mmap_line));
+        }
 
-#[cfg(test)]
-mod test {
-    use crate::file::File;
-    use crate::op::OperationType;
-    use crate::parser::{Parser, Parts};
-    use std::path::PathBuf;
-    use std::sync::Arc;
+        Ok(())
+    }
 
     #[test]
-    fn parts() -> Result<(), Box<dyn std::error::Error>> {
+    fn pipe() -> Result<(), Box<dyn std::error::Error>> {
         let mut parser = Parser::new(PathBuf::new());
-        let str = "909194 openat(AT_FDCWD, \"/proc/self/cgroup\", O_RDONLY|O_CLOEXEC) = 3";
-        let parts = parser.parts(str.as_ref())?;
-        assert_eq!(
-            parts,
-            Parts::Finished(
-                909194,
-                "openat".to_string(),
-                "AT_FDCWD, \"/proc/self/cgroup\", O_RDONLY|O_CLOEXEC".to_string(),
-                "3".to_string()
-            )
-        );
-
-        let str = "909194 close(3)                                = 0";
-        let parts = parser.parts(str.as_ref())?;
-        assert_eq!(
-            parts,
-            Parts::Finished(
-                909194,
-                "close".to_string(),
-                "3".to_string(),
-                "0".to_string()
-            )
-        );
-
-        let str =  "909194 statx(AT_FDCWD, \"a-path\", AT_STATX_SYNC_AS_STAT, STATX_ALL, 0x7ffeaceb9e30) = -1 ENOENT (No such file or directory)";
-        let parts = parser.parts(str.as_ref())?;
-        assert_eq!(
-            parts,
-            Parts::Finished(
-                909194,
-                "statx".to_string(),
-                "AT_FDCWD, \"a-path\", AT_STATX_SYNC_AS_STAT, STATX_ALL, 0x7ffeaceb9e30"
-                    .to_string(),
-                "-1".to_string()
-            )
-        );
-
-        let str = "909194 renameat2(AT_FDCWD, \"old_path\", AT_FDCWD, \"new_path\", RENAME_NOREPLACE) = 0";
-        let parts = parser.parts(str.as_ref())?;
-        assert_eq!(
-            parts,
-            Parts::Finished(
-                909194,
-                "renameat2".to_string(),
-                "AT_FDCWD, \"old_path\", AT_FDCWD, \"new_path\", RENAME_NOREPLACE".to_string(),
-                "0".to_string()
-            )
-        );
-
-        let str = "909194 getrandom( <unfinished ...>";
-        let parts = parser.parts(str.as_ref())?;
-        assert_eq!(
-            parts,
-            Parts::Unfinished(909194, "getrandom( <unfinished ...>".to_string())
-        );
+        let pipe_line = "909190 pipe2([6, 7], O_CLOEXEC) = 0".to_string();
+        if let Parts::Finished(pid, _, args, _) = parser.parts(&pipe_line)? {
+            let _operation = parser.pipe(pid, args)?;
+        } else {
+            panic!("{}", format!("could not get the parts from {}", pipe_line));
+        }
 
-        let str = "909194 <... getrandom resumed>NULL, 0, GRND_NONBLOCK) = 0";
-        let parts = parser.parts(str.as_ref())?;
-        assert_eq!(
-            parts,
-            Parts::Finished(
-                909194,
-                "getrandom".to_string(),
-                "NULL, 0, GRND_NONBLOCK".to_string(),
-                "0".to_string()
-            )
-        );
+        // the child inherits the pipe and moves the write end to its STDOUT
+        let clone_line = "909190 clone(child_stack=NULL, flags=CLONE_CHILD_CLEARTID|CLONE_CHILD_SETTID|SIGCHLD, child_tidptr=0x7f0dae0a6a10) = 909191".to_string();
+        if let Parts::Finished(pid, _, _, ret) = parser.parts(&clone_line)? {
+            let clone_op = parser.clone(pid, ret)?;
+            assert_eq!(clone_op.op_type(), &OperationType::Clone(909191));
+        } else {
+            panic!("{}", format!("could not get the parts from {}", clone_line));
+        }
 
-        let str = "909194 openat(AT_FDCWD, \"a-path\", O_WRONLY|O_CREAT|O_APPEND|O_CLOEXEC, 0666 <unfinished ...>";
-        let parts = parser.parts(str.as_ref())?;
-        assert_eq!(parts, Parts::Unfinished(909194, "openat(AT_FDCWD, \"a-path\", O_WRONLY|O_CREAT|O_APPEND|O_CLOEXEC, 0666 <unfinished ...>".to_string()));
+        let dup_line = "909191 dup2(7, 1) = 1".to_string();
+        if let Parts::Finished(pid, _, args, ret) = parser.parts(&dup_line)? {
+            let _operation = parser.dup(pid, args, ret)?;
+        } else {
+            panic!("{}", format!("could not get the parts from {}", dup_line));
+        }
 
-        let str = "909194 <... openat resumed>)            = 5";
-        let parts = parser.parts(str.as_ref())?;
-        assert_eq!(
-            parts,
-            Parts::Finished(
-                909194,
-                "openat".to_string(),
-                "AT_FDCWD, \"a-path\", O_WRONLY|O_CREAT|O_APPEND|O_CLOEXEC, 0666".to_string(),
-                "5".to_string()
-            )
-        );
+        let write_line = "909191 write(1, hello, 5) = 5".to_string();
+        if let Parts::Finished(pid, _, args, _) = parser.parts(&write_line)? {
+            let write_op = parser.write(pid, args)?;
+            let file = write_op
+                .file()
+                .expect("the write operation should access the pipe");
+            assert_eq!(file.kind(), FileKind::Pipe);
+            assert_eq!(file.path()?, "pipe:[1]");
+        } else {
+            panic!("{}", format!("could not get the parts from {}", write_line));
+        }
 
-        let str = "909194 write(5, a-(buf, 4096 <unfinished ...>";
-        let parts = parser.parts(str.as_ref())?;
-        assert_eq!(
-            parts,
-            Parts::Unfinished(909194, "write(5, a-(buf, 4096 <unfinished ...>".to_string())
-        );
+        // the parent reads from the read end of the same pipe
+        let read_line = "909190 read(6, hello, 5) = 5".to_string();
+        if let Parts::Finished(pid, _, args, _) = parser.parts(&read_line)? {
+            let read_op = parser.read(pid, args)?;
+            assert_eq!(
+                read_op.op_type(),
+                &OperationType::Read(Arc::new(File::with_kind("pipe:[1]", FileKind::Pipe)), 0, 5)
+            );
+        } else {
+            panic!("{}", format!("could not get the parts from {}", read_line));
+        }
 
-        let str = "909194 <... write resumed>)             = 4096";
-        let parts = parser.parts(str.as_ref())?;
-        assert_eq!(
-            parts,
-            Parts::Finished(
-                909194,
-                "write".to_string(),
-                "5, a-(buf, 4096".to_string(),
-                "4096".to_string()
-            )
-        );
+        // writing to an untracked STDOUT does not generate an operation
+        let write_line = "909190 write(1, hello, 5) = 5".to_string();
+        if let Parts::Finished(pid, _, args, _) = parser.parts(&write_line)? {
+            let write_op = parser.write(pid, args)?;
+            assert_eq!(write_op.op_type(), &OperationType::NoOp);
+        } else {
+            panic!("{}", format!("could not get the parts from {}", write_line));
+        }
 
         Ok(())
     }
 
     #[test]
-    fn openat() -> Result<(), Box<dyn std::error::Error>> {
+    fn copy() -> Result<(), Box<dyn std::error::Error>> {
         let mut parser = Parser::new(PathBuf::new());
-        let line = "909193 openat(AT_FDCWD, \"/a_path\", O_RDONLY|O_CLOEXEC) = 9".to_string();
-        if let Parts::Finished(pid, _, args, ret) = parser.parts(&line)? {
-            let operations = parser.openat(pid, args, ret)?;
-            assert_eq!(operations.len(), 1);
+        let lines = vec![
+            "909196 openat(AT_FDCWD, \"/src\", O_RDONLY|O_CLOEXEC) = 3",
+            "909196 openat(AT_FDCWD, \"/dst\", O_WRONLY|O_CREAT|O_TRUNC, 0644) = 4",
+        ];
+        for line in lines {
+            if let Parts::Finished(pid, _, args, ret) = parser.parts(line)? {
+                let _operations = parser.openat(pid, args, ret)?;
+            } else {
+                panic!("{}", format!("could not get the parts from {}", line));
+            }
+        }
+
+        // the copy with NULL offsets uses and moves the file offsets
+        let line = "909196 copy_file_range(3, NULL, 4, NULL, 9223372035781033984, 0) = 1335";
+        if let Parts::Finished(pid, _, args, ret) = parser.parts(line)? {
+            let copy_op = parser.copy_file_range(pid, args, ret)?;
             assert_eq!(
-                operations
-                    .get(0)
-                    .expect("failed to read the first entry of the vector")
-                    .op_type(),
-                &OperationType::OpenAt(Arc::new(File::new("/a_path")), 0)
+                copy_op.op_type(),
+                &OperationType::Copy(
+                    Arc::new(File::new("/src")),
+                    0,
+                    Arc::new(File::new("/dst")),
+                    0,
+                    1335
+                )
             );
+            let accesses = copy_op.accesses();
+            assert_eq!(accesses[0].1, "Read".to_string());
+            assert_eq!(accesses[1].1, "Write".to_string());
         } else {
             panic!("{}", format!("could not get the parts from {}", line));
         }
 
-        let line =
-            "909193 openat(AT_FDCWD, \"/another_path\", O_RDONLY|O_CREAT|O_CLOEXEC, 0666) = 7"
-                .to_string();
-        if let Parts::Finished(pid, _, args, ret) = parser.parts(&line)? {
-            let operations = parser.openat(pid, args, ret)?;
-            assert_eq!(operations.len(), 2);
+        // the copy with an explicit source offset does not move the source file offset
+        let line = "909196 sendfile(4, 3, [100] => [200], 100) = 100";
+        if let Parts::Finished(pid, _, args, ret) = parser.parts(line)? {
+            let copy_op = parser.sendfile(pid, args, ret)?;
             assert_eq!(
-                operations
-                    .get(0)
-                    .expect("failed to read the first entry of the vector")
-                    .op_type(),
-                &OperationType::Mknod(Arc::new(File::new("/another_path")))
+                copy_op.op_type(),
+                &OperationType::Copy(
+                    Arc::new(File::new("/src")),
+                    100,
+                    Arc::new(File::new("/dst")),
+                    1335,
+                    100
+                )
             );
+        } else {
+            panic!("{}", format!("could not get the parts from {}", line));
+        }
+
+        let line = "909196 splice(3, NULL, 4, [0 => 10], 10, SPLICE_F_MOVE) = 10";
+        if let Parts::Finished(pid, _, args, ret) = parser.parts(line)? {
+            let copy_op = parser.splice(pid, args, ret)?;
             assert_eq!(
-                operations
-                    .get(1)
-                    .expect("failed to read the second entry of the vector")
-                    .op_type(),
-                &OperationType::OpenAt(Arc::new(File::new("/another_path")), 0)
+                copy_op.op_type(),
+                &OperationType::Copy(
+                    Arc::new(File::new("/src")),
+                    1335,
+                    Arc::new(File::new("/dst")),
+                    0,
+                    10
+                )
             );
         } else {
             panic!("{}", format!("could not get the parts from {}", line));
         }
 
-        let line =
-            "909193 openat(AT_FDCWD, \"/another_path\", O_RDONLY|O_CREAT|O_APPEND|O_TRUNC, 0666) = 7"
-                .to_string();
-        if let Parts::Finished(pid, _, args, ret) = parser.parts(&line)? {
-            let operations = parser.openat(pid, args, ret)?;
-            assert_eq!(operations.len(), 3);
+        // copying to an untracked descriptor only reads the source
+        let line = "909196 sendfile(1, 3, NULL, 5) = 5";
+        if let Parts::Finished(pid, _, args, ret) = parser.parts(line)? {
+            let read_op = parser.sendfile(pid, args, ret)?;
             assert_eq!(
-                operations
-                    .get(0)
-                    .expect("failed to read the first entry of the vector")
-                    .op_type(),
-                &OperationType::Mknod(Arc::new(File::new("/another_path")))
+                read_op.op_type(),
+                &OperationType::Read(Arc::new(File::new("/src")), 1345, 5)
             );
+        } else {
+            panic!("{}", format!("could not get the parts from {}", line));
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn renameat() -> Result<(), Box<dyn std::error::Error>> {
+        let mut parser = Parser::new(PathBuf::new());
+        let line =
+            "909196 renameat2(AT_FDCWD, \"old_path\", AT_FDCWD, \"new_path\", RENAME_NOREPLACE) = 0"
+                .to_string();
+        if let Parts::Finished(pid, _, args, _) = parser.parts(&line)? {
+            let operation = parser.renameat(pid, args)?;
             assert_eq!(
-                operations
-                    .get(1)
-                    .expect("failed to read the second entry of the vector")
-                    .op_type(),
-                &OperationType::Truncate(Arc::new(File::new("/another_path")))
+                operation.op_type(),
+                &OperationType::Rename(Arc::new(File::new("old_path")), "new_path".to_string())
             );
+        } else {
+            panic!("{}", format!("could not get the parts from {}", line));
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn unknown_cwd() -> Result<(), Box<dyn std::error::Error>> {
+        let mut parser = Parser::new(PathBuf::new());
+
+        // the path is normalized, but stays relative to the unknown cwd
+        let line = "909196 unlinkat(AT_FDCWD, \"a/./b/../../../c//d\", 0) = 0".to_string();
+        if let Parts::Finished(pid, _, args, _) = parser.parts(&line)? {
+            let unlink_op = parser.unlink(pid, args)?;
             assert_eq!(
-                operations
-                    .get(2)
-                    .expect("failed to read the third entry of the vector")
-                    .op_type(),
-                &OperationType::OpenAt(Arc::new(File::new("/another_path")), 0)
+                unlink_op.op_type(),
+                &OperationType::Remove(Arc::new(File::new("../c/d")))
             );
         } else {
             panic!("{}", format!("could not get the parts from {}", line));
@@ -1250,51 +3299,70 @@ mod test {
     }
 
     #[test]
-    fn read() -> Result<(), Box<dyn std::error::Error>> {
+    fn cwd() -> Result<(), Box<dyn std::error::Error>> {
         let mut parser = Parser::new(PathBuf::new());
-        let openat_line =
-            "909196 openat(AT_FDCWD, \"/a_path\", O_RDONLY|O_CLOEXEC) = 3".to_string();
-        if let Parts::Finished(pid, _, args, ret) = parser.parts(&openat_line)? {
-            let _operation = parser.openat(pid, args, ret)?;
+
+        let getcwd_line = "909196 getcwd(\"/home/user\", 4096) = 11".to_string();
+        if let Parts::Finished(pid, _, args, _) = parser.parts(&getcwd_line)? {
+            let _operation = parser.getcwd(pid, args)?;
         } else {
             panic!(
                 "{}",
-                format!("could not get the parts from {}", openat_line)
+                format!("could not get the parts from {}", getcwd_line)
             );
         }
 
-        let read_line1 = "909196 read(3, buf, 50) = 50".to_string();
-        if let Parts::Finished(pid, _, args, _) = parser.parts(&read_line1)? {
-            let read_op1 = parser.read(pid, args)?;
+        // a relative chdir is resolved against the current working directory
+        let chdir_line = "909196 chdir(\"project/./src/..\") = 0".to_string();
+        if let Parts::Finished(pid, _, args, _) = parser.parts(&chdir_line)? {
+            let chdir_op = parser.chdir(pid, args)?;
             assert_eq!(
-                read_op1.op_type(),
-                &OperationType::Read(Arc::new(File::new("/a_path")), 0, 50)
+                chdir_op.op_type(),
+                &OperationType::Chdir("/home/user/project".to_string())
             );
         } else {
-            panic!("{}", format!("could not get the parts from {}", read_line1));
+            panic!("{}", format!("could not get the parts from {}", chdir_line));
         }
 
-        let read_line2 = "909196 read(3, buf, 20) = 20".to_string();
-        if let Parts::Finished(pid, _, args, _) = parser.parts(&read_line2)? {
-            let read_op2 = parser.read(pid, args)?;
+        let stat_line =
+            "909196 stat(\"target//debug\", {st_mode=S_IFDIR|0775, st_size=4096, ...}) = 0"
+                .to_string();
+        if let Parts::Finished(pid, _, args, _) = parser.parts(&stat_line)? {
+            let stat_op = parser.stat(pid, args)?;
             assert_eq!(
-                read_op2.op_type(),
-                &OperationType::Read(Arc::new(File::new("/a_path")), 50, 20)
+                stat_op.op_type(),
+                &OperationType::Stat(Arc::new(File::new("/home/user/project/target/debug")))
             );
         } else {
-            panic!("{}", format!("could not get the parts from {}", read_line2));
+            panic!("{}", format!("could not get the parts from {}", stat_line));
         }
 
-        Ok(())
-    }
+        // a cloned process starts in the current working directory of its parent
+        let clone_line = "909196 clone(child_stack=NULL, flags=SIGCHLD) = 909197".to_string();
+        if let Parts::Finished(pid, _, _, ret) = parser.parts(&clone_line)? {
+            let _operation = parser.clone(pid, ret)?;
+        } else {
+            panic!("{}", format!("could not get the parts from {}", clone_line));
+        }
 
-    #[test]
-    fn pread() -> Result<(), Box<dyn std::error::Error>> {
-        let mut parser = Parser::new(PathBuf::new());
-        let openat_line =
-            "909196 openat(AT_FDCWD, \"/a_path\", O_RDONLY|O_CLOEXEC) = 3".to_string();
+        let unlink_line = "909197 unlinkat(AT_FDCWD, \"../a_file\", 0) = 0".to_string();
+        if let Parts::Finished(pid, _, args, _) = parser.parts(&unlink_line)? {
+            let unlink_op = parser.unlink(pid, args)?;
+            assert_eq!(
+                unlink_op.op_type(),
+                &OperationType::Remove(Arc::new(File::new("/home/user/a_file")))
+            );
+        } else {
+            panic!(
+                "{}",
+                format!("could not get the parts from {}", unlink_line)
+            );
+        }
+
+        // fchdir changes the current working directory to an opened directory
+        let openat_line = "909197 openat(AT_FDCWD, \"/tmp\", O_RDONLY|O_DIRECTORY) = 3".to_string();
         if let Parts::Finished(pid, _, args, ret) = parser.parts(&openat_line)? {
-            let _operation = parser.openat(pid, args, ret)?;
+            let _operations = parser.openat(pid, args, ret)?;
         } else {
             panic!(
                 "{}",
@@ -1302,228 +3370,574 @@ mod test {
             );
         }
 
-        let read_line1 = "909196 pread(3, buf, 50, 100) = 50".to_string();
-        if let Parts::Finished(pid, _, args, _) = parser.parts(&read_line1)? {
-            let pread_op1 = parser.pread(pid, args)?;
+        let fchdir_line = "909197 fchdir(3) = 0".to_string();
+        if let Parts::Finished(pid, _, args, _) = parser.parts(&fchdir_line)? {
+            let fchdir_op = parser.fchdir(pid, args)?;
             assert_eq!(
-                pread_op1.op_type(),
-                &OperationType::Read(Arc::new(File::new("/a_path")), 100, 50)
+                fchdir_op.op_type(),
+                &OperationType::Chdir("/tmp".to_string())
             );
         } else {
-            panic!("{}", format!("could not get the parts from {}", read_line1));
-        }
-
-        let read_line2 = "909196 pread(3, buf, 20, 500) = 20".to_string();
-        if let Parts::Finished(pid, _, args, _) = parser.parts(&read_line2)? {
-            let pread_op2 = parser.pread(pid, args)?;
-            assert_eq!(
-                pread_op2.op_type(),
-                &OperationType::Read(Arc::new(File::new("/a_path")), 500, 20)
+            panic!(
+                "{}",
+                format!("could not get the parts from {}", fchdir_line)
             );
-        } else {
-            panic!("{}", format!("could not get the parts from {}", read_line2));
         }
 
-        // the previous pread ops should not update the opened file offset
-        let read_line3 = "909196 read(3, buf, 20) = 20".to_string();
-        if let Parts::Finished(pid, _, args, _) = parser.parts(&read_line3)? {
-            let read_op = parser.read(pid, args)?;
+        let mkdir_line = "909197 mkdir(\"a_dir\", 0777) = 0".to_string();
+        if let Parts::Finished(pid, _, args, _) = parser.parts(&mkdir_line)? {
+            let mkdir_op = parser.mkdir(pid, args)?;
             assert_eq!(
-                read_op.op_type(),
-                &OperationType::Read(Arc::new(File::new("/a_path")), 0, 20)
+                mkdir_op.op_type(),
+                &OperationType::Mkdir(Arc::new(File::new("/tmp/a_dir")), "0777".to_string())
             );
         } else {
-            panic!("{}", format!("could not get the parts from {}", read_line3));
+            panic!("{}", format!("could not get the parts from {}", mkdir_line));
         }
 
         Ok(())
     }
 
     #[test]
-    fn write() -> Result<(), Box<dyn std::error::Error>> {
+    fn relative_to_absolute() -> Result<(), Box<dyn std::error::Error>> {
         let mut parser = Parser::new(PathBuf::new());
-        let line = "909196 openat(AT_FDCWD, \"/a_path\", O_RDONLY|O_CREAT) = 5".to_string();
-        if let Parts::Finished(pid, _, args, ret) = parser.parts(&line)? {
+        let openat_line = "909196 openat(AT_FDCWD, \"/tmp\", O_RDONLY|O_DIRECTORY) = 3".to_string();
+        if let Parts::Finished(pid, _, args, ret) = parser.parts(&openat_line)? {
             let _operations = parser.openat(pid, args, ret)?;
         } else {
-            panic!("{}", format!("could not get the parts from {}", line));
+            panic!(
+                "{}",
+                format!("could not get the parts from {}", openat_line)
+            );
         }
 
-        // first write
-        let write_line1 = "909196 write(5, some content here, 17) = 17".to_string();
-        if let Parts::Finished(pid, _, args, _) = parser.parts(&write_line1)? {
-            let write_op1 = parser.write(pid, args)?;
+        // the relative path is joined to the directory path with a separator
+        assert_eq!(parser.relative_to_absolute(909196, "3", "foo")?, "/tmp/foo");
+        assert_eq!(
+            parser.relative_to_absolute(909196, "3", "./foo/../bar")?,
+            "/tmp/bar"
+        );
+        assert_eq!(parser.relative_to_absolute(909196, "3", "")?, "/tmp");
+
+        // an unknown dirfd falls back to the path decorated by strace
+        assert_eq!(
+            parser.relative_to_absolute(909196, "4</home/user>", "foo")?,
+            "/home/user/foo"
+        );
+        assert_eq!(
+            parser.relative_to_absolute(909196, "4", "bar")?,
+            "/home/user/bar"
+        );
+        // an unknown dirfd that is not decorated leaves the path relative
+        assert_eq!(parser.relative_to_absolute(909196, "7", "foo")?, "foo");
+        assert_eq!(
+            parser.relative_to_absolute(909196, "7", "./a/../../bar/")?,
+            "../bar"
+        );
+        assert_eq!(
+            parser.unresolved_paths()?,
+            vec![
+                UnresolvedPath::new(909196, 7, "foo".to_string()),
+                UnresolvedPath::new(909196, 7, "../bar".to_string())
+            ]
+        );
+
+        let openat_line =
+            "909196 openat(5</var/lib>, \"a_file\", O_RDONLY|O_CLOEXEC) = 6".to_string();
+        if let Parts::Finished(pid, _, args, ret) = parser.parts(&openat_line)? {
+            let operations = parser.openat(pid, args, ret)?;
             assert_eq!(
-                write_op1.op_type(),
-                &OperationType::Write(
-                    Arc::new(File::new("/a_path")),
-                    0,
-                    17,
-                    "some content here".to_string()
-                )
+                operations
+                    .first()
+                    .expect("failed to read the first entry of the vector")
+                    .op_type(),
+                &OperationType::OpenAt(Arc::new(File::new("/var/lib/a_file")), 0)
             );
         } else {
             panic!(
                 "{}",
-                format!("could not get the parts from {}", write_line1)
+                format!("could not get the parts from {}", openat_line)
             );
         }
 
-        // second write
-        let write_line2 = "909196 write(5, hello, 5) = 5".to_string();
-        if let Parts::Finished(pid, _, args, _) = parser.parts(&write_line2)? {
-            let write_op2 = parser.write(pid, args)?;
+        Ok(())
+    }
+
+    #[test]
+    fn decorated_fd() -> Result<(), Box<dyn std::error::Error>> {
+        let mut parser = Parser::new(PathBuf::new());
+
+        // the opened path is a symlink, so strace decorates the fd with the target path
+        let openat_line =
+            "909193 openat(AT_FDCWD, \"/lib/libc.so.6\", O_RDONLY|O_CLOEXEC) = 3</usr/lib/libc.so.6>"
+                .to_string();
+        if let Parts::Finished(pid, _, args, ret) = parser.parts(&openat_line)? {
+            let operations = parser.openat(pid, args, ret)?;
             assert_eq!(
-                write_op2.op_type(),
-                &OperationType::Write(Arc::new(File::new("/a_path")), 17, 5, "hello".to_string())
+                operations
+                    .first()
+                    .expect("failed to read the first entry of the vector")
+                    .op_type(),
+                &OperationType::OpenAt(Arc::new(File::new("/usr/lib/libc.so.6")), 0)
             );
         } else {
             panic!(
                 "{}",
-                format!("could not get the parts from {}", write_line2)
+                format!("could not get the parts from {}", openat_line)
             );
         }
 
-        // open the file one more time to check the offset
-        let line = "909196 openat(AT_FDCWD, \"/a_path\", O_RDONLY|O_CREAT) = 5".to_string();
-        if let Parts::Finished(pid, _, args, ret) = parser.parts(&line)? {
-            let _operations = parser.openat(pid, args, ret)?;
+        // the fd was opened before the trace started, so the decoration populates the fd_map
+        let read_line = "909193 read(5</tmp/input>, \"hello\", 5) = 5".to_string();
+        if let Parts::Finished(pid, _, args, _) = parser.parts(&read_line)? {
+            let read_op = parser.read(pid, args)?;
+            assert_eq!(
+                read_op.op_type(),
+                &OperationType::Read(Arc::new(File::new("/tmp/input")), 0, 5)
+            );
         } else {
-            panic!("{}", format!("could not get the parts from {}", line));
+            panic!("{}", format!("could not get the parts from {}", read_line));
         }
 
-        // now open the file with truncate flag, which should zero the size and offset
-        let line = "909196 openat(AT_FDCWD, \"/a_path\", O_RDONLY|O_TRUNC) = 5".to_string();
-        if let Parts::Finished(pid, _, args, ret) = parser.parts(&line)? {
-            let _operations = parser.openat(pid, args, ret)?;
+        // the decoration corrects a tracked path that went stale
+        parser
+            .fd_map
+            .insert((909193, 4), OpenedFile::new("/tmp/stale".to_string(), 0, 0));
+        let write_line = "909193 write(4</tmp/actual>, \"hello\", 5) = 5".to_string();
+        if let Parts::Finished(pid, _, args, _) = parser.parts(&write_line)? {
+            let write_op = parser.write(pid, args)?;
+            assert_eq!(
+                write_op
+                    .file()
+                    .expect("the write should access a file")
+                    .path()?,
+                "/tmp/actual"
+            );
         } else {
-            panic!("{}", format!("could not get the parts from {}", line));
+            panic!("{}", format!("could not get the parts from {}", write_line));
         }
+        // the stale path is kept as another path of the same file
+        assert_eq!(parser.canonical_path("/tmp/stale"), "/tmp/actual");
 
-        // write after truncate
-        let write_line2 = "909196 write(5, some other content here, 10) = 10".to_string();
-        if let Parts::Finished(pid, _, args, _) = parser.parts(&write_line2)? {
-            let write_op2 = parser.write(pid, args)?;
+        // a decorated dirfd is normalized, and corrects the tracked path the same way
+        parser
+            .fd_map
+            .insert((909193, 9), OpenedFile::new("/tmp/old".to_string(), 0, 0));
+        let unlink_line = "909193 unlinkat(9</tmp//dir/>, \"sub\", 0) = 0".to_string();
+        if let Parts::Finished(pid, _, args, _) = parser.parts(&unlink_line)? {
+            let unlink_op = parser.unlink(pid, args)?;
             assert_eq!(
-                write_op2.op_type(),
-                &OperationType::Write(
-                    Arc::new(File::new("/a_path")),
-                    0,
-                    10,
-                    "some other content here".to_string()
-                )
+                unlink_op.op_type(),
+                &OperationType::Remove(Arc::new(File::new("/tmp/dir/sub")))
             );
         } else {
             panic!(
                 "{}",
-                format!("could not get the parts from {}", write_line2)
+                format!("could not get the parts from {}", unlink_line)
+            );
+        }
+        assert_eq!(
+            parser.fd_map.get(&(909193, 9)).map(|f| f.path.as_str()),
+            Some("/tmp/dir")
+        );
+        assert_eq!(parser.canonical_path("/tmp/old"), "/tmp/dir");
+
+        // decorated pipes and sockets keep the names given by strace
+        let pipe_line = "909193 pipe2([6<pipe:[1234]>, 7<pipe:[1234]>], O_CLOEXEC) = 0".to_string();
+        if let Parts::Finished(pid, _, args, _) = parser.parts(&pipe_line)? {
+            let _operation = parser.pipe(pid, args)?;
+        } else {
+            panic!("{}", format!("could not get the parts from {}", pipe_line));
+        }
+        let socket_line =
+            "909193 socket(AF_INET, SOCK_STREAM, IPPROTO_TCP) = 8<TCP:[1.2.3.4:5->6.7.8.9:10]>"
+                .to_string();
+        if let Parts::Finished(pid, _, _, ret) = parser.parts(&socket_line)? {
+            let _operation = parser.socket(pid, ret)?;
+        } else {
+            panic!(
+                "{}",
+                format!("could not get the parts from {}", socket_line)
             );
         }
+        assert_eq!(
+            parser.fd_map.get(&(909193, 7)).map(|f| f.path.as_str()),
+            Some("pipe:[1234]")
+        );
+        assert_eq!(
+            parser.accessed_files.get("pipe:[1234]").map(|f| f.kind()),
+            Some(FileKind::Pipe)
+        );
+        assert_eq!(
+            parser
+                .accessed_files
+                .get("TCP:[1.2.3.4:5->6.7.8.9:10]")
+                .map(|f| f.kind()),
+            Some(FileKind::Socket)
+        );
+
+        assert_eq!(
+            parser.fd_discrepancies()?,
+            vec![
+                FdDiscrepancy::new(
+                    909193,
+                    3,
+                    "/lib/libc.so.6".to_string(),
+                    "/usr/lib/libc.so.6".to_string()
+                ),
+                FdDiscrepancy::new(
+                    909193,
+                    4,
+                    "/tmp/stale".to_string(),
+                    "/tmp/actual".to_string()
+                ),
+                FdDiscrepancy::new(909193, 9, "/tmp/old".to_string(), "/tmp/dir".to_string()),
+            ]
+        );
 
         Ok(())
     }
 
     #[test]
-    fn get_random() -> Result<(), Box<dyn std::error::Error>> {
+    fn canonical_identity() -> Result<(), Box<dyn std::error::Error>> {
         let mut parser = Parser::new(PathBuf::new());
-        let line = "909196 getrandom(a_buf, 16, GRND_NONBLOCK) = 16".to_string();
-        if let Parts::Finished(_, _, args, _) = parser.parts(&line)? {
-            let operation = parser.get_random(args)?;
-            assert_eq!(operation.op_type(), &OperationType::GetRandom(16));
-        } else {
-            panic!("{}", format!("could not get the parts from {}", line));
+        let lines = vec![
+            "909193 readlink(\"/lib\", \"usr/lib\", 4095) = 7",
+            "909193 readlinkat(AT_FDCWD, \"/usr/lib/libc.so\", \"libc.so.6\", 4095) = 9",
+            "909193 stat(\"/lib/libc.so\", {st_mode=S_IFREG|0755, st_size=2216304, ...}) = 0",
+            "909193 stat(\"/usr/lib/libc.so.6\", {st_mode=S_IFREG|0755, st_size=2216304, ...}) = 0",
+            // hard links to the same file share the device and inode numbers
+            "909193 stat(\"/tmp/a\", {st_dev=makedev(0x8, 0x1), st_ino=1234, st_mode=S_IFREG|0644, st_nlink=2, st_size=5, ...}) = 0",
+            "909193 stat(\"/tmp/b\", {st_dev=makedev(0x8, 0x1), st_ino=1234, st_mode=S_IFREG|0644, st_nlink=2, st_size=5, ...}) = 0",
+            "909193 stat(\"/tmp/c\", {st_dev=makedev(0x8, 0x1), st_ino=5678, st_mode=S_IFREG|0644, st_nlink=1, st_size=5, ...}) = 0",
+        ];
+
+        let mut process = Process::new(909193);
+        for line in lines {
+            match parser.parts(line)? {
+                Parts::Finished(pid, op, args, _) => match op.as_ref() {
+                    "readlink" => process.add_op(parser.readlink(pid, args)?),
+                    "readlinkat" => process.add_op(parser.readlinkat(pid, args)?),
+                    "stat" => process.add_op(parser.stat(pid, args)?),
+                    _ => panic!("unexpected operation {}", op),
+                },
+                _ => panic!("{}", format!("could not get the parts from {}", line)),
+            }
         }
 
+        let mut processes = vec![process];
+        parser.canonicalize(&mut processes)?;
+        let files: Vec<Arc<File>> = processes[0]
+            .ops()
+            .iter()
+            .filter_map(|op| op.file())
+            .collect();
+
+        // the symlinks are resolved, including the one in the path's ancestors
+        assert_eq!(files[0].path()?, "/lib/libc.so");
+        assert_eq!(files[0].canonical()?, "/usr/lib/libc.so.6");
+        assert_eq!(files[0], files[1]);
+
+        assert_eq!(files[2], files[3]);
+        assert_ne!(files[2], files[4]);
+
         Ok(())
     }
 
     #[test]
-    fn fstat() -> Result<(), Box<dyn std::error::Error>> {
+    fn versioned_files() -> Result<(), Box<dyn std::error::Error>> {
         let mut parser = Parser::new(PathBuf::new());
-        let openat_line = "909196 openat(AT_FDCWD, \"a_path\", O_RDONLY|O_CLOEXEC) = 3".to_string();
-        if let Parts::Finished(pid, _, args, ret) = parser.parts(&openat_line)? {
-            let _operation = parser.openat(pid, args, ret)?;
-        } else {
-            panic!(
-                "{}",
-                format!("could not get the parts from {}", openat_line)
+        let lines = vec![
+            "909193 openat(AT_FDCWD, \"/tmp/a\", O_WRONLY|O_CREAT, 0644) = 3",
+            "909193 write(3, \"x\", 1) = 1",
+            "909193 rename(\"/tmp/a\", \"/tmp/b\") = 0",
+            "909193 openat(AT_FDCWD, \"/tmp/b\", O_RDONLY) = 4",
+            "909193 read(4, \"x\", 1) = 1",
+            "909193 write(3, \"y\", 1) = 1",
+            "909193 unlinkat(AT_FDCWD, \"/tmp/b\", 0) = 0",
+            "909193 openat(AT_FDCWD, \"/tmp/b\", O_WRONLY|O_CREAT, 0644) = 5",
+            "909193 read(4</tmp/b (deleted)>, \"y\", 1) = 1",
+            "909193 openat(AT_FDCWD, \"/tmp/a\", O_WRONLY|O_CREAT, 0644) = 6",
+        ];
+
+        let mut files = vec![];
+        for line in lines {
+            let operation = match parser.parts(line)? {
+                Parts::Finished(pid, op, args, ret) => match op.as_ref() {
+                    "openat" => parser
+                        .openat(pid, args, ret)?
+                        .pop()
+                        .expect("openat should return an operation"),
+                    "write" => parser.write(pid, args)?,
+                    "read" => parser.read(pid, args)?,
+                    "rename" => parser.rename(pid, args)?,
+                    "unlinkat" => parser.unlink(pid, args)?,
+                    _ => panic!("unexpected operation {}", op),
+                },
+                _ => panic!("{}", format!("could not get the parts from {}", line)),
+            };
+            files.push(
+                operation
+                    .file()
+                    .expect("the operation should access a file"),
             );
         }
 
-        let fstat_line =
-            "909196 fstat(3, {st_mode=S_IFREG|0644, st_size=95921, ...}) = 0".to_string();
-        if let Parts::Finished(pid, _, args, _) = parser.parts(&fstat_line)? {
-            let fstat_op = parser.fstat(pid, args)?;
-            assert_eq!(
-                fstat_op.op_type(),
-                &OperationType::Fstat(Arc::new(File::new("/a_path")))
-            );
+        // the renamed file is the same file under its new path, and the file descriptors
+        // opened on its old path still refer to it
+        assert_eq!(files[3], files[0]);
+        assert_eq!(files[3].path()?, "/tmp/b");
+        assert_eq!(files[4], files[0]);
+        assert_eq!(files[5], files[0]);
 
-            assert_eq!(parser.existing_files.len(), 1);
-            let file_size = parser
-                .existing_files
-                .iter()
-                .next()
-                .expect("failed to get the first element from hashset")
-                .size();
-            assert_eq!(*file_size, 95921 as usize);
-        } else {
-            panic!("{}", format!("could not get the parts from {}", fstat_line));
+        // the file removed from /tmp/b is still readable through the file descriptor, but
+        // the file created at /tmp/b afterwards is another file
+        assert_eq!(files[6], files[0]);
+        assert_ne!(files[7], files[0]);
+        assert_eq!(files[8], files[0]);
+        assert_eq!(parser.fd_discrepancies()?, vec![]);
+
+        // the old path names a new file too
+        assert_ne!(files[9], files[0]);
+        assert_ne!(files[9], files[7]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn initial_files() -> Result<(), Box<dyn std::error::Error>> {
+        let mut parser = Parser::new(PathBuf::new());
+        let lines = vec![
+            "909193 stat(\"/data/input\", {st_mode=S_IFREG|0644, st_size=8192, ...}) = 0",
+            "909193 openat(AT_FDCWD, \"/data/input\", O_RDONLY) = 3",
+            "909193 read(3, \"...\", 4096) = 4096",
+            "909193 openat(AT_FDCWD, \"/data/log\", O_RDWR) = 4",
+            "909193 read(4, \"...\", 100) = 100",
+            "909193 write(4, \"...\", 50) = 50",
+            "909193 read(4, \"...\", 1000) = 1000",
+            "909193 openat(AT_FDCWD, \"/data/out\", O_WRONLY|O_CREAT|O_TRUNC, 0644) = 5",
+            "909193 write(5, \"...\", 10) = 10",
+            "909193 stat(\"/data/out\", {st_mode=S_IFREG|0644, st_size=10, ...}) = 0",
+            "909193 mkdir(\"/data/new\", 0755) = 0",
+            "909193 openat(AT_FDCWD, \"/etc\", O_RDONLY|O_DIRECTORY) = 6",
+        ];
+
+        for line in lines {
+            let operations = match parser.parts(line)? {
+                Parts::Finished(pid, op, args, ret) => match op.as_ref() {
+                    "openat" => parser.openat(pid, args, ret)?,
+                    "read" => vec![parser.read(pid, args)?],
+                    "write" => vec![parser.write(pid, args)?],
+                    "stat" => vec![parser.stat(pid, args)?],
+                    "mkdir" => vec![parser.mkdir(pid, args)?],
+                    _ => panic!("unexpected operation {}", op),
+                },
+                _ => panic!("{}", format!("could not get the parts from {}", line)),
+            };
+            for operation in operations {
+                parser.observe(&operation);
+            }
         }
 
+        // the files created during the trace are not in the snapshot, and the sizes observed
+        // after a file was modified are not its initial size
+        assert_eq!(
+            parser.snapshot()?.files(),
+            &vec![
+                FileType::Dir("/data".to_string(), 0),
+                FileType::File("/data/input".to_string(), 8192),
+                FileType::File("/data/log".to_string(), 100),
+                FileType::Dir("/etc".to_string(), 0),
+            ]
+        );
+
         Ok(())
     }
 
     #[test]
-    fn mkdir() -> Result<(), Box<dyn std::error::Error>> {
+    fn timing() -> Result<(), Box<dyn std::error::Error>> {
         let mut parser = Parser::new(PathBuf::new());
 
-        let mkdir_line = "909196 mkdir(\"a_path\", 0777) = 0".to_string();
-        if let Parts::Finished(_, _, args, _) = parser.parts(&mkdir_line)? {
-            let mkdir_op = parser.mkdir(args)?;
-            assert_eq!(
-                mkdir_op.op_type(),
-                &OperationType::Mkdir(Arc::new(File::new("a_path")), "0777".to_string())
-            );
-        } else {
-            panic!("{}", format!("could not get the parts from {}", mkdir_line));
-        }
+        let (line, (timestamp, duration)) = parser.timing(
+            "909194 12:34:56.123456 openat(AT_FDCWD, \"a-path\", O_RDONLY) = 3 <0.000021>",
+        )?;
+        assert_eq!(line, "909194 openat(AT_FDCWD, \"a-path\", O_RDONLY) = 3");
+        assert_eq!(
+            timestamp,
+            Some(Duration::new(12 * 3600 + 34 * 60 + 56, 123_456_000))
+        );
+        assert_eq!(duration, Some(Duration::from_micros(21)));
+
+        let (line, (timestamp, duration)) =
+            parser.timing("909194 1650000000.5 read(3</tmp/a>, \"\", 10) = 0")?;
+        assert_eq!(line, "909194 read(3</tmp/a>, \"\", 10) = 0");
+        assert_eq!(timestamp, Some(Duration::new(1650000000, 500_000_000)));
+        assert_eq!(duration, None);
+
+        let (line, (timestamp, duration)) = parser.timing("909194 getrandom( <unfinished ...>")?;
+        assert_eq!(line, "909194 getrandom( <unfinished ...>");
+        assert_eq!(timestamp, None);
+        assert_eq!(duration, None);
+
+        // the wall clock passes midnight
+        let mut parser = Parser::new(PathBuf::new());
+        parser.timing("909194 23:59:59 read(3, \"\", 10) = 0")?;
+        let (_, (timestamp, _)) = parser.timing("909194 00:00:01 read(3, \"\", 10) = 0")?;
+        assert_eq!(timestamp, Some(Duration::from_secs(24 * 3600 + 1)));
 
         Ok(())
     }
 
     #[test]
-    fn rename() -> Result<(), Box<dyn std::error::Error>> {
+    fn unresolved_identity() -> Result<(), Box<dyn std::error::Error>> {
         let mut parser = Parser::new(PathBuf::new());
-        let line = "909196 rename(\"old_path\", \"new_path\") = 0".to_string();
-        if let Parts::Finished(_, _, args, _) = parser.parts(&line)? {
-            let operation = parser.rename(args)?;
-            assert_eq!(
-                operation.op_type(),
-                &OperationType::Rename(Arc::new(File::new("old_path")), "new_path".to_string())
-            );
-        } else {
-            panic!("{}", format!("could not get the parts from {}", line));
+        let lines = vec![
+            "909193 stat(\"a_path\", {st_mode=S_IFREG|0644, st_size=5, ...}) = 0",
+            "909193 stat(\"/a_path\", {st_mode=S_IFREG|0644, st_size=5, ...}) = 0",
+        ];
+
+        let mut process = Process::new(909193);
+        for line in lines {
+            match parser.parts(line)? {
+                Parts::Finished(pid, _, args, _) => process.add_op(parser.stat(pid, args)?),
+                _ => panic!("{}", format!("could not get the parts from {}", line)),
+            }
         }
 
+        let mut processes = vec![process];
+        parser.canonicalize(&mut processes)?;
+        let files: Vec<Arc<File>> = processes[0]
+            .ops()
+            .iter()
+            .filter_map(|op| op.file())
+            .collect();
+
+        // the relative path is relative to an unknown directory, so it is another file
+        assert_eq!(files[0].canonical()?, "a_path");
+        assert_eq!(files[1].canonical()?, "/a_path");
+        assert_ne!(files[0], files[1]);
+
+        let dep_graph = DependencyGraph::new(processes)?;
+        assert_eq!(dep_graph.dag.nodes().len(), 3);
+
         Ok(())
     }
 
     #[test]
-    fn renameat() -> Result<(), Box<dyn std::error::Error>> {
-        let mut parser = Parser::new(PathBuf::new());
-        let line =
-            "909196 renameat2(AT_FDCWD, \"old_path\", AT_FDCWD, \"new_path\", RENAME_NOREPLACE) = 0"
-                .to_string();
-        if let Parts::Finished(pid, _, args, _) = parser.parts(&line)? {
-            let operation = parser.renameat(pid, args)?;
-            assert_eq!(
-                operation.op_type(),
-                &OperationType::Rename(Arc::new(File::new("old_path")), "new_path".to_string())
-            );
-        } else {
-            panic!("{}", format!("could not get the parts from {}", line));
+    fn renamed() -> Result<(), Box<dyn std::error::Error>> {
+        let log =
+            std::env::temp_dir().join(format!("strace-parser-renamed-{}", std::process::id()));
+        std::fs::write(
+            &log,
+            [
+                "909193 openat(AT_FDCWD, \"/tmp/a\", O_WRONLY|O_CREAT, 0644) = 3",
+                "909193 write(3, \"hello\", 5) = 5",
+                "909193 rename(\"/tmp/a\", \"/tmp/b\") = 0",
+                "909193 openat(AT_FDCWD, \"/tmp/b\", O_RDONLY) = 4",
+                "909193 read(4, \"hello\", 5) = 5",
+            ]
+            .join("\n"),
+        )?;
+
+        let mut parser = Parser::new(log.clone());
+        let mut dep_graph = parser.parse()?;
+        std::fs::remove_file(&log)?;
+
+        let mut files = vec![];
+        loop {
+            let processes = dep_graph.available_set()?;
+            if processes.is_empty() {
+                break;
+            }
+            for process in processes {
+                files.extend(process.ops().iter().filter_map(|op| op.file()));
+            }
+        }
+
+        // the renamed file keeps its identity, but each operation keeps the path it used
+        let paths = files
+            .iter()
+            .map(|file| file.path())
+            .collect::<Result<Vec<&str>, _>>()?;
+        assert_eq!(paths[paths.len() - 2..], ["/tmp/b", "/tmp/b"]);
+        assert!(files.iter().all(|file| file == &files[0]));
+
+        Ok(())
+    }
+
+    #[test]
+    fn create_existing() -> Result<(), Box<dyn std::error::Error>> {
+        let log = std::env::temp_dir().join(format!("strace-parser-create-{}", std::process::id()));
+        std::fs::write(
+            &log,
+            [
+                "909193 stat(\"/tmp/a\", {st_mode=S_IFREG|0644, st_size=5, ...}) = 0",
+                "909193 openat(AT_FDCWD, \"/tmp/a\", O_WRONLY|O_CREAT, 0644) = 3",
+                "909193 openat(AT_FDCWD, \"/tmp/b\", O_WRONLY|O_CREAT, 0644) = 4",
+                "909193 openat(AT_FDCWD, \"/tmp/b\", O_WRONLY|O_CREAT, 0644) = 5",
+                "909193 unlinkat(AT_FDCWD, \"/tmp/a\", 0) = 0",
+                "909193 openat(AT_FDCWD, \"/tmp/a\", O_WRONLY|O_CREAT, 0644) = 6",
+            ]
+            .join("\n"),
+        )?;
+
+        let mut parser = Parser::new(log.clone());
+        let mut dep_graph = parser.parse()?;
+        std::fs::remove_file(&log)?;
+
+        let mut created = vec![];
+        loop {
+            let processes = dep_graph.available_set()?;
+            if processes.is_empty() {
+                break;
+            }
+            for process in processes {
+                for op in process.ops() {
+                    if let OperationType::Mknod(file) = op.op_type() {
+                        created.push(file.path()?.to_string());
+                    }
+                }
+            }
+        }
+
+        // only the opens of the files that didn't exist create them
+        assert_eq!(created, ["/tmp/b", "/tmp/a"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn ancestor_without_clone() -> Result<(), Box<dyn std::error::Error>> {
+        let log =
+            std::env::temp_dir().join(format!("strace-parser-ancestor-{}", std::process::id()));
+        std::fs::write(
+            &log,
+            [
+                "909193 clone(child_stack=NULL, flags=SIGCHLD) = 909194",
+                "909194 openat(AT_FDCWD, \"/tmp/a\", O_WRONLY) = 3",
+                "909194 write(3, \"hello\", 5) = 5",
+                "909195 openat(AT_FDCWD, \"/tmp/b\", O_WRONLY) = 3",
+                "909195 write(3, \"hello\", 5) = 5",
+            ]
+            .join("\n"),
+        )?;
+
+        // the clone isn't kept, but the child is still a descendant of the parent
+        let mut parser = Parser::new(log.clone());
+        parser.set_syscalls(&["write"]);
+        parser.set_filter("ancestor = 909193".parse()?);
+        let mut dep_graph = parser.parse()?;
+        std::fs::remove_file(&log)?;
+
+        let mut pids = vec![];
+        loop {
+            let processes = dep_graph.available_set()?;
+            if processes.is_empty() {
+                break;
+            }
+            pids.extend(processes.iter().map(|process| process.pid()));
         }
+        assert_eq!(pids, [909194]);
 
         Ok(())
     }
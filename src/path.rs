@@ -0,0 +1,167 @@
+use crate::error::Error;
+
+///
+/// Remove the ., .., and duplicate slashes from a path, and make it absolute.
+/// The .. of the root directory is the root directory itself.
+///
+pub fn normalize(path: &str) -> String {
+    let mut components: Vec<&str> = vec![];
+    for component in path.split("/") {
+        match component {
+            "" | "." => {}
+            ".." => {
+                components.pop();
+            }
+            component => components.push(component),
+        }
+    }
+
+    format!("/{}", components.join("/"))
+}
+
+///
+/// Remove the ., .., and duplicate slashes from a path, and keep it relative if it is. The ..
+/// that go above the unknown directory the path is relative to are kept, and the empty path is
+/// the directory itself.
+///
+pub fn normalize_relative(path: &str) -> String {
+    if path.starts_with("/") {
+        return normalize(path);
+    }
+
+    let mut components: Vec<&str> = vec![];
+    for component in path.split("/") {
+        match component {
+            "" | "." => {}
+            ".." => match components.last() {
+                Some(&last) if last != ".." => {
+                    components.pop();
+                }
+                _ => components.push(".."),
+            },
+            component => components.push(component),
+        }
+    }
+
+    match components.is_empty() {
+        true => ".".to_string(),
+        false => components.join("/"),
+    }
+}
+
+///
+/// Join a relative path to a base directory and normalize the result.
+/// If the path is absolute, the base directory is ignored.
+///
+pub fn join(base: &str, path: &str) -> String {
+    if path.starts_with("/") {
+        normalize(path)
+    } else {
+        normalize(&format!("{}/{}", base, path))
+    }
+}
+
+///
+/// Whether the path is the prefix itself or under it. The prefix / is a prefix of all the
+/// absolute paths.
+///
+pub fn has_prefix(path: &str, prefix: &str) -> bool {
+    let prefix = prefix.trim_end_matches("/");
+    path == prefix
+        || path
+            .strip_prefix(prefix)
+            .map(|rest| rest.starts_with("/"))
+            .unwrap_or(false)
+}
+
+///
+/// Parse a file descriptor argument, which can be decorated with the path it refers to when
+/// strace runs with -y or --decode-fds, like 3</tmp/dir>.
+/// Return the file descriptor number and the decorated path, if there is any.
+///
+pub fn fd_annotation(arg: &str) -> Result<(i32, Option<&str>), Box<dyn std::error::Error>> {
+    let arg = arg.trim();
+    match arg.find("<") {
+        Some(start) => {
+            let fd = arg[..start].trim().parse::<i32>()?;
+            let path = arg[start + 1..]
+                .strip_suffix(">")
+                .ok_or(Error::ParseError(format!("file descriptor {}", arg)))?;
+            Ok((fd, Some(path)))
+        }
+        None => Ok((arg.parse::<i32>()?, None)),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::path::{fd_annotation, has_prefix, join, normalize, normalize_relative};
+
+    #[test]
+    fn prefix() {
+        assert!(has_prefix("/usr/lib/libc.so", "/usr/lib"));
+        assert!(has_prefix("/usr/lib", "/usr/lib/"));
+        assert!(has_prefix("/usr", "/"));
+        assert!(!has_prefix("/usr/lib64", "/usr/lib"));
+        assert!(!has_prefix("pipe:[1]", "/"));
+    }
+
+    #[test]
+    fn normalize_path() {
+        assert_eq!(normalize("/tmp/foo"), "/tmp/foo");
+        assert_eq!(normalize("/tmp/foo/"), "/tmp/foo");
+        assert_eq!(normalize("//tmp///foo"), "/tmp/foo");
+        assert_eq!(normalize("/tmp/./foo/."), "/tmp/foo");
+        assert_eq!(normalize("/tmp/bar/../foo"), "/tmp/foo");
+        assert_eq!(normalize("/tmp/bar/baz/../../foo"), "/tmp/foo");
+        assert_eq!(normalize("/.."), "/");
+        assert_eq!(normalize("/../tmp"), "/tmp");
+        assert_eq!(normalize("/"), "/");
+        assert_eq!(normalize(""), "/");
+        assert_eq!(normalize("foo/bar"), "/foo/bar");
+    }
+
+    #[test]
+    fn normalize_relative_path() {
+        assert_eq!(normalize_relative("foo/bar"), "foo/bar");
+        assert_eq!(normalize_relative("./foo//bar/"), "foo/bar");
+        assert_eq!(normalize_relative("foo/../bar"), "bar");
+        assert_eq!(normalize_relative("a/../../b"), "../b");
+        assert_eq!(normalize_relative("../../b/.."), "../..");
+        assert_eq!(normalize_relative("foo/.."), ".");
+        assert_eq!(normalize_relative(""), ".");
+        // an absolute path is normalized as usual
+        assert_eq!(normalize_relative("/tmp/../foo"), "/foo");
+    }
+
+    #[test]
+    fn join_path() {
+        assert_eq!(join("/tmp", "foo"), "/tmp/foo");
+        assert_eq!(join("/tmp/", "foo"), "/tmp/foo");
+        assert_eq!(join("/tmp", "./foo"), "/tmp/foo");
+        assert_eq!(join("/tmp/dir", "../foo"), "/tmp/foo");
+        assert_eq!(join("/tmp", "foo/"), "/tmp/foo");
+        assert_eq!(join("/tmp", ""), "/tmp");
+        assert_eq!(join("/tmp", "."), "/tmp");
+        assert_eq!(join("/", "foo"), "/foo");
+        assert_eq!(join("", "foo"), "/foo");
+        // an absolute path ignores the base directory
+        assert_eq!(join("/tmp", "/etc/passwd"), "/etc/passwd");
+        assert_eq!(join("/tmp", "//etc//passwd"), "/etc/passwd");
+    }
+
+    #[test]
+    fn annotated_fd() -> Result<(), Box<dyn std::error::Error>> {
+        assert_eq!(fd_annotation("3")?, (3, None));
+        assert_eq!(fd_annotation(" 3 ")?, (3, None));
+        assert_eq!(fd_annotation("3</tmp/dir>")?, (3, Some("/tmp/dir")));
+        assert_eq!(fd_annotation(" 12</tmp/a b>")?, (12, Some("/tmp/a b")));
+        assert_eq!(fd_annotation("5<pipe:[1234]>")?, (5, Some("pipe:[1234]")));
+        assert_eq!(fd_annotation("-1")?, (-1, None));
+
+        assert!(fd_annotation("AT_FDCWD").is_err());
+        assert!(fd_annotation("3</tmp/dir").is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,460 @@
+use crate::op::OperationType;
+use crate::process::Process;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Formatter;
+use std::time::Duration;
+
+// the size of the blocks the reuse distances are measured in
+const BLOCK_SIZE: usize = 4096;
+
+// the share of the accesses that have to follow the pattern for it to be detected
+const THRESHOLD: f64 = 0.8;
+
+///
+/// How a file is read or written
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Pattern {
+    Sequential,   // the accesses start where the previous ones ended
+    Strided(i64), // the accesses start at a fixed distance from the previous ones
+    Random,       // none of the above
+    Appended,     // the writes start at the end of what is written before
+    Overwritten,  // the writes overwrite what is written before
+}
+
+impl std::fmt::Display for Pattern {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Pattern::Sequential => write!(f, "sequential"),
+            Pattern::Strided(stride) => write!(f, "strided ({} bytes)", stride),
+            Pattern::Random => write!(f, "random"),
+            Pattern::Appended => write!(f, "appended"),
+            Pattern::Overwritten => write!(f, "overwritten"),
+        }
+    }
+}
+
+// a read or write of the len bytes at the offset of a file
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Access {
+    offset: usize,
+    len: usize,
+    write: bool,
+}
+
+///
+/// The access pattern of the reads and the writes of a file, and the reuse distances of its
+/// blocks, which are the numbers of distinct blocks of the file accessed between two accesses
+/// to the same block
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct FilePattern {
+    path: String,
+    reads: usize,
+    writes: usize,
+    read_pattern: Option<Pattern>, // the pattern of the reads, if the file is read
+    read_once: bool,               // whether no byte is read more than once
+    write_pattern: Option<Pattern>, // the pattern of the writes, if the file is written
+    stride: Option<i64>,           // the distance between the starts of the accesses, if fixed
+    reuse_distances: Vec<usize>,
+}
+
+impl FilePattern {
+    fn new(path: String, accesses: &[Access]) -> Self {
+        let reads: Vec<Access> = accesses.iter().filter(|a| !a.write).cloned().collect();
+        let writes: Vec<Access> = accesses.iter().filter(|a| a.write).cloned().collect();
+
+        let read_pattern = match reads.is_empty() {
+            true => None,
+            false => Some(order(&reads)),
+        };
+        let write_pattern = match writes.is_empty() {
+            true => None,
+            false if overlaps(&writes) => Some(Pattern::Overwritten),
+            false if appended(&writes) => Some(Pattern::Appended),
+            false => Some(order(&writes)),
+        };
+
+        FilePattern {
+            path,
+            reads: reads.len(),
+            writes: writes.len(),
+            read_pattern,
+            read_once: !reads.is_empty() && !overlaps(&reads),
+            write_pattern,
+            stride: stride(accesses),
+            reuse_distances: reuse_distances(accesses),
+        }
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    pub fn reads(&self) -> usize {
+        self.reads
+    }
+
+    pub fn writes(&self) -> usize {
+        self.writes
+    }
+
+    pub fn read_pattern(&self) -> Option<Pattern> {
+        self.read_pattern
+    }
+
+    pub fn is_read_once(&self) -> bool {
+        self.read_once
+    }
+
+    pub fn write_pattern(&self) -> Option<Pattern> {
+        self.write_pattern
+    }
+
+    pub fn stride(&self) -> Option<i64> {
+        self.stride
+    }
+
+    pub fn reuse_distances(&self) -> &Vec<usize> {
+        &self.reuse_distances
+    }
+}
+
+impl std::fmt::Display for FilePattern {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:", self.path)?;
+        if let Some(pattern) = self.read_pattern {
+            write!(f, " {} reads {},", self.reads, pattern)?;
+            if self.read_once {
+                write!(f, " read once,")?;
+            }
+        }
+        if let Some(pattern) = self.write_pattern {
+            write!(f, " {} writes {},", self.writes, pattern)?;
+        }
+
+        let mut distances = self.reuse_distances.clone();
+        distances.sort_unstable();
+        match (distances.first(), distances.last()) {
+            (Some(min), Some(max)) => writeln!(
+                f,
+                " {} block reuses at distances {} to {}, median {}",
+                distances.len(),
+                min,
+                max,
+                distances[distances.len() / 2]
+            ),
+            _ => writeln!(f, " no block reuse"),
+        }
+    }
+}
+
+///
+/// The access patterns of the regular files read or written by the processes. The accesses of
+/// different processes to a file are ordered by their timestamps if all of them have one, and
+/// by the order of the processes otherwise.
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct AccessPatterns {
+    files: Vec<FilePattern>,
+}
+
+impl AccessPatterns {
+    pub fn new(processes: &[Process]) -> Self {
+        let mut accesses: BTreeMap<String, Vec<(Option<Duration>, Access)>> = BTreeMap::new();
+        for process in processes {
+            for op in process.ops() {
+                // a copy reads its source, and writes its destination
+                let files = match op.op_type() {
+                    OperationType::Read(file, offset, len) => vec![(file, offset, len, false)],
+                    OperationType::Write(file, offset, len, _) => vec![(file, offset, len, true)],
+                    OperationType::Copy(from, from_offset, to, to_offset, len) => {
+                        vec![(from, from_offset, len, false), (to, to_offset, len, true)]
+                    }
+                    _ => continue,
+                };
+
+                for (file, offset, len, write) in files {
+                    let path = match file.path() {
+                        Ok(path) if file.is_regular() => path,
+                        _ => continue,
+                    };
+
+                    accesses.entry(path.to_string()).or_default().push((
+                        op.timestamp(),
+                        Access {
+                            offset: (*offset).max(0) as usize,
+                            len: *len,
+                            write,
+                        },
+                    ));
+                }
+            }
+        }
+
+        let files = accesses
+            .into_iter()
+            .map(|(path, mut accesses)| {
+                if accesses.iter().all(|(timestamp, _)| timestamp.is_some()) {
+                    accesses.sort_by_key(|(timestamp, _)| *timestamp);
+                }
+                let accesses: Vec<Access> = accesses.into_iter().map(|(_, a)| a).collect();
+                FilePattern::new(path, &accesses)
+            })
+            .collect();
+
+        AccessPatterns { files }
+    }
+
+    pub fn files(&self) -> &Vec<FilePattern> {
+        &self.files
+    }
+
+    pub fn file(&self, path: &str) -> Option<&FilePattern> {
+        self.files.iter().find(|file| file.path == path)
+    }
+}
+
+impl std::fmt::Display for AccessPatterns {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for file in self.files.iter() {
+            write!(f, "{}", file)?;
+        }
+
+        Ok(())
+    }
+}
+
+// whether any byte is accessed more than once
+fn overlaps(accesses: &[Access]) -> bool {
+    let mut ranges: Vec<(usize, usize)> = accesses
+        .iter()
+        .filter(|a| a.len > 0)
+        .map(|a| (a.offset, a.offset + a.len))
+        .collect();
+    ranges.sort_unstable();
+
+    let mut end = 0;
+    for (i, (start, stop)) in ranges.into_iter().enumerate() {
+        if i > 0 && start < end {
+            return true;
+        }
+        end = end.max(stop);
+    }
+    false
+}
+
+// whether each write starts at or after the end of the previous writes
+fn appended(writes: &[Access]) -> bool {
+    let mut end = 0;
+    for write in writes {
+        if write.offset < end {
+            return false;
+        }
+        end = write.offset + write.len;
+    }
+    true
+}
+
+// whether the accesses are sequential, strided, or random
+fn order(accesses: &[Access]) -> Pattern {
+    if accesses.len() < 2 {
+        return Pattern::Sequential;
+    }
+
+    let sequential = accesses
+        .windows(2)
+        .filter(|pair| pair[1].offset == pair[0].offset + pair[0].len)
+        .count();
+    if sequential as f64 >= THRESHOLD * (accesses.len() - 1) as f64 {
+        return Pattern::Sequential;
+    }
+
+    match stride(accesses) {
+        Some(stride) => Pattern::Strided(stride),
+        None => Pattern::Random,
+    }
+}
+
+// the distance between the starts of most of the consecutive accesses, if there are enough of
+// them and it is not just the length of the accesses
+fn stride(accesses: &[Access]) -> Option<i64> {
+    if accesses.len() < 3 {
+        return None;
+    }
+
+    let mut strides: BTreeMap<i64, usize> = BTreeMap::new();
+    for pair in accesses.windows(2) {
+        let stride = pair[1].offset as i64 - pair[0].offset as i64;
+        *strides.entry(stride).or_insert(0) += 1;
+    }
+
+    let (stride, count) = strides.into_iter().max_by_key(|(_, count)| *count)?;
+    let sequential = accesses.iter().all(|a| a.len as i64 == stride);
+    if stride == 0 || sequential || (count as f64) < THRESHOLD * (accesses.len() - 1) as f64 {
+        return None;
+    }
+    Some(stride)
+}
+
+// the number of distinct blocks accessed since the previous access to each reused block
+fn reuse_distances(accesses: &[Access]) -> Vec<usize> {
+    let blocks: Vec<usize> = accesses
+        .iter()
+        .filter(|a| a.len > 0)
+        .flat_map(|a| a.offset / BLOCK_SIZE..=(a.offset + a.len - 1) / BLOCK_SIZE)
+        .collect();
+
+    // the index of the last access to each block, and a Fenwick tree with a one at the index of
+    // the last access to each block, so the distinct blocks accessed since an index are counted
+    // in logarithmic time
+    let mut last_use: HashMap<usize, usize> = HashMap::new();
+    let mut tree = vec![0i64; blocks.len() + 1];
+    let update = |tree: &mut Vec<i64>, index: usize, delta: i64| {
+        let mut i = index + 1;
+        while i < tree.len() {
+            tree[i] += delta;
+            i += i & i.wrapping_neg();
+        }
+    };
+    let prefix = |tree: &Vec<i64>, index: usize| -> i64 {
+        let mut sum = 0;
+        let mut i = index;
+        while i > 0 {
+            sum += tree[i];
+            i -= i & i.wrapping_neg();
+        }
+        sum
+    };
+
+    let mut distances = vec![];
+    for (index, block) in blocks.into_iter().enumerate() {
+        if let Some(previous) = last_use.insert(block, index) {
+            distances.push((prefix(&tree, index) - prefix(&tree, previous + 1)) as usize);
+            update(&mut tree, previous, -1);
+        }
+        update(&mut tree, index, 1);
+    }
+
+    distances
+}
+
+#[cfg(test)]
+mod test {
+    use crate::file::File;
+    use crate::op::Operation;
+    use crate::pattern::{AccessPatterns, Pattern};
+    use crate::process::Process;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    #[test]
+    fn patterns() {
+        let once = Arc::new(File::new("/data/once"));
+        let reread = Arc::new(File::new("/data/reread"));
+        let strided = Arc::new(File::new("/data/strided"));
+        let random = Arc::new(File::new("/data/random"));
+        let log = Arc::new(File::new("/data/log"));
+        let db = Arc::new(File::new("/data/db"));
+
+        let mut process = Process::new(1);
+        for offset in [0, 4096, 8192] {
+            process.add_op(Operation::read(once.clone(), 4096, offset));
+        }
+        let nested = Arc::new(File::new("/data/nested"));
+        process.add_op(Operation::read(nested.clone(), 100, 0));
+        process.add_op(Operation::read(nested.clone(), 10, 10));
+        process.add_op(Operation::read(nested.clone(), 10, 50));
+        for offset in [0, 100, 200, 0, 100, 200] {
+            process.add_op(Operation::read(reread.clone(), 100, offset));
+        }
+        for offset in [0, 8192, 16384, 24576, 32768, 0] {
+            process.add_op(Operation::read(strided.clone(), 4096, offset));
+        }
+        for offset in [40960, 0, 12288, 0] {
+            process.add_op(Operation::read(random.clone(), 4096, offset));
+        }
+        for offset in [0, 10, 30] {
+            process.add_op(Operation::write(log.clone(), String::new(), 10, offset));
+        }
+        for offset in [0, 4096, 0] {
+            process.add_op(Operation::write(db.clone(), String::new(), 4096, offset));
+        }
+        process.add_op(Operation::stat(db.clone()));
+        // the copy source is read, and the destination written
+        let source = Arc::new(File::new("/data/source"));
+        let copied = Arc::new(File::new("/data/copied"));
+        for offset in [0, 4096] {
+            process.add_op(Operation::copy(
+                source.clone(),
+                offset,
+                copied.clone(),
+                offset,
+                4096,
+            ));
+        }
+
+        let patterns = AccessPatterns::new(&[process]);
+        let pattern = |path: &str| {
+            let file = patterns.file(path).unwrap();
+            (file.read_pattern(), file.write_pattern())
+        };
+
+        assert_eq!(pattern("/data/once"), (Some(Pattern::Sequential), None));
+        assert!(patterns.file("/data/once").unwrap().is_read_once());
+        assert_eq!(pattern("/data/source"), (Some(Pattern::Sequential), None));
+        assert!(!patterns.file("/data/reread").unwrap().is_read_once());
+        assert_eq!(patterns.file("/data/source").unwrap().reads(), 2);
+        assert_eq!(pattern("/data/copied"), (None, Some(Pattern::Appended)));
+        assert_eq!(pattern("/data/reread"), (Some(Pattern::Sequential), None));
+        assert_eq!(pattern("/data/strided").0, Some(Pattern::Strided(8192)));
+        assert_eq!(pattern("/data/random"), (Some(Pattern::Random), None));
+        assert_eq!(pattern("/data/log"), (None, Some(Pattern::Appended)));
+        assert_eq!(pattern("/data/db"), (None, Some(Pattern::Overwritten)));
+        assert_eq!(pattern("/data/nested").0, Some(Pattern::Random));
+        assert_eq!(patterns.files().len(), 9);
+    }
+
+    #[test]
+    fn reuse_distances() {
+        let file = Arc::new(File::new("/data/file"));
+
+        let mut process = Process::new(1);
+        // blocks 0, 1, 2, 0, 2, 1
+        for offset in [0, 4096, 8192, 0, 8192, 4096] {
+            process.add_op(Operation::read(file.clone(), 4096, offset));
+        }
+
+        let patterns = AccessPatterns::new(&[process]);
+        let file = patterns.file("/data/file").unwrap();
+
+        assert_eq!(file.reuse_distances(), &vec![2, 1, 2]);
+        assert_eq!(file.stride(), None);
+        assert_eq!(
+            file.to_string(),
+            "/data/file: 6 reads random, 3 block reuses at distances 1 to 2, median 2\n"
+        );
+    }
+
+    #[test]
+    fn ordered_by_timestamp() {
+        let file = Arc::new(File::new("/data/file"));
+
+        let mut p1 = Process::new(1);
+        p1.add_op(
+            Operation::write(file.clone(), String::new(), 10, 10)
+                .with_timing(Some(Duration::from_secs(2)), None),
+        );
+        let mut p2 = Process::new(2);
+        p2.add_op(
+            Operation::write(file.clone(), String::new(), 10, 0)
+                .with_timing(Some(Duration::from_secs(1)), None),
+        );
+
+        let patterns = AccessPatterns::new(&[p1, p2]);
+        assert_eq!(
+            patterns.file("/data/file").unwrap().write_pattern(),
+            Some(Pattern::Appended)
+        );
+    }
+}
@@ -0,0 +1,326 @@
+use crate::error::Error;
+use std::str::FromStr;
+
+// the size of the blocks the compressible and deduplicable payloads are made of
+const BLOCK_SIZE: usize = 4096;
+
+///
+/// Generate the bytes written by the replayed write operations. strace only shows a short
+/// preview of the written data, so the rest of it has to be made up.
+///
+pub trait PayloadGenerator: Send + Sync {
+    ///
+    /// The len bytes written at the offset of the file at the traced path. The captured bytes
+    /// are the unescaped preview of the written data shown by strace.
+    ///
+    fn generate(&self, path: &str, offset: usize, len: usize, captured: &[u8]) -> Vec<u8>;
+}
+
+///
+/// The payload generators available out of the box. The random, compressible, and deduplicable
+/// bytes depend only on the path, and the position in the file, so the same file gets the same
+/// content in every replay regardless of how the writes are split. The repeated and captured
+/// bytes also depend on the data strace captured for each write.
+///
+#[derive(Debug, Clone, PartialEq)]
+pub enum Payload {
+    Zeros,                  // all the bytes are zero
+    Repeat,                 // the captured bytes repeated to the written length, or zeros
+    Random(u64),            // pseudo-random bytes, with a seed
+    Compressible(f64, u64), // blocks with the input fraction of zeros, and pseudo-random bytes
+    Dedup(usize, u64),      // blocks picked from the input number of distinct blocks
+    Captured(Box<Payload>), // the captured bytes, and the rest from the filler payload
+}
+
+impl PayloadGenerator for Payload {
+    fn generate(&self, path: &str, offset: usize, len: usize, captured: &[u8]) -> Vec<u8> {
+        match self {
+            Payload::Zeros => vec![0; len],
+            Payload::Repeat => {
+                if captured.is_empty() {
+                    return vec![0; len];
+                }
+                captured.iter().cycle().take(len).cloned().collect()
+            }
+            Payload::Random(seed) => {
+                let key = key(*seed, path);
+                (offset..offset + len).map(|pos| byte(key, pos)).collect()
+            }
+            Payload::Compressible(ratio, seed) => {
+                let key = key(*seed, path);
+                let zeros = (ratio.clamp(0.0, 1.0) * BLOCK_SIZE as f64) as usize;
+                (offset..offset + len)
+                    .map(|pos| {
+                        if pos % BLOCK_SIZE < zeros {
+                            0
+                        } else {
+                            byte(key, pos)
+                        }
+                    })
+                    .collect()
+            }
+            Payload::Dedup(blocks, seed) => {
+                // the content of the distinct blocks does not depend on the path, so the same
+                // blocks show up in all the files
+                let file_key = key(*seed, path);
+                let block_key = key(*seed, "");
+                (offset..offset + len)
+                    .map(|pos| {
+                        let block = splitmix(file_key.wrapping_add((pos / BLOCK_SIZE) as u64))
+                            % (*blocks).max(1) as u64;
+                        byte(block_key, block as usize * BLOCK_SIZE + pos % BLOCK_SIZE)
+                    })
+                    .collect()
+            }
+            Payload::Captured(filler) => {
+                let prefix = captured.len().min(len);
+                let mut bytes = captured[..prefix].to_vec();
+                bytes.extend(filler.generate(path, offset + prefix, len - prefix, captured));
+                bytes
+            }
+        }
+    }
+}
+
+impl FromStr for Payload {
+    type Err = Error;
+
+    ///
+    /// Parse zeros, repeat, random[:SEED], compressible:RATIO[:SEED], dedup:BLOCKS[:SEED], or
+    /// captured[:FILLER] where the filler is one of the others, and zeros if not given
+    ///
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = || {
+            Error::ParseError(format!(
+                "payload {}, expected zeros, repeat, random[:SEED], compressible:RATIO[:SEED], \
+                 dedup:BLOCKS[:SEED], or captured[:FILLER]",
+                s
+            ))
+        };
+
+        let (name, args) = s.split_once(":").unwrap_or((s, ""));
+        let mut args = args.split(":").filter(|arg| !arg.is_empty());
+        let seed = |args: &mut dyn Iterator<Item = &str>| -> Result<u64, Error> {
+            args.next()
+                .map(|seed| seed.parse::<u64>().map_err(|_| error()))
+                .unwrap_or(Ok(0))
+        };
+
+        let payload = match name {
+            "zeros" => Payload::Zeros,
+            "repeat" => Payload::Repeat,
+            "random" => Payload::Random(seed(&mut args)?),
+            "compressible" => {
+                let ratio = args
+                    .next()
+                    .and_then(|ratio| ratio.parse::<f64>().ok())
+                    .filter(|ratio| (0.0..=1.0).contains(ratio))
+                    .ok_or_else(error)?;
+                Payload::Compressible(ratio, seed(&mut args)?)
+            }
+            "dedup" => {
+                let blocks = args
+                    .next()
+                    .and_then(|blocks| blocks.parse::<usize>().ok())
+                    .filter(|blocks| *blocks > 0)
+                    .ok_or_else(error)?;
+                Payload::Dedup(blocks, seed(&mut args)?)
+            }
+            "captured" => {
+                let filler = s.split_once(":").map(|(_, filler)| filler);
+                return match filler {
+                    Some(filler) => Ok(Payload::Captured(Box::new(filler.parse()?))),
+                    None => Ok(Payload::Captured(Box::new(Payload::Zeros))),
+                };
+            }
+            _ => return Err(error()),
+        };
+
+        match args.next() {
+            Some(_) => Err(error()),
+            None => Ok(payload),
+        }
+    }
+}
+
+///
+/// The bytes of the quoted, and possibly shortened, string strace shows for the written data,
+/// with the escape sequences replaced by the bytes they stand for
+///
+pub fn captured(content: &str) -> Vec<u8> {
+    let content = content.trim_end_matches("...");
+    let content = content
+        .strip_prefix('"')
+        .and_then(|content| content.strip_suffix('"'))
+        .unwrap_or(content)
+        .as_bytes();
+
+    let mut bytes = Vec::with_capacity(content.len());
+    let mut i = 0;
+    while i < content.len() {
+        if content[i] != b'\\' || i + 1 == content.len() {
+            bytes.push(content[i]);
+            i += 1;
+            continue;
+        }
+
+        i += 1;
+        match content[i] {
+            b'n' => bytes.push(b'\n'),
+            b't' => bytes.push(b'\t'),
+            b'r' => bytes.push(b'\r'),
+            b'v' => bytes.push(0x0b),
+            b'f' => bytes.push(0x0c),
+            b'x' => {
+                // a hexadecimal escape, as printed with strace -x
+                let digits = content[i + 1..]
+                    .iter()
+                    .take(2)
+                    .take_while(|c| c.is_ascii_hexdigit())
+                    .count();
+                let value = std::str::from_utf8(&content[i + 1..i + 1 + digits])
+                    .ok()
+                    .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+                match value {
+                    Some(value) => {
+                        bytes.push(value);
+                        i += digits;
+                    }
+                    None => bytes.push(b'x'),
+                }
+            }
+            b'0'..=b'7' => {
+                // an octal escape of up to three digits
+                let digits = content[i..]
+                    .iter()
+                    .take(3)
+                    .take_while(|c| (b'0'..=b'7').contains(*c))
+                    .count();
+                let value = content[i..i + digits]
+                    .iter()
+                    .fold(0u32, |value, c| value * 8 + (c - b'0') as u32);
+                bytes.push(value as u8);
+                i += digits - 1;
+            }
+            c => bytes.push(c),
+        }
+        i += 1;
+    }
+
+    bytes
+}
+
+// the pseudo-random byte at the position of the stream with the key
+fn byte(key: u64, pos: usize) -> u8 {
+    splitmix(key.wrapping_add((pos / 8) as u64)).to_le_bytes()[pos % 8]
+}
+
+// the key of the pseudo-random stream of a file, from the seed and the path
+fn key(seed: u64, path: &str) -> u64 {
+    // FNV-1a, which is stable across platforms and versions, unlike the std hasher
+    let hash = path.bytes().fold(0xcbf29ce484222325u64, |hash, c| {
+        (hash ^ c as u64).wrapping_mul(0x100000001b3)
+    });
+    splitmix(seed ^ hash)
+}
+
+fn splitmix(x: u64) -> u64 {
+    let mut z = x.wrapping_add(0x9e3779b97f4a7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::payload::{captured, Payload, PayloadGenerator};
+    use std::collections::HashSet;
+
+    #[test]
+    fn captured_bytes() {
+        assert_eq!(captured("\"ab\\n\\t\\\"\\\\\"..."), b"ab\n\t\"\\".to_vec());
+        assert_eq!(captured("\"\\0\\377\\1a\""), vec![0, 255, 1, b'a']);
+        assert_eq!(captured("\"\\x41\\x0a\""), b"A\n".to_vec());
+        assert_eq!(captured(""), Vec::<u8>::new());
+    }
+
+    #[test]
+    fn payloads() {
+        assert_eq!(Payload::Zeros.generate("/a", 0, 3, b"ab"), vec![0; 3]);
+        assert_eq!(
+            Payload::Repeat.generate("/a", 0, 5, b"ab"),
+            b"ababa".to_vec()
+        );
+        assert_eq!(Payload::Repeat.generate("/a", 0, 2, b""), vec![0; 2]);
+        assert_eq!(
+            Payload::Captured(Box::new(Payload::Zeros)).generate("/a", 0, 4, b"ab"),
+            b"ab\0\0".to_vec()
+        );
+        assert_eq!(
+            Payload::Captured(Box::new(Payload::Zeros)).generate("/a", 0, 1, b"ab"),
+            b"a".to_vec()
+        );
+    }
+
+    #[test]
+    fn deterministic() {
+        let random = Payload::Random(7);
+
+        // the content depends on the position in the file, not on how the writes are split
+        let whole = random.generate("/data/a", 0, 100, b"");
+        let mut split = random.generate("/data/a", 0, 37, b"");
+        split.extend(random.generate("/data/a", 37, 63, b""));
+        assert_eq!(whole, split);
+
+        assert_ne!(whole, random.generate("/data/b", 0, 100, b""));
+        assert_ne!(whole, Payload::Random(8).generate("/data/a", 0, 100, b""));
+    }
+
+    #[test]
+    fn compressible() {
+        let bytes = Payload::Compressible(0.75, 0).generate("/a", 0, 4 * 4096, b"");
+        let zeros = bytes.iter().filter(|b| **b == 0).count();
+
+        // the pseudo-random bytes may be zero too
+        assert!((3 * 4096..3 * 4096 + 4096 / 64).contains(&zeros));
+    }
+
+    #[test]
+    fn dedup() {
+        let dedup = Payload::Dedup(2, 0);
+        let mut blocks = HashSet::new();
+        for path in ["/a", "/b", "/c"] {
+            let bytes = dedup.generate(path, 0, 16 * 4096, b"");
+            blocks.extend(bytes.chunks(4096).map(|block| block.to_vec()));
+        }
+
+        assert_eq!(blocks.len(), 2);
+    }
+
+    #[test]
+    fn parse() -> Result<(), Box<dyn std::error::Error>> {
+        assert_eq!("zeros".parse::<Payload>()?, Payload::Zeros);
+        assert_eq!("random".parse::<Payload>()?, Payload::Random(0));
+        assert_eq!("random:42".parse::<Payload>()?, Payload::Random(42));
+        assert_eq!(
+            "compressible:0.5".parse::<Payload>()?,
+            Payload::Compressible(0.5, 0)
+        );
+        assert_eq!("dedup:8:3".parse::<Payload>()?, Payload::Dedup(8, 3));
+        assert_eq!(
+            "captured".parse::<Payload>()?,
+            Payload::Captured(Box::new(Payload::Zeros))
+        );
+        assert_eq!(
+            "captured:random:1".parse::<Payload>()?,
+            Payload::Captured(Box::new(Payload::Random(1)))
+        );
+        assert!("compressible".parse::<Payload>().is_err());
+        assert!("compressible:2".parse::<Payload>().is_err());
+        assert!("dedup:0".parse::<Payload>().is_err());
+        assert!("random:1:2".parse::<Payload>().is_err());
+        assert!("ones".parse::<Payload>().is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,515 @@
+use crate::binary::{BinaryTrace, MAGIC};
+use crate::deps::DependencyGraph;
+use crate::error::Error;
+use crate::file::{File, FileKind};
+use crate::filter::Filter;
+use crate::op::{Operation, OperationType};
+use crate::parser::{FileType, Parser};
+use crate::path::has_prefix;
+use crate::process::Process;
+use crate::snapshot::Snapshot;
+use regex::Regex;
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+use std::io::BufRead;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+///
+/// A reader of the ordered operations strace-parser writes, in the text, jsonl, or binary format,
+/// to rebuild the processes and their dependency graph without the strace logs. The text format
+/// does not keep the timing of the operations, and neither it nor the jsonl format keeps which
+/// paths name the same file, so their files are identified by their paths, followed across
+/// renames. The binary format keeps everything the parser found, the snapshot included.
+///
+pub struct Reader {
+    log_file: PathBuf,
+    files: HashMap<String, Arc<File>>, // the files accessed so far, by the path naming them
+    generations: HashMap<String, usize>, // the number of times a path stopped naming a file, after removing or renaming it
+    paths: HashSet<String>,              // all the paths accessed, and the working directories
+    filter: Option<Filter>, // the operations kept in the dependency graph, or all if none
+    snapshot: Option<Snapshot>, // the snapshot a binary trace keeps
+    tracker: Parser, // keeps the files that existed before the trace, from the operations read
+}
+
+// an operation in the jsonl format, with the keys the operation is rebuilt from
+#[derive(Deserialize)]
+struct Row {
+    pid: usize,
+    operation: String,
+    timestamp: Option<f64>,
+    duration: Option<f64>,
+}
+
+impl Reader {
+    pub fn new(log_file: PathBuf) -> Self {
+        Reader {
+            log_file,
+            files: HashMap::new(),
+            generations: HashMap::new(),
+            paths: HashSet::new(),
+            filter: None,
+            snapshot: None,
+            tracker: Parser::new(PathBuf::new()),
+        }
+    }
+
+    ///
+    /// Keep only the operations the filter selects in the dependency graph
+    ///
+    pub fn set_filter(&mut self, filter: Filter) {
+        self.filter = Some(filter);
+    }
+
+    pub fn read(&mut self) -> Result<DependencyGraph, Box<dyn std::error::Error>> {
+        let bytes = std::fs::read(&self.log_file)?;
+        let mut processes = match bytes.starts_with(MAGIC) {
+            true => {
+                let trace = BinaryTrace::parse(&bytes)?;
+                self.snapshot = Some(trace.snapshot());
+                trace.processes()?
+            }
+            false => self.processes(bytes.as_slice())?,
+        };
+
+        if let Some(filter) = &self.filter {
+            processes = filter.apply(processes);
+        }
+
+        let dep_graph = DependencyGraph::new(processes)?.order()?;
+
+        Ok(dep_graph)
+    }
+
+    ///
+    /// The processes of the written operations, in the order they are first seen, with their
+    /// operations in order. Each line is either a process id and an operation, as in the text
+    /// format, or a JSON object, as in the jsonl format.
+    ///
+    pub fn processes<R: BufRead>(
+        &mut self,
+        reader: R,
+    ) -> Result<Vec<Process>, Box<dyn std::error::Error>> {
+        let mut processes: Vec<Process> = vec![];
+
+        for line in reader.lines() {
+            let line = line?;
+            let line = line.trim_end_matches('\r');
+            if line.is_empty() {
+                continue;
+            }
+
+            let (pid, op) = match line.starts_with("{") {
+                true => {
+                    let row: Row = serde_json::from_str(line)
+                        .map_err(|e| Error::ParseError(format!("{}: {}", line, e)))?;
+                    // the timing is negative, not a number, or too large in a corrupted row
+                    let seconds = |seconds: Option<f64>| {
+                        seconds
+                            .map(Duration::try_from_secs_f64)
+                            .transpose()
+                            .map_err(|e| Error::ParseError(format!("{}: {}", line, e)))
+                    };
+                    let op = self
+                        .operation(&row.operation)?
+                        .with_timing(seconds(row.timestamp)?, seconds(row.duration)?);
+                    (row.pid, op)
+                }
+                false => {
+                    let (pid, op) = line
+                        .split_once(" ")
+                        .ok_or(Error::ParseError(line.to_string()))?;
+                    let pid = pid
+                        .parse()
+                        .map_err(|_| Error::ParseError(line.to_string()))?;
+                    (pid, self.operation(op)?)
+                }
+            };
+
+            self.tracker.observe(&op);
+            self.follow(&op)?;
+
+            match processes.iter_mut().find(|p| p.pid() == pid) {
+                Some(process) => process.add_op(op),
+                None => {
+                    let mut process = Process::new(pid);
+                    process.add_op(op);
+                    processes.push(process);
+                }
+            }
+        }
+
+        Ok(processes)
+    }
+
+    ///
+    /// The files and directories that existed before the trace started. Unless the trace is
+    /// binary, they are only known as far as the operations read show them: the sizes strace
+    /// showed are lost, and the paths are only known to be directories if the root, or if other
+    /// paths or working directories are under them.
+    ///
+    pub fn snapshot(&self) -> Result<Snapshot, Box<dyn std::error::Error>> {
+        if let Some(snapshot) = &self.snapshot {
+            return Ok(snapshot.clone());
+        }
+
+        let is_dir = |path: &str| {
+            path == "/"
+                || self
+                    .paths
+                    .iter()
+                    .any(|other| other.len() > path.len() && has_prefix(other, path))
+        };
+
+        Ok(Snapshot::new(
+            self.tracker
+                .snapshot()?
+                .files()
+                .iter()
+                .map(|file_type| match file_type {
+                    FileType::File(path, _) if is_dir(path) => FileType::Dir(path.clone(), 0),
+                    _ => file_type.clone(),
+                })
+                .collect(),
+        ))
+    }
+
+    // parse an operation as its Display writes it, like read(file(/data/a), 0, 5)
+    fn operation(&mut self, text: &str) -> Result<Operation, Box<dyn std::error::Error>> {
+        if text == "no-op" {
+            return Ok(Operation::no_op());
+        }
+
+        let (name, args) = text
+            .strip_suffix(")")
+            .and_then(|text| text.split_once("("))
+            .ok_or(Error::ParseError(format!("operation {}", text)))?;
+
+        // the path of a file is the rest of the arguments, so it may have commas and parentheses
+        const FILE: &str = r"^file\((.*)\)$";
+        let number = |arg: &str| -> Result<usize, Error> {
+            arg.parse()
+                .map_err(|_| Error::ParseError(format!("operation {}", text)))
+        };
+        let offset = |arg: &str| -> Result<i32, Error> {
+            arg.parse()
+                .map_err(|_| Error::ParseError(format!("operation {}", text)))
+        };
+
+        let op = match name {
+            "mkdir" => {
+                let a = captures(r"^file\((.*)\), ([^,]*)$", text, args)?;
+                Operation::mkdir(self.file(a[0]), a[1].to_string())
+            }
+            "mknod" => Operation::mknod(self.file(captures(FILE, text, args)?[0])),
+            "remove" => Operation::remove(self.file(captures(FILE, text, args)?[0])),
+            "read" => {
+                let a = captures(r"^file\((.*)\), (-?\d+), (\d+)$", text, args)?;
+                Operation::read(self.file(a[0]), number(a[2])?, offset(a[1])?)
+            }
+            "write" => {
+                // the content is the rest of the arguments instead
+                let a = captures(r"^file\((.*?)\), (-?\d+), (\d+), (.*)$", text, args)?;
+                Operation::write(
+                    self.file(a[0]),
+                    a[3].to_string(),
+                    number(a[2])?,
+                    offset(a[1])?,
+                )
+            }
+            "open" => {
+                let a = captures(r"^file\((.*)\), (-?\d+)$", text, args)?;
+                Operation::open_at(self.file(a[0]), offset(a[1])?)
+            }
+            "truncate" => Operation::truncate(self.file(captures(FILE, text, args)?[0])),
+            "get_random" => Operation::get_random(number(args)?),
+            "stat" => Operation::stat(self.file(captures(FILE, text, args)?[0])),
+            "fstat" => Operation::fstat(self.file(captures(FILE, text, args)?[0])),
+            "statx" => Operation::statx(self.file(captures(FILE, text, args)?[0])),
+            "statfs" => Operation::statfs(self.file(captures(FILE, text, args)?[0])),
+            "fstatat" => Operation::fstatat(self.file(captures(FILE, text, args)?[0])),
+            "rename" => {
+                let a = captures(r"^file\((.*?)\) (.*)$", text, args)?;
+                Operation::rename(self.file(a[0]), a[1].to_string())
+            }
+            "clone" => Operation::clone_op(number(args)?),
+            "chdir" => {
+                self.paths.insert(args.to_string());
+                Operation::chdir(args.to_string())
+            }
+            "map" => {
+                let a = captures(
+                    r"^file\((.*)\), (-?\d+), (\d+), ([^,]*), ([^,]*)$",
+                    text,
+                    args,
+                )?;
+                Operation::map(
+                    self.file(a[0]),
+                    offset(a[1])?,
+                    number(a[2])?,
+                    a[3].to_string(),
+                    a[4].to_string(),
+                )
+            }
+            "sync" => Operation::sync(self.file(captures(FILE, text, args)?[0])),
+            "copy" => {
+                let a = captures(
+                    r"^file\((.*?)\), (-?\d+), file\((.*)\), (-?\d+), (\d+)$",
+                    text,
+                    args,
+                )?;
+                Operation::copy(
+                    self.file(a[0]),
+                    offset(a[1])?,
+                    self.file(a[2]),
+                    offset(a[3])?,
+                    number(a[4])?,
+                )
+            }
+            _ => return Err(Box::new(Error::ParseError(format!("operation {}", text)))),
+        };
+
+        Ok(op)
+    }
+
+    // the removed and renamed paths stop naming their files, and the renamed files keep their
+    // identity under their new paths, like the parser tracks them
+    fn follow(&mut self, op: &Operation) -> Result<(), Box<dyn std::error::Error>> {
+        match op.op_type() {
+            OperationType::Remove(file) => {
+                let path = file.path()?.to_string();
+                self.files.remove(&path);
+                *self.generations.entry(path).or_insert(0) += 1;
+            }
+            OperationType::Rename(file, to) => {
+                self.paths.insert(to.clone());
+                let from = file.path()?.to_string();
+                if from == *to {
+                    return Ok(());
+                }
+
+                let replaced: Vec<String> = self
+                    .files
+                    .keys()
+                    .filter(|path| has_prefix(path, to))
+                    .cloned()
+                    .collect();
+                for path in replaced {
+                    self.files.remove(&path);
+                    *self.generations.entry(path).or_insert(0) += 1;
+                }
+
+                let moved: Vec<String> = self
+                    .files
+                    .keys()
+                    .filter(|path| has_prefix(path, &from))
+                    .cloned()
+                    .collect();
+                for path in moved {
+                    if let Some(file) = self.files.remove(&path) {
+                        let new_path = format!("{}{}", to, &path[from.len()..]);
+                        self.files
+                            .insert(new_path.clone(), Arc::new(file.with_path(&new_path)));
+                    }
+                    *self.generations.entry(path).or_insert(0) += 1;
+                }
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    fn file(&mut self, path: &str) -> Arc<File> {
+        self.paths.insert(path.to_string());
+        match self.files.get(path) {
+            Some(file) => file.clone(),
+            None => {
+                let generation = *self.generations.get(path).unwrap_or(&0);
+                let file = Arc::new(
+                    File::with_kind(path, FileKind::from_path(path)).with_generation(generation),
+                );
+                self.files.insert(path.to_string(), file.clone());
+                file
+            }
+        }
+    }
+}
+
+// the groups the pattern captures in the arguments of an operation
+fn captures<'a>(pattern: &str, text: &str, args: &'a str) -> Result<Vec<&'a str>, Error> {
+    let re = Regex::new(pattern).map_err(|e| Error::ParseError(e.to_string()))?;
+    let captures = re
+        .captures(args)
+        .ok_or(Error::ParseError(format!("operation {}", text)))?;
+
+    Ok(captures
+        .iter()
+        .skip(1)
+        .map(|group| group.map(|group| group.as_str()).unwrap_or(""))
+        .collect())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::deps::DependencyGraph;
+    use crate::file::{File, FileKind};
+    use crate::format::Format;
+    use crate::op::{Operation, OperationType};
+    use crate::parser::FileType;
+    use crate::process::Process;
+    use crate::reader::Reader;
+    use std::path::PathBuf;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    fn processes() -> Vec<Process> {
+        let input = Arc::new(File::new("/data/in, (1)"));
+        let output = Arc::new(File::new("/data/out"));
+        let pipe = Arc::new(File::with_kind("pipe:[7]", FileKind::Pipe));
+
+        let mut parent = Process::new(10);
+        parent.add_op(Operation::chdir("/data".to_string()));
+        parent.add_op(Operation::open_at(input.clone(), 0));
+        parent.add_op(Operation::read(input.clone(), 128, 0));
+        parent.add_op(
+            Operation::write(pipe.clone(), "a, b)".to_string(), 5, 0)
+                .with_timing(Some(Duration::from_micros(1_500_250)), None),
+        );
+        parent.add_op(Operation::map(
+            input.clone(),
+            0,
+            4096,
+            "PROT_READ".to_string(),
+            "MAP_PRIVATE".to_string(),
+        ));
+        parent.add_op(Operation::clone_op(11));
+        parent.add_op(Operation::get_random(16));
+        parent.add_op(Operation::no_op());
+
+        let mut child = Process::new(11);
+        child.add_op(Operation::read(pipe.clone(), 5, 0));
+        child.add_op(Operation::mkdir(
+            Arc::new(File::new("/data/tmp")),
+            "0755".to_string(),
+        ));
+        child.add_op(Operation::mknod(Arc::new(File::new("/data/tmp/out"))));
+        child.add_op(Operation::write(
+            Arc::new(File::new("/data/tmp/out")),
+            String::new(),
+            3,
+            -1,
+        ));
+        child.add_op(Operation::copy(input.clone(), 0, output.clone(), 0, 64));
+        child.add_op(Operation::rename(
+            Arc::new(File::new("/data/tmp/out")),
+            "/data/out".to_string(),
+        ));
+        child.add_op(Operation::stat(output.clone()));
+        child.add_op(Operation::sync(output.clone()));
+        child.add_op(Operation::remove(output.clone()));
+        child.add_op(Operation::truncate(output.clone()));
+
+        vec![parent, child]
+    }
+
+    #[test]
+    fn text() -> Result<(), Box<dyn std::error::Error>> {
+        let text: String = processes().iter().map(|p| p.to_string()).collect();
+
+        let mut reader = Reader::new(PathBuf::new());
+        let read = reader.processes(text.as_bytes())?;
+        let read_text: String = read.iter().map(|p| p.to_string()).collect();
+        assert_eq!(read_text, text);
+
+        let ops = read[1].ops();
+        assert_eq!(ops[0].file().map(|file| file.kind()), Some(FileKind::Pipe));
+        match (ops[5].op_type(), ops[6].op_type(), ops[9].op_type()) {
+            (
+                OperationType::Rename(renamed, _),
+                OperationType::Stat(stated),
+                OperationType::Truncate(truncated),
+            ) => {
+                // the renamed file is the one stated at the path it replaced, and another one once
+                // removed
+                assert_eq!(renamed, stated);
+                assert_eq!(stated.path()?, "/data/out");
+                assert_eq!(stated.generation(), 0);
+                assert_ne!(stated, truncated);
+                assert_eq!(truncated.generation(), 2);
+            }
+            _ => panic!("expected a rename, a stat, and a truncate"),
+        }
+
+        // the input was read before, while the directory the child created was not there
+        let snapshot = reader.snapshot()?;
+        assert!(snapshot
+            .files()
+            .contains(&FileType::File("/data/in, (1)".to_string(), 128)));
+        assert!(!snapshot
+            .files()
+            .iter()
+            .any(|file| file.path().starts_with("/data/tmp")));
+        assert!(snapshot
+            .files()
+            .contains(&FileType::Dir("/data".to_string(), 0)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn jsonl() -> Result<(), Box<dyn std::error::Error>> {
+        let mut dep_graph = DependencyGraph::new(processes())?.order()?;
+        let jsonl = Format::Jsonl.ops(&mut dep_graph)?;
+
+        let read = Reader::new(PathBuf::new()).processes(jsonl.as_bytes())?;
+        assert_eq!(read.len(), 2);
+        assert_eq!(read[0].ops().len(), 8);
+        assert_eq!(
+            read[0].ops()[3].timestamp(),
+            Some(Duration::from_micros(1_500_250))
+        );
+        assert_eq!(
+            read[0].ops()[3].to_string(),
+            "write(file(pipe:[7]), 0, 5, a, b))"
+        );
+
+        // the graph read back has the same dependencies
+        let dep_graph = DependencyGraph::new(read)?.order()?;
+        let expected = DependencyGraph::new(processes())?.order()?;
+        assert_eq!(
+            Format::Text.graph(&dep_graph)?,
+            Format::Text.graph(&expected)?
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn errors() {
+        let mut reader = Reader::new(PathBuf::new());
+        assert!(reader
+            .processes("10 read(file(/a), 0)\n".as_bytes())
+            .is_err());
+        assert!(reader.processes("10 open(/a, 0)\n".as_bytes()).is_err());
+        assert!(reader.processes("ten no-op\n".as_bytes()).is_err());
+        assert!(reader.processes("10 fork(11)\n".as_bytes()).is_err());
+        assert!(reader
+            .processes("{\"pid\":10,\"op\":\"NoOp\"}\n".as_bytes())
+            .is_err());
+        assert!(reader.processes("10 no-op\n\n".as_bytes()).is_ok());
+
+        // the timing has to be a duration
+        let row = |timing: &str| format!("{{\"pid\":10,\"operation\":\"no-op\",{}}}\n", timing);
+        assert!(reader
+            .processes(row("\"timestamp\":1.5").as_bytes())
+            .is_ok());
+        assert!(reader
+            .processes(row("\"timestamp\":-1.5").as_bytes())
+            .is_err());
+        assert!(reader
+            .processes(row("\"duration\":1e300").as_bytes())
+            .is_err());
+    }
+}
@@ -0,0 +1,566 @@
+use crate::deps::DependencyGraph;
+use crate::error::Error;
+use crate::op::{Operation, OperationType};
+use crate::parser::FileType;
+use crate::path::normalize_relative;
+use crate::payload::{captured, Payload, PayloadGenerator};
+use crate::process::Process;
+use crate::snapshot::Snapshot;
+use std::fmt::Formatter;
+use std::fs::OpenOptions;
+use std::os::unix::fs::FileExt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+///
+/// How long the replay waits between the operations of a process
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Pacing {
+    Afap,        // as fast as possible, without waiting between the operations
+    Original,    // wait as long as the traced process did between the operations
+    Scaled(f64), // wait as the traced process did, but run the input times faster
+}
+
+impl Pacing {
+    ///
+    /// The time to wait before each operation of the process, which is the time between the end
+    /// of the previous operation and the start of this one. The operations with no timestamp
+    /// are not waited for.
+    ///
+    pub fn schedule(&self, process: &Process) -> Vec<Duration> {
+        let speed = match self {
+            Pacing::Afap => 0.0,
+            Pacing::Original => 1.0,
+            Pacing::Scaled(speed) => *speed,
+        };
+        if speed <= 0.0 || !speed.is_finite() {
+            return vec![Duration::ZERO; process.ops().len()];
+        }
+
+        let mut previous_end: Option<Duration> = None;
+        process
+            .ops()
+            .iter()
+            .map(|op| {
+                let wait = match (previous_end, op.timestamp()) {
+                    (Some(end), Some(start)) => start.saturating_sub(end).div_f64(speed),
+                    _ => Duration::ZERO,
+                };
+
+                if let Some(start) = op.timestamp() {
+                    previous_end = Some(start + op.duration().unwrap_or(Duration::ZERO));
+                }
+
+                wait
+            })
+            .collect()
+    }
+}
+
+impl FromStr for Pacing {
+    type Err = Error;
+
+    ///
+    /// Parse afap, original, or a speed multiplier like 2 or 0.5x
+    ///
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "afap" => Ok(Pacing::Afap),
+            "original" => Ok(Pacing::Original),
+            speed => match speed.trim_end_matches("x").parse::<f64>() {
+                Ok(speed) if speed > 0.0 && speed.is_finite() => Ok(Pacing::Scaled(speed)),
+                _ => Err(Error::ParseError(format!(
+                    "pacing {}, expected afap, original, or a positive speed multiplier",
+                    s
+                ))),
+            },
+        }
+    }
+}
+
+///
+/// Execute the parsed operations against a root directory, with the processes that do not
+/// depend on each other running in parallel
+///
+pub struct Replay {
+    root: PathBuf,                      // the directory the traced paths are replayed under
+    remaps: Vec<(String, String)>, // the path prefixes replaced before replaying the paths under root
+    pacing: Pacing,                // how long to wait between the operations of a process
+    payload: Box<dyn PayloadGenerator>, // the bytes written by the write operations
+}
+
+impl Replay {
+    pub fn new<P: AsRef<Path>>(root: P) -> Self {
+        Replay {
+            root: root.as_ref().to_path_buf(),
+            remaps: vec![],
+            pacing: Pacing::Afap,
+            payload: Box::new(Payload::Repeat),
+        }
+    }
+
+    pub fn set_pacing(&mut self, pacing: Pacing) {
+        self.pacing = pacing;
+    }
+
+    pub fn set_payload<G: PayloadGenerator + 'static>(&mut self, payload: G) {
+        self.payload = Box::new(payload);
+    }
+
+    ///
+    /// Replay the paths starting with the from prefix as if they started with the to prefix
+    ///
+    pub fn remap(&mut self, from: &str, to: &str) {
+        self.remaps.push((
+            from.trim_end_matches("/").to_string(),
+            to.trim_end_matches("/").to_string(),
+        ));
+    }
+
+    ///
+    /// The traced path after applying the longest matching remap
+    ///
+    pub fn remapped(&self, path: &str) -> String {
+        let remap = self
+            .remaps
+            .iter()
+            .filter(|(from, _)| {
+                path == from
+                    || path
+                        .strip_prefix(from.as_str())
+                        .map(|rest| rest.starts_with("/") || from.is_empty())
+                        .unwrap_or(false)
+            })
+            .max_by_key(|(from, _)| from.len());
+
+        match remap {
+            Some((from, to)) => format!("{}{}", to, &path[from.len()..]),
+            None => path.to_string(),
+        }
+    }
+
+    ///
+    /// The path under root that a traced path is replayed at. The relative paths are replayed
+    /// under root too, and the paths whose .. would leave root are rejected
+    ///
+    pub fn target(&self, path: &str) -> Result<PathBuf, Error> {
+        let remapped = normalize_relative(&self.remapped(path));
+        let remapped = remapped.trim_start_matches("/");
+        if remapped == ".." || remapped.starts_with("../") {
+            return Err(Error::Replay(format!("{} outside of the root", path)));
+        }
+
+        Ok(self.root.join(remapped))
+    }
+
+    ///
+    /// Create the files and directories that existed before the trace started under root
+    ///
+    pub fn prepare(&self, snapshot: &Snapshot) -> Result<(), Box<dyn std::error::Error>> {
+        // the files that cannot be replayed under root are left out
+        let files = snapshot
+            .files()
+            .iter()
+            .filter(|file| match file {
+                FileType::File(path, _) | FileType::Dir(path, _) => self.target(path).is_ok(),
+                FileType::Other => true,
+            })
+            .map(|file| match file {
+                FileType::File(path, size) => FileType::File(self.remapped(path), *size),
+                FileType::Dir(path, size) => FileType::Dir(self.remapped(path), *size),
+                FileType::Other => FileType::Other,
+            })
+            .collect();
+
+        Snapshot::new(files).materialize(&self.root)
+    }
+
+    ///
+    /// Replay the processes in the order of the dependency graph. The processes of each available
+    /// set run in parallel, and the next set starts when all of them are done.
+    ///
+    pub fn run(
+        &self,
+        dep_graph: &mut DependencyGraph,
+    ) -> Result<ReplayReport, Box<dyn std::error::Error>> {
+        let start = Instant::now();
+        let mut ops = vec![];
+
+        let mut available_set = dep_graph.available_set()?;
+        while !available_set.is_empty() {
+            let results = std::thread::scope(|scope| {
+                let handles: Vec<_> = available_set
+                    .iter()
+                    .map(|process| scope.spawn(move || self.replay_process(process)))
+                    .collect();
+
+                handles
+                    .into_iter()
+                    .map(|handle| handle.join())
+                    .collect::<Vec<_>>()
+            });
+
+            for result in results {
+                let replayed = result
+                    .map_err(|_| Error::Replay("a process of the available set".to_string()))?;
+                ops.extend(replayed);
+            }
+
+            available_set = dep_graph.available_set()?;
+        }
+
+        Ok(ReplayReport::new(ops, start.elapsed()))
+    }
+
+    fn replay_process(&self, process: &Process) -> Vec<ReplayedOp> {
+        let schedule = self.pacing.schedule(process);
+
+        process
+            .ops()
+            .iter()
+            .zip(schedule)
+            .filter_map(|(op, wait)| {
+                if !wait.is_zero() {
+                    std::thread::sleep(wait);
+                }
+                self.replay_op(process.pid(), op)
+            })
+            .collect()
+    }
+
+    // execute an operation and measure its latency. The operations that do not access the file
+    // system, or access pipes, sockets, and other non-file objects, are not replayed.
+    fn replay_op(&self, pid: usize, op: &Operation) -> Option<ReplayedOp> {
+        let accesses = op.accesses();
+        if accesses.is_empty() || accesses.iter().any(|(file, _)| !file.is_regular()) {
+            return None;
+        }
+
+        let target_path = |path: &str| -> std::io::Result<PathBuf> {
+            self.target(path)
+                .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e.to_string()))
+        };
+        let target = |file: &crate::file::File| -> std::io::Result<PathBuf> {
+            file.path()
+                .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e.to_string()))
+                .and_then(target_path)
+        };
+
+        let start = Instant::now();
+        let result: std::io::Result<()> = (|| match op.op_type() {
+            OperationType::Read(file, offset, len) => {
+                let mut buf = vec![0; *len];
+                let opened = std::fs::File::open(target(file)?)?;
+                opened.read_at(&mut buf, (*offset).max(0) as u64)?;
+                Ok(())
+            }
+            OperationType::Write(file, offset, len, content) => {
+                let offset = (*offset).max(0) as usize;
+                let payload = self.payload.generate(
+                    file.path().unwrap_or(""),
+                    offset,
+                    *len,
+                    &captured(content),
+                );
+                let opened = OpenOptions::new().write(true).open(target(file)?)?;
+                opened.write_all_at(&payload, offset as u64)
+            }
+            OperationType::Mkdir(file, _) => std::fs::create_dir(target(file)?),
+            OperationType::Mknod(file) => OpenOptions::new()
+                .write(true)
+                .create(true)
+                .truncate(false)
+                .open(target(file)?)
+                .map(|_| ()),
+            OperationType::Remove(file) => {
+                let target = target(file)?;
+                if std::fs::symlink_metadata(&target)?.is_dir() {
+                    std::fs::remove_dir(target)
+                } else {
+                    std::fs::remove_file(target)
+                }
+            }
+            OperationType::Rename(file, to) => std::fs::rename(target(file)?, target_path(to)?),
+            OperationType::OpenAt(file, _) => std::fs::File::open(target(file)?).map(|_| ()),
+            OperationType::Truncate(file) => OpenOptions::new()
+                .write(true)
+                .open(target(file)?)?
+                .set_len(0),
+            OperationType::Stat(file)
+            | OperationType::Fstat(file)
+            | OperationType::Statx(file)
+            | OperationType::StatFS(file)
+            | OperationType::Fstatat(file) => std::fs::metadata(target(file)?).map(|_| ()),
+            OperationType::Map(file, offset, len, _, _) => {
+                // the accesses to the mapped memory are not traced, so replay the mapping as a
+                // read of the mapped region
+                let mut buf = vec![0; *len];
+                let opened = std::fs::File::open(target(file)?)?;
+                opened.read_at(&mut buf, (*offset).max(0) as u64)?;
+                Ok(())
+            }
+            OperationType::Sync(file) => OpenOptions::new()
+                .write(true)
+                .open(target(file)?)?
+                .sync_all(),
+            OperationType::Copy(from, from_offset, to, to_offset, len) => {
+                let mut buf = vec![0; *len];
+                let source = std::fs::File::open(target(from)?)?;
+                let read = source.read_at(&mut buf, (*from_offset).max(0) as u64)?;
+                let destination = OpenOptions::new().write(true).open(target(to)?)?;
+                destination.write_all_at(&buf[..read], (*to_offset).max(0) as u64)
+            }
+            _ => Ok(()),
+        })();
+
+        Some(ReplayedOp::new(
+            pid,
+            op.clone(),
+            start.elapsed(),
+            result.err().map(|e| e.to_string()),
+        ))
+    }
+}
+
+///
+/// A replayed operation with its latency, and the error it failed with, if there is any
+///
+#[derive(Debug, Clone)]
+pub struct ReplayedOp {
+    pid: usize,
+    op: Operation,
+    latency: Duration,
+    error: Option<String>,
+}
+
+impl ReplayedOp {
+    pub fn new(pid: usize, op: Operation, latency: Duration, error: Option<String>) -> Self {
+        ReplayedOp {
+            pid,
+            op,
+            latency,
+            error,
+        }
+    }
+
+    pub fn pid(&self) -> usize {
+        self.pid
+    }
+
+    pub fn op(&self) -> &Operation {
+        &self.op
+    }
+
+    pub fn latency(&self) -> Duration {
+        self.latency
+    }
+
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+}
+
+impl std::fmt::Display for ReplayedOp {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match &self.error {
+            Some(error) => write!(
+                f,
+                "{} {} {}us error: {}",
+                self.pid,
+                self.op,
+                self.latency.as_micros(),
+                error
+            ),
+            None => write!(f, "{} {} {}us", self.pid, self.op, self.latency.as_micros()),
+        }
+    }
+}
+
+///
+/// The replayed operations, in the order they finished per process, and the total replay time
+///
+#[derive(Debug, Clone)]
+pub struct ReplayReport {
+    ops: Vec<ReplayedOp>,
+    elapsed: Duration,
+}
+
+impl ReplayReport {
+    pub fn new(ops: Vec<ReplayedOp>, elapsed: Duration) -> Self {
+        ReplayReport { ops, elapsed }
+    }
+
+    pub fn ops(&self) -> &Vec<ReplayedOp> {
+        &self.ops
+    }
+
+    pub fn errors(&self) -> Vec<&ReplayedOp> {
+        self.ops.iter().filter(|op| op.error.is_some()).collect()
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+}
+
+impl std::fmt::Display for ReplayReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for op in self.ops.iter() {
+            writeln!(f, "{}", op)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::deps::DependencyGraph;
+    use crate::file::File;
+    use crate::op::Operation;
+    use crate::parser::FileType;
+    use crate::process::Process;
+    use crate::replay::{Pacing, Replay};
+    use crate::snapshot::Snapshot;
+    use std::path::PathBuf;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    #[test]
+    fn remap() {
+        let mut replay = Replay::new("/sandbox");
+        replay.remap("/home/user", "/data");
+        replay.remap("/home/user/cache/", "/tmp/cache");
+
+        assert_eq!(replay.remapped("/home/user/a"), "/data/a");
+        assert_eq!(replay.remapped("/home/user"), "/data");
+        assert_eq!(replay.remapped("/home/user/cache/b"), "/tmp/cache/b");
+        assert_eq!(replay.remapped("/home/username"), "/home/username");
+        assert_eq!(
+            replay.target("/home/user/a").ok(),
+            Some(PathBuf::from("/sandbox/data/a"))
+        );
+        assert_eq!(
+            replay.target("/../home/user/../a").ok(),
+            Some(PathBuf::from("/sandbox/home/a"))
+        );
+        assert_eq!(
+            replay.target("a/../b").ok(),
+            Some(PathBuf::from("/sandbox/b"))
+        );
+        assert!(replay.target("a/../../b").is_err());
+    }
+
+    #[test]
+    fn outside() -> Result<(), Box<dyn std::error::Error>> {
+        let dir =
+            std::env::temp_dir().join(format!("strace-parser-outside-{}", std::process::id()));
+        let root = dir.join("root");
+        std::fs::create_dir_all(&root)?;
+        std::fs::write(dir.join("outside"), b"keep")?;
+
+        let mut process = Process::new(1);
+        process.add_op(Operation::remove(Arc::new(File::new("../outside"))));
+        let mut dep_graph = DependencyGraph::new(vec![process])?.order()?;
+
+        // the path would leave the root, so the remove fails instead of reaching the file
+        let report = Replay::new(&root).run(&mut dep_graph)?;
+        assert_eq!(report.errors().len(), 1);
+        assert_eq!(std::fs::read(dir.join("outside"))?, b"keep".to_vec());
+
+        std::fs::remove_dir_all(&dir)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn replay() -> Result<(), Box<dyn std::error::Error>> {
+        let root =
+            std::env::temp_dir().join(format!("strace-parser-replay-{}", std::process::id()));
+
+        let input = Arc::new(File::new("/data/input"));
+        let output = Arc::new(File::new("/data/output"));
+        let missing = Arc::new(File::new("/data/missing"));
+
+        let mut writer = Process::new(1);
+        writer.add_op(Operation::read(input.clone(), 4, 0));
+        writer.add_op(Operation::mknod(output.clone()));
+        writer.add_op(Operation::write(output.clone(), "\"ab\"".to_string(), 5, 0));
+
+        let mut reader = Process::new(2);
+        reader.add_op(Operation::read(output.clone(), 5, 0));
+        reader.add_op(Operation::read(missing.clone(), 5, 0));
+
+        let mut dep_graph = DependencyGraph::new(vec![writer, reader])?.order()?;
+
+        let replay = Replay::new(&root);
+        replay.prepare(&Snapshot::new(vec![FileType::File(
+            "/data/input".to_string(),
+            4,
+        )]))?;
+        let report = replay.run(&mut dep_graph)?;
+
+        assert_eq!(std::fs::read(root.join("data/output"))?, b"ababa".to_vec());
+        assert_eq!(report.ops().len(), 5);
+
+        // the read of a file that does not exist fails, and the failure is reported
+        let errors = report.errors();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].pid(), 2);
+        assert_eq!(errors[0].op(), &Operation::read(missing, 5, 0));
+
+        std::fs::remove_dir_all(&root)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn pacing() -> Result<(), Box<dyn std::error::Error>> {
+        let file = Arc::new(File::new("/data/input"));
+
+        let mut process = Process::new(1);
+        process.add_op(Operation::read(file.clone(), 4, 0).with_timing(
+            Some(Duration::from_millis(100)),
+            Some(Duration::from_millis(10)),
+        ));
+        process.add_op(Operation::read(file.clone(), 4, 4).with_timing(
+            Some(Duration::from_millis(150)),
+            Some(Duration::from_millis(10)),
+        ));
+        process.add_op(Operation::read(file.clone(), 4, 8));
+        process.add_op(
+            Operation::read(file.clone(), 4, 12)
+                .with_timing(Some(Duration::from_millis(200)), None),
+        );
+
+        assert_eq!(Pacing::Afap.schedule(&process), vec![Duration::ZERO; 4]);
+        assert_eq!(
+            Pacing::Original.schedule(&process),
+            vec![
+                Duration::ZERO,
+                Duration::from_millis(40),
+                Duration::ZERO,
+                Duration::from_millis(40)
+            ]
+        );
+        assert_eq!(
+            Pacing::Scaled(2.0).schedule(&process),
+            vec![
+                Duration::ZERO,
+                Duration::from_millis(20),
+                Duration::ZERO,
+                Duration::from_millis(20)
+            ]
+        );
+
+        assert_eq!("afap".parse::<Pacing>()?, Pacing::Afap);
+        assert_eq!("original".parse::<Pacing>()?, Pacing::Original);
+        assert_eq!("0.5x".parse::<Pacing>()?, Pacing::Scaled(0.5));
+        assert!("0".parse::<Pacing>().is_err());
+        assert!("fast".parse::<Pacing>().is_err());
+
+        Ok(())
+    }
+}
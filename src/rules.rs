@@ -0,0 +1,197 @@
+use crate::error::Error;
+use crate::path::has_prefix;
+use std::str::FromStr;
+
+///
+/// The built-in sets of path prefixes that are excluded from the traces
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Preset {
+    System, // the shared libraries, the dynamic loader cache, and other system files
+    Pseudo, // the pseudo-filesystems, /proc, /sys, and /dev
+}
+
+impl Preset {
+    pub fn prefixes(&self) -> &'static [&'static str] {
+        match self {
+            Preset::System => &[
+                "/lib",
+                "/lib32",
+                "/lib64",
+                "/libx32",
+                "/usr/lib",
+                "/usr/lib32",
+                "/usr/lib64",
+                "/usr/libexec",
+                "/usr/share/locale",
+                "/usr/share/zoneinfo",
+                "/usr/lib/locale",
+                "/etc/ld.so.cache",
+                "/etc/ld.so.preload",
+                "/etc/localtime",
+                "/etc/nsswitch.conf",
+                "/etc/passwd",
+                "/etc/group",
+            ],
+            Preset::Pseudo => &["/proc", "/sys", "/dev"],
+        }
+    }
+}
+
+impl FromStr for Preset {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "system" => Ok(Preset::System),
+            "pseudo" => Ok(Preset::Pseudo),
+            _ => Err(Error::ParseError(format!(
+                "preset {}, expected system or pseudo",
+                s
+            ))),
+        }
+    }
+}
+
+///
+/// The paths kept in the traces, and how they are rewritten. A path is excluded if the longest
+/// include or exclude prefix it is under is an exclude, or if it is under none of them while
+/// there are include prefixes. The kept paths are rewritten with the longest matching rewrite,
+/// like /home/user/project to $ROOT.
+///
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PathRules {
+    prefixes: Vec<(String, bool)>, // the include and exclude prefixes, true if included
+    rewrites: Vec<(String, String)>, // the path prefixes replaced, and their replacements
+}
+
+impl PathRules {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    ///
+    /// The rules excluding the paths of all the presets
+    ///
+    pub fn with_presets() -> Self {
+        let mut rules = PathRules::new();
+        rules.preset(Preset::System);
+        rules.preset(Preset::Pseudo);
+        rules
+    }
+
+    pub fn include(&mut self, prefix: &str) {
+        self.prefixes
+            .push((prefix.trim_end_matches("/").to_string(), true));
+    }
+
+    pub fn exclude(&mut self, prefix: &str) {
+        self.prefixes
+            .push((prefix.trim_end_matches("/").to_string(), false));
+    }
+
+    pub fn preset(&mut self, preset: Preset) {
+        for prefix in preset.prefixes() {
+            self.exclude(prefix);
+        }
+    }
+
+    pub fn rewrite(&mut self, from: &str, to: &str) {
+        self.rewrites.push((
+            from.trim_end_matches("/").to_string(),
+            to.trim_end_matches("/").to_string(),
+        ));
+    }
+
+    ///
+    /// Whether the operations on the path are dropped from the traces. The rules apply to the
+    /// absolute paths, so the relative paths the parser could not resolve are always kept.
+    ///
+    pub fn excludes(&self, path: &str) -> bool {
+        // pipes, sockets, non-file descriptors, and unresolved relative paths are never excluded
+        if !path.starts_with("/") {
+            return false;
+        }
+
+        let longest = self
+            .prefixes
+            .iter()
+            .filter(|(prefix, _)| has_prefix(path, prefix))
+            .max_by_key(|(prefix, _)| prefix.len());
+
+        match longest {
+            Some((_, included)) => !included,
+            None => self.prefixes.iter().any(|(_, included)| *included),
+        }
+    }
+
+    ///
+    /// The path after applying the longest matching rewrite
+    ///
+    pub fn rewritten(&self, path: &str) -> String {
+        let rewrite = self
+            .rewrites
+            .iter()
+            .filter(|(from, _)| has_prefix(path, from))
+            .max_by_key(|(from, _)| from.len());
+
+        match rewrite {
+            Some((from, to)) => format!("{}{}", to, &path[from.len()..]),
+            None => path.to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::rules::{PathRules, Preset};
+
+    #[test]
+    fn excludes() -> Result<(), Box<dyn std::error::Error>> {
+        let mut rules = PathRules::new();
+        assert!(!rules.excludes("/lib/x86_64-linux-gnu/libc.so.6"));
+
+        rules.preset("system".parse::<Preset>()?);
+        rules.preset(Preset::Pseudo);
+        assert!(rules.excludes("/lib/x86_64-linux-gnu/libc.so.6"));
+        assert!(rules.excludes("/etc/ld.so.cache"));
+        assert!(rules.excludes("/proc/1/stat"));
+        assert!(!rules.excludes("/library/a"));
+        assert!(!rules.excludes("/home/user/a"));
+        assert!(!rules.excludes("pipe:[1]"));
+
+        // the paths under no included prefix are excluded once any prefix is included
+        rules.include("/proc/self/fd");
+        rules.include("/home/user/");
+        assert!(!rules.excludes("/proc/self/fd/3"));
+        assert!(rules.excludes("/proc/self/maps"));
+        assert!(!rules.excludes("/home/user/a"));
+        assert!(rules.excludes("/tmp/a"));
+        assert!(!rules.excludes("pipe:[1]"));
+
+        assert!("usr".parse::<Preset>().is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn rewrites() {
+        let mut rules = PathRules::new();
+        rules.rewrite("/home/user/project/", "$ROOT");
+        rules.rewrite("/home/user/project/cache", "/tmp/cache");
+
+        assert_eq!(
+            rules.rewritten("/home/user/project/src/a.c"),
+            "$ROOT/src/a.c"
+        );
+        assert_eq!(rules.rewritten("/home/user/project"), "$ROOT");
+        assert_eq!(
+            rules.rewritten("/home/user/project/cache/b"),
+            "/tmp/cache/b"
+        );
+        assert_eq!(
+            rules.rewritten("/home/user/projects"),
+            "/home/user/projects"
+        );
+    }
+}
@@ -0,0 +1,130 @@
+use crate::parser::FileType;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+///
+/// The files and directories that existed before the trace started, which a replay needs to
+/// start from an equivalent state
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct Snapshot {
+    files: Vec<FileType>, // sorted by path, so the directories come before their content
+}
+
+impl Snapshot {
+    pub fn new(files: Vec<FileType>) -> Self {
+        let mut entries: BTreeMap<String, FileType> = files
+            .into_iter()
+            .filter(|file| file != &FileType::Other)
+            .map(|file| (file.path().to_string(), file))
+            .collect();
+
+        // the directories containing the files existed too
+        let dirs: Vec<String> = entries
+            .keys()
+            .flat_map(|path| {
+                Path::new(path)
+                    .ancestors()
+                    .skip(1)
+                    .filter_map(|ancestor| ancestor.to_str())
+                    .filter(|ancestor| *ancestor != "/" && !ancestor.is_empty())
+                    .map(|ancestor| ancestor.to_string())
+                    .collect::<Vec<String>>()
+            })
+            .collect();
+        for dir in dirs {
+            match entries.get(&dir) {
+                Some(FileType::Dir(_, _)) => {}
+                _ => {
+                    entries.insert(dir.clone(), FileType::Dir(dir, 0));
+                }
+            }
+        }
+
+        Snapshot {
+            files: entries.into_values().collect(),
+        }
+    }
+
+    pub fn files(&self) -> &Vec<FileType> {
+        &self.files
+    }
+
+    ///
+    /// Create the files and directories under the root directory. The files are created with
+    /// their size, filled with zeros.
+    ///
+    pub fn materialize<P: AsRef<Path>>(&self, root: P) -> Result<(), Box<dyn std::error::Error>> {
+        for file in self.files.iter() {
+            let target = root.as_ref().join(file.path().trim_start_matches("/"));
+            match file {
+                FileType::Dir(_, _) => std::fs::create_dir_all(&target)?,
+                FileType::File(_, size) => {
+                    if let Some(parent) = target.parent() {
+                        std::fs::create_dir_all(parent)?;
+                    }
+                    let created = std::fs::File::create(&target)?;
+                    created.set_len(*size as u64)?;
+                }
+                FileType::Other => {}
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl std::fmt::Display for Snapshot {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for file in self.files.iter() {
+            write!(f, "{}", file)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::parser::FileType;
+    use crate::snapshot::Snapshot;
+
+    #[test]
+    fn implied_directories() {
+        let snapshot = Snapshot::new(vec![
+            FileType::File("/tmp/dir/a".to_string(), 5),
+            FileType::Dir("/tmp".to_string(), 4096),
+            FileType::Other,
+        ]);
+
+        assert_eq!(
+            snapshot.files(),
+            &vec![
+                FileType::Dir("/tmp".to_string(), 4096),
+                FileType::Dir("/tmp/dir".to_string(), 0),
+                FileType::File("/tmp/dir/a".to_string(), 5),
+            ]
+        );
+    }
+
+    #[test]
+    fn materialize() -> Result<(), Box<dyn std::error::Error>> {
+        let root =
+            std::env::temp_dir().join(format!("strace-parser-snapshot-{}", std::process::id()));
+
+        let snapshot = Snapshot::new(vec![
+            FileType::File("/data/input".to_string(), 4096),
+            FileType::File("/data/empty".to_string(), 0),
+            FileType::Dir("/data/out".to_string(), 4096),
+        ]);
+        snapshot.materialize(&root)?;
+
+        assert_eq!(std::fs::metadata(root.join("data/input"))?.len(), 4096);
+        assert_eq!(std::fs::metadata(root.join("data/empty"))?.len(), 0);
+        assert!(std::fs::metadata(root.join("data/out"))?.is_dir());
+
+        std::fs::remove_dir_all(&root)?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,324 @@
+use crate::op::OperationType;
+use crate::process::Process;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Formatter;
+
+// the number of files listed in the top files of the reports
+const TOP_FILES: usize = 10;
+
+///
+/// The statistics of the operations of a process, or of all the processes
+///
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Stats {
+    pid: Option<usize>,                  // the process, or none for all the processes
+    ops: BTreeMap<String, usize>,        // the number of operations of each type
+    bytes_read: usize,                   // the bytes read by the read and copy operations
+    bytes_written: usize,                // the bytes written by the write and copy operations
+    read_sizes: BTreeMap<usize, usize>, // the number of reads of each size, rounded up to the powers of two
+    write_sizes: BTreeMap<usize, usize>, // the number of writes of each size, rounded up to the powers of two
+    traffic: BTreeMap<String, (usize, usize)>, // the bytes read and written of each regular file accessed
+}
+
+impl Stats {
+    pub fn new(process: &Process) -> Self {
+        let mut stats = Stats {
+            pid: Some(process.pid()),
+            ..Default::default()
+        };
+
+        for op in process.ops() {
+            // the system calls that do nothing the parser keeps track of are not operations
+            if let OperationType::NoOp = op.op_type() {
+                continue;
+            }
+            *stats.ops.entry(op.name()).or_insert(0) += 1;
+
+            for (file, _) in op.accesses() {
+                if let (Ok(path), true) = (file.path(), file.is_regular()) {
+                    stats.traffic.entry(path.to_string()).or_insert((0, 0));
+                }
+            }
+
+            match op.op_type() {
+                OperationType::Read(file, _, len) => stats.read(file.path().ok(), *len),
+                OperationType::Write(file, _, len, _) => stats.write(file.path().ok(), *len),
+                OperationType::Copy(from, _, to, _, len) => {
+                    stats.read(from.path().ok(), *len);
+                    stats.write(to.path().ok(), *len);
+                }
+                _ => {}
+            }
+        }
+
+        stats
+    }
+
+    ///
+    /// The statistics of all the operations of the processes
+    ///
+    pub fn overall(stats: &[Stats]) -> Self {
+        let mut overall = Stats::default();
+        for stats in stats {
+            for (name, count) in stats.ops.iter() {
+                *overall.ops.entry(name.clone()).or_insert(0) += count;
+            }
+            overall.bytes_read += stats.bytes_read;
+            overall.bytes_written += stats.bytes_written;
+            for (size, count) in stats.read_sizes.iter() {
+                *overall.read_sizes.entry(*size).or_insert(0) += count;
+            }
+            for (size, count) in stats.write_sizes.iter() {
+                *overall.write_sizes.entry(*size).or_insert(0) += count;
+            }
+            for (path, (read, written)) in stats.traffic.iter() {
+                let traffic = overall.traffic.entry(path.clone()).or_insert((0, 0));
+                traffic.0 += read;
+                traffic.1 += written;
+            }
+        }
+
+        overall
+    }
+
+    fn read(&mut self, path: Option<&str>, len: usize) {
+        self.bytes_read += len;
+        *self.read_sizes.entry(bucket(len)).or_insert(0) += 1;
+        if let Some(traffic) = path.and_then(|path| self.traffic.get_mut(path)) {
+            traffic.0 += len;
+        }
+    }
+
+    fn write(&mut self, path: Option<&str>, len: usize) {
+        self.bytes_written += len;
+        *self.write_sizes.entry(bucket(len)).or_insert(0) += 1;
+        if let Some(traffic) = path.and_then(|path| self.traffic.get_mut(path)) {
+            traffic.1 += len;
+        }
+    }
+
+    pub fn pid(&self) -> Option<usize> {
+        self.pid
+    }
+
+    pub fn ops(&self) -> &BTreeMap<String, usize> {
+        &self.ops
+    }
+
+    pub fn bytes_read(&self) -> usize {
+        self.bytes_read
+    }
+
+    pub fn bytes_written(&self) -> usize {
+        self.bytes_written
+    }
+
+    ///
+    /// The bytes read per byte written, or none if nothing is written
+    ///
+    pub fn read_write_ratio(&self) -> Option<f64> {
+        if self.bytes_written == 0 {
+            return None;
+        }
+        Some(self.bytes_read as f64 / self.bytes_written as f64)
+    }
+
+    pub fn read_sizes(&self) -> &BTreeMap<usize, usize> {
+        &self.read_sizes
+    }
+
+    pub fn write_sizes(&self) -> &BTreeMap<usize, usize> {
+        &self.write_sizes
+    }
+
+    ///
+    /// The regular files accessed by the operations
+    ///
+    pub fn files(&self) -> BTreeSet<&str> {
+        self.traffic.keys().map(|path| path.as_str()).collect()
+    }
+
+    ///
+    /// The files with the most bytes read and written, with the bytes read and written
+    ///
+    pub fn top_files(&self, n: usize) -> Vec<(&str, usize, usize)> {
+        let mut files: Vec<(&str, usize, usize)> = self
+            .traffic
+            .iter()
+            .filter(|(_, (read, written))| read + written > 0)
+            .map(|(path, (read, written))| (path.as_str(), *read, *written))
+            .collect();
+        files.sort_by(|(p1, r1, w1), (p2, r2, w2)| (r2 + w2).cmp(&(r1 + w1)).then(p1.cmp(p2)));
+        files.truncate(n);
+        files
+    }
+}
+
+impl std::fmt::Display for Stats {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.pid {
+            Some(pid) => writeln!(f, "process {}", pid)?,
+            None => writeln!(f, "all processes")?,
+        }
+
+        let ops: Vec<String> = self
+            .ops
+            .iter()
+            .map(|(name, count)| format!("{} {}", name, count))
+            .collect();
+        writeln!(f, "  operations: {}", ops.join(", "))?;
+        writeln!(f, "  bytes read: {}", self.bytes_read)?;
+        writeln!(f, "  bytes written: {}", self.bytes_written)?;
+        match self.read_write_ratio() {
+            Some(ratio) => writeln!(f, "  read/write ratio: {:.2}", ratio)?,
+            None => writeln!(f, "  read/write ratio: -")?,
+        }
+        writeln!(f, "  files: {}", self.traffic.len())?;
+        writeln!(f, "  read sizes: {}", histogram(&self.read_sizes))?;
+        writeln!(f, "  write sizes: {}", histogram(&self.write_sizes))?;
+
+        let top_files = self.top_files(TOP_FILES);
+        if !top_files.is_empty() {
+            writeln!(f, "  top files:")?;
+            for (path, read, written) in top_files {
+                writeln!(f, "    {}: read {}, written {}", path, read, written)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+///
+/// The statistics of each process, and of all the processes
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct StatsReport {
+    processes: Vec<Stats>,
+    overall: Stats,
+}
+
+impl StatsReport {
+    pub fn new(processes: &[Process]) -> Self {
+        let mut processes: Vec<Stats> = processes.iter().map(Stats::new).collect();
+        processes.sort_by_key(|stats| stats.pid);
+        let overall = Stats::overall(&processes);
+
+        StatsReport { processes, overall }
+    }
+
+    pub fn processes(&self) -> &Vec<Stats> {
+        &self.processes
+    }
+
+    pub fn overall(&self) -> &Stats {
+        &self.overall
+    }
+}
+
+impl std::fmt::Display for StatsReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.overall)?;
+        for stats in self.processes.iter() {
+            write!(f, "\n{}", stats)?;
+        }
+
+        Ok(())
+    }
+}
+
+// the power of two the size is rounded up to
+fn bucket(size: usize) -> usize {
+    size.max(1).next_power_of_two()
+}
+
+fn histogram(sizes: &BTreeMap<usize, usize>) -> String {
+    if sizes.is_empty() {
+        return "-".to_string();
+    }
+
+    sizes
+        .iter()
+        .map(|(size, count)| format!("<={} {}", size, count))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod test {
+    use crate::file::File;
+    use crate::op::Operation;
+    use crate::process::Process;
+    use crate::stats::{Stats, StatsReport};
+    use std::sync::Arc;
+
+    fn processes() -> Vec<Process> {
+        let input = Arc::new(File::new("/data/input"));
+        let output = Arc::new(File::new("/data/output"));
+
+        let mut p1 = Process::new(1);
+        p1.add_op(Operation::read(input.clone(), 4096, 0));
+        p1.add_op(Operation::read(input.clone(), 100, 4096));
+        p1.add_op(Operation::mknod(output.clone()));
+        p1.add_op(Operation::write(output.clone(), String::new(), 1000, 0));
+        p1.add_op(Operation::stat(Arc::new(File::new("/etc/hosts"))));
+
+        let mut p2 = Process::new(2);
+        p2.add_op(Operation::read(output.clone(), 1000, 0));
+        p2.add_op(Operation::no_op());
+        p2.add_op(Operation::copy(input.clone(), 0, output.clone(), 0, 10));
+
+        vec![p2, p1]
+    }
+
+    #[test]
+    fn process() {
+        let stats = Stats::new(&processes()[1]);
+
+        assert_eq!(stats.pid(), Some(1));
+        assert_eq!(stats.ops().get("Read"), Some(&2));
+        assert_eq!(stats.ops().get("Mknod"), Some(&1));
+        assert_eq!(stats.bytes_read(), 4196);
+        assert_eq!(stats.bytes_written(), 1000);
+        assert_eq!(stats.read_write_ratio(), Some(4.196));
+        assert_eq!(
+            stats.read_sizes().iter().collect::<Vec<_>>(),
+            vec![(&128, &1), (&4096, &1)]
+        );
+        assert_eq!(
+            stats.files().into_iter().collect::<Vec<_>>(),
+            vec!["/data/input", "/data/output", "/etc/hosts"]
+        );
+        assert_eq!(stats.top_files(1), vec![("/data/input", 4196, 0)]);
+    }
+
+    #[test]
+    fn report() {
+        let report = StatsReport::new(&processes());
+
+        assert_eq!(
+            report
+                .processes()
+                .iter()
+                .map(|stats| stats.pid())
+                .collect::<Vec<_>>(),
+            vec![Some(1), Some(2)]
+        );
+
+        let overall = report.overall();
+        assert_eq!(overall.pid(), None);
+        assert_eq!(overall.ops().get("Read"), Some(&3));
+        assert_eq!(overall.ops().get("NoOp"), None);
+        // the copy source is counted as read, and the destination as written
+        assert_eq!(overall.ops().get("Copy"), Some(&1));
+        assert_eq!(overall.bytes_read(), 5206);
+        assert_eq!(overall.bytes_written(), 1010);
+        assert_eq!(
+            overall.top_files(10),
+            vec![("/data/input", 4206, 0), ("/data/output", 1000, 1010)]
+        );
+        assert!(report.to_string().starts_with(
+            "all processes\n  operations: Copy 1, Mknod 1, Read 3, Stat 1, Write 1\n  bytes read: 5206\n"
+        ));
+    }
+}
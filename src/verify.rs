@@ -0,0 +1,394 @@
+use crate::op::{Operation, OperationType};
+use crate::parser::FileType;
+use crate::replay::{Replay, ReplayReport};
+use crate::snapshot::Snapshot;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Formatter;
+
+///
+/// The files and directories expected after applying the operations to the initial state of
+/// the file system, with the sizes of the files
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExpectedState {
+    entries: BTreeMap<String, FileType>,
+}
+
+impl ExpectedState {
+    pub fn new(snapshot: &Snapshot) -> Self {
+        ExpectedState {
+            entries: snapshot
+                .files()
+                .iter()
+                .map(|file| (file.path().to_string(), file.clone()))
+                .collect(),
+        }
+    }
+
+    pub fn entries(&self) -> Vec<FileType> {
+        self.entries.values().cloned().collect()
+    }
+
+    ///
+    /// Apply the effects of an operation on the file system. The operations should be applied in
+    /// the order they are replayed in.
+    ///
+    pub fn apply(&mut self, op: &Operation) {
+        // the removed files that are still open cannot be reached by a path
+        let path = |file: &crate::file::File| -> Option<String> {
+            file.path()
+                .ok()
+                .filter(|path| file.is_regular() && !path.ends_with(" (deleted)"))
+                .map(|path| path.to_string())
+        };
+
+        match op.op_type() {
+            OperationType::Mknod(file) => {
+                if let Some(path) = path(file) {
+                    self.entries
+                        .entry(path.clone())
+                        .or_insert(FileType::File(path, 0));
+                }
+            }
+            OperationType::Mkdir(file, _) => {
+                if let Some(path) = path(file) {
+                    self.entries
+                        .entry(path.clone())
+                        .or_insert(FileType::Dir(path, 0));
+                }
+            }
+            OperationType::Write(file, offset, len, _) => {
+                if let Some(path) = path(file) {
+                    self.extend(&path, (*offset).max(0) as usize + len);
+                }
+            }
+            OperationType::Truncate(file) => {
+                if let Some(path) = path(file) {
+                    if let Some(FileType::File(_, size)) = self.entries.get_mut(&path) {
+                        *size = 0;
+                    }
+                }
+            }
+            OperationType::Copy(from, from_offset, to, to_offset, len) => {
+                if let (Some(from), Some(to)) = (path(from), path(to)) {
+                    // only the bytes available in the source are copied
+                    let available = match self.entries.get(&from) {
+                        Some(FileType::File(_, size)) => {
+                            size.saturating_sub((*from_offset).max(0) as usize)
+                        }
+                        _ => 0,
+                    };
+                    let copied = available.min(*len);
+                    if copied > 0 {
+                        self.extend(&to, (*to_offset).max(0) as usize + copied);
+                    }
+                }
+            }
+            OperationType::Remove(file) => {
+                if let Some(path) = path(file) {
+                    self.remove(&path);
+                }
+            }
+            OperationType::Rename(file, to) => {
+                if let Some(path) = path(file) {
+                    if path != *to && self.entries.contains_key(&path) {
+                        self.remove(to);
+
+                        let moved: Vec<String> = self
+                            .entries
+                            .keys()
+                            .filter(|entry| is_under(entry, &path))
+                            .cloned()
+                            .collect();
+                        for entry in moved {
+                            if let Some(file_type) = self.entries.remove(&entry) {
+                                let new_path = format!("{}{}", to, &entry[path.len()..]);
+                                let file_type = match file_type {
+                                    FileType::File(_, size) => {
+                                        FileType::File(new_path.clone(), size)
+                                    }
+                                    FileType::Dir(_, size) => FileType::Dir(new_path.clone(), size),
+                                    FileType::Other => FileType::Other,
+                                };
+                                self.entries.insert(new_path, file_type);
+                            }
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // the file has at least the input size after writing to it
+    fn extend(&mut self, path: &str, size: usize) {
+        match self.entries.get_mut(path) {
+            Some(FileType::File(_, current)) => *current = (*current).max(size),
+            Some(_) => {}
+            None => {
+                self.entries
+                    .insert(path.to_string(), FileType::File(path.to_string(), size));
+            }
+        }
+    }
+
+    // remove the file, or the directory and everything in it
+    fn remove(&mut self, path: &str) {
+        self.entries.retain(|entry, _| !is_under(entry, path));
+    }
+
+    ///
+    /// Apply the operations that succeeded in a replay, in the order they were replayed, and
+    /// report the ones that failed, since the replay did not do what the trace did
+    ///
+    pub fn apply_replayed(&mut self, report: &ReplayReport) -> Vec<Mismatch> {
+        let mut mismatches = vec![];
+        for replayed in report.ops() {
+            match replayed.error() {
+                None => self.apply(replayed.op()),
+                Some(error) => mismatches.push(Mismatch::Failed(
+                    replayed.pid(),
+                    replayed.op().to_string(),
+                    error.to_string(),
+                )),
+            }
+        }
+
+        mismatches
+    }
+
+    ///
+    /// Compare the expected state with the files and directories under the replay's root, and
+    /// report the mismatches per file
+    ///
+    pub fn verify(&self, replay: &Replay) -> Vec<Mismatch> {
+        let mut mismatches = vec![];
+
+        for (path, file_type) in self.entries.iter() {
+            // the paths that cannot be replayed under root are missing from it
+            let target = match replay.target(path) {
+                Ok(target) => target,
+                Err(_) => {
+                    mismatches.push(Mismatch::Missing(path.clone()));
+                    continue;
+                }
+            };
+            let metadata = match std::fs::symlink_metadata(&target) {
+                Ok(metadata) => metadata,
+                Err(_) => {
+                    mismatches.push(Mismatch::Missing(path.clone()));
+                    continue;
+                }
+            };
+
+            match file_type {
+                FileType::File(_, size) => {
+                    if metadata.is_dir() {
+                        mismatches.push(Mismatch::NotAFile(path.clone()));
+                    } else if metadata.len() != *size as u64 {
+                        mismatches.push(Mismatch::Size(path.clone(), *size, metadata.len()));
+                    }
+                }
+                FileType::Dir(_, _) => {
+                    if !metadata.is_dir() {
+                        mismatches.push(Mismatch::NotADir(path.clone()));
+                        continue;
+                    }
+
+                    // the directory has nothing but the expected entries
+                    let expected: BTreeSet<String> = self
+                        .entries
+                        .keys()
+                        .filter(|entry| parent(entry) == path.as_str())
+                        .cloned()
+                        .collect();
+                    if let Ok(dir) = std::fs::read_dir(&target) {
+                        let mut unexpected: Vec<String> = dir
+                            .filter_map(|entry| entry.ok())
+                            .filter_map(|entry| entry.file_name().to_str().map(|n| n.to_string()))
+                            .map(|name| format!("{}/{}", path.trim_end_matches("/"), name))
+                            .filter(|entry| {
+                                // the relative paths, like rewritten ones, are replayed under root
+                                !expected.contains(entry)
+                                    && !expected.contains(entry.trim_start_matches("/"))
+                            })
+                            .collect();
+                        unexpected.sort();
+                        mismatches.extend(unexpected.into_iter().map(Mismatch::Unexpected));
+                    }
+                }
+                FileType::Other => {}
+            }
+        }
+
+        mismatches
+    }
+}
+
+// whether the entry is the path, or is in the directory at the path
+fn is_under(entry: &str, path: &str) -> bool {
+    entry == path
+        || entry
+            .strip_prefix(path)
+            .map(|rest| rest.starts_with("/"))
+            .unwrap_or(false)
+}
+
+fn parent(path: &str) -> &str {
+    match path.rfind("/") {
+        Some(0) => "/",
+        Some(index) => &path[..index],
+        None => "/",
+    }
+}
+
+///
+/// A difference between the expected state and the file system after a replay
+///
+#[derive(Debug, Clone, PartialEq)]
+pub enum Mismatch {
+    Missing(String),               // the expected file or directory does not exist
+    Unexpected(String),            // the file or directory exists, but is not expected
+    NotAFile(String),              // a file is expected, but there is a directory
+    NotADir(String),               // a directory is expected, but there is a file
+    Size(String, usize, u64), // the file does not have the expected size: path, expected size, size
+    Failed(usize, String, String), // the operation failed in the replay: pid, operation, error
+}
+
+impl std::fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Mismatch::Missing(path) => write!(f, "{}: missing", path),
+            Mismatch::Unexpected(path) => write!(f, "{}: unexpected", path),
+            Mismatch::NotAFile(path) => write!(f, "{}: expected a file, found a directory", path),
+            Mismatch::NotADir(path) => write!(f, "{}: expected a directory, found a file", path),
+            Mismatch::Size(path, expected, size) => write!(
+                f,
+                "{}: expected {} bytes, found {} bytes",
+                path, expected, size
+            ),
+            Mismatch::Failed(pid, op, error) => {
+                write!(f, "process {}: {} failed: {}", pid, op, error)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::file::File;
+    use crate::op::Operation;
+    use crate::parser::FileType;
+    use crate::replay::{Replay, ReplayReport, ReplayedOp};
+    use crate::snapshot::Snapshot;
+    use crate::verify::{ExpectedState, Mismatch};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    #[test]
+    fn expected_state() {
+        let mut expected = ExpectedState::new(&Snapshot::new(vec![
+            FileType::File("/data/input".to_string(), 10),
+            FileType::File("/data/old".to_string(), 3),
+        ]));
+
+        let output = Arc::new(File::new("/data/out/file"));
+        let ops = [
+            Operation::mkdir(Arc::new(File::new("/data/out")), "0755".to_string()),
+            Operation::mknod(output.clone()),
+            Operation::write(output.clone(), String::new(), 4, 0),
+            Operation::write(output.clone(), String::new(), 4, 2),
+            Operation::rename(Arc::new(File::new("/data/out")), "/data/new".to_string()),
+            Operation::copy(
+                Arc::new(File::new("/data/input")),
+                6,
+                Arc::new(File::new("/data/copy")),
+                0,
+                100,
+            ),
+            Operation::remove(Arc::new(File::new("/data/old"))),
+        ];
+        for op in ops.iter() {
+            expected.apply(op);
+        }
+
+        assert_eq!(
+            expected.entries(),
+            vec![
+                FileType::Dir("/data".to_string(), 0),
+                FileType::File("/data/copy".to_string(), 4),
+                FileType::File("/data/input".to_string(), 10),
+                FileType::Dir("/data/new".to_string(), 0),
+                FileType::File("/data/new/file".to_string(), 6),
+            ]
+        );
+    }
+
+    #[test]
+    fn replayed() {
+        let mut expected = ExpectedState::new(&Snapshot::new(vec![]));
+
+        let created = Operation::mknod(Arc::new(File::new("/data/created")));
+        let failed = Operation::mknod(Arc::new(File::new("/data/missing/file")));
+        let report = ReplayReport::new(
+            vec![
+                ReplayedOp::new(1, created, Duration::ZERO, None),
+                ReplayedOp::new(
+                    1,
+                    failed.clone(),
+                    Duration::ZERO,
+                    Some("No such file or directory".to_string()),
+                ),
+            ],
+            Duration::ZERO,
+        );
+
+        // the failed operation is a mismatch, and does not change the expected state
+        assert_eq!(
+            expected.apply_replayed(&report),
+            vec![Mismatch::Failed(
+                1,
+                failed.to_string(),
+                "No such file or directory".to_string()
+            )]
+        );
+        assert_eq!(
+            expected.entries(),
+            vec![FileType::File("/data/created".to_string(), 0)]
+        );
+    }
+
+    #[test]
+    fn verify() -> Result<(), Box<dyn std::error::Error>> {
+        let root =
+            std::env::temp_dir().join(format!("strace-parser-verify-{}", std::process::id()));
+        let replay = Replay::new(&root);
+
+        let expected = ExpectedState::new(&Snapshot::new(vec![
+            FileType::File("/data/same".to_string(), 3),
+            FileType::File("/data/resized".to_string(), 3),
+            FileType::File("/data/missing".to_string(), 3),
+            FileType::File("/data/dir".to_string(), 0),
+        ]));
+
+        std::fs::create_dir_all(root.join("data/dir"))?;
+        std::fs::write(root.join("data/same"), b"abc")?;
+        std::fs::write(root.join("data/resized"), b"a")?;
+        std::fs::write(root.join("data/extra"), b"a")?;
+
+        let mismatches = expected.verify(&replay);
+        std::fs::remove_dir_all(&root)?;
+
+        assert_eq!(
+            mismatches,
+            vec![
+                Mismatch::Unexpected("/data/extra".to_string()),
+                Mismatch::NotAFile("/data/dir".to_string()),
+                Mismatch::Missing("/data/missing".to_string()),
+                Mismatch::Size("/data/resized".to_string(), 3, 1),
+            ]
+        );
+
+        Ok(())
+    }
+}
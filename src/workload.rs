@@ -0,0 +1,508 @@
+use crate::deps::DependencyGraph;
+use crate::error::Error;
+use crate::op::OperationType;
+use crate::process::Process;
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+///
+/// The reads or the writes of a process on a file, or on all of its files
+///
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct IoSummary {
+    count: usize,                  // the number of operations
+    bytes: usize,                  // the number of bytes read or written
+    sizes: BTreeMap<usize, usize>, // the number of operations of each size
+    sequential: usize,             // the operations starting where the previous one ended
+}
+
+impl IoSummary {
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    pub fn bytes(&self) -> usize {
+        self.bytes
+    }
+
+    pub fn sizes(&self) -> &BTreeMap<usize, usize> {
+        &self.sizes
+    }
+
+    pub fn sequential(&self) -> usize {
+        self.sequential
+    }
+
+    pub fn random(&self) -> usize {
+        self.count - self.sequential
+    }
+
+    pub fn average_size(&self) -> usize {
+        if self.count == 0 {
+            return 0;
+        }
+        self.bytes / self.count
+    }
+
+    fn add(&mut self, size: usize, sequential: bool) {
+        self.count += 1;
+        self.bytes += size;
+        *self.sizes.entry(size).or_insert(0) += 1;
+        if sequential {
+            self.sequential += 1;
+        }
+    }
+
+    fn merge(&mut self, other: &IoSummary) {
+        self.count += other.count;
+        self.bytes += other.bytes;
+        for (size, count) in other.sizes.iter() {
+            *self.sizes.entry(*size).or_insert(0) += count;
+        }
+        self.sequential += other.sequential;
+    }
+}
+
+///
+/// The reads and writes of a process on a file, and the extent of the file they cover
+///
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FileProfile {
+    extent: usize, // the end of the furthest read or write
+    reads: IoSummary,
+    writes: IoSummary,
+    read_end: Option<usize>,  // where the previous read ended
+    write_end: Option<usize>, // where the previous write ended
+}
+
+impl FileProfile {
+    pub fn extent(&self) -> usize {
+        self.extent
+    }
+
+    pub fn reads(&self) -> &IoSummary {
+        &self.reads
+    }
+
+    pub fn writes(&self) -> &IoSummary {
+        &self.writes
+    }
+}
+
+///
+/// The read and write operations of a process on regular files, summarised per file
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProcessProfile {
+    pid: usize,
+    files: BTreeMap<String, FileProfile>,
+}
+
+impl ProcessProfile {
+    pub fn new(process: &Process) -> Self {
+        let mut files: BTreeMap<String, FileProfile> = BTreeMap::new();
+
+        for op in process.ops() {
+            let (file, offset, len, write) = match op.op_type() {
+                OperationType::Read(file, offset, len) => (file, offset, len, false),
+                OperationType::Write(file, offset, len, _) => (file, offset, len, true),
+                _ => continue,
+            };
+            let path = match file.path() {
+                Ok(path) if file.is_regular() => path,
+                _ => continue,
+            };
+
+            let offset = (*offset).max(0) as usize;
+            let profile = files.entry(path.to_string()).or_default();
+            profile.extent = profile.extent.max(offset + len);
+
+            // the first access is sequential if it starts at the beginning of the file
+            let (summary, end) = match write {
+                false => (&mut profile.reads, &mut profile.read_end),
+                true => (&mut profile.writes, &mut profile.write_end),
+            };
+            summary.add(*len, end.unwrap_or(0) == offset);
+            *end = Some(offset + len);
+        }
+
+        ProcessProfile {
+            pid: process.pid(),
+            files,
+        }
+    }
+
+    pub fn pid(&self) -> usize {
+        self.pid
+    }
+
+    pub fn files(&self) -> &BTreeMap<String, FileProfile> {
+        &self.files
+    }
+
+    pub fn reads(&self) -> IoSummary {
+        let mut reads = IoSummary::default();
+        for profile in self.files.values() {
+            reads.merge(&profile.reads);
+        }
+        reads
+    }
+
+    pub fn writes(&self) -> IoSummary {
+        let mut writes = IoSummary::default();
+        for profile in self.files.values() {
+            writes.merge(&profile.writes);
+        }
+        writes
+    }
+}
+
+///
+/// The synthetic benchmarks the parsed operations can be exported as a workload of
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Workload {
+    Fio,       // a fio job file
+    Filebench, // a filebench workload model
+}
+
+impl Workload {
+    ///
+    /// Generate a workload with the reads and writes of each process, on files created in the
+    /// input directory. The files are named after their order of appearance, and the traced paths
+    /// are listed in the comments.
+    ///
+    pub fn export(
+        &self,
+        dep_graph: &mut DependencyGraph,
+        directory: &str,
+    ) -> Result<String, Box<dyn std::error::Error>> {
+        let mut waves = vec![];
+        let mut available_set = dep_graph.available_set()?;
+        while !available_set.is_empty() {
+            waves.push(
+                available_set
+                    .iter()
+                    .map(ProcessProfile::new)
+                    .filter(|profile| profile.reads().count() + profile.writes().count() > 0)
+                    .collect::<Vec<_>>(),
+            );
+            available_set = dep_graph.available_set()?;
+        }
+
+        // the name of each file in the workload
+        let mut names: BTreeMap<String, String> = BTreeMap::new();
+        for profile in waves.iter().flatten() {
+            for path in profile.files().keys() {
+                if !names.contains_key(path) {
+                    names.insert(path.clone(), format!("file_{}", names.len() + 1));
+                }
+            }
+        }
+
+        match self {
+            Workload::Fio => Ok(fio(&waves, &names, directory)),
+            Workload::Filebench => Ok(filebench(&waves, &names, directory)),
+        }
+    }
+}
+
+impl FromStr for Workload {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "fio" => Ok(Workload::Fio),
+            "filebench" => Ok(Workload::Filebench),
+            _ => Err(Error::ParseError(format!(
+                "workload {}, expected fio or filebench",
+                s
+            ))),
+        }
+    }
+}
+
+// a fio job per process. The jobs of each available set run in parallel, and the first job of
+// the next set waits for them with stonewall.
+fn fio(waves: &[Vec<ProcessProfile>], names: &BTreeMap<String, String>, directory: &str) -> String {
+    let mut jobs = String::from("; fio job file generated by strace-parser\n");
+    for (path, name) in sorted(names) {
+        jobs.push_str(&format!("; {}: {}\n", name, path));
+    }
+    jobs.push_str(&format!(
+        "\n[global]\ndirectory={}\nioengine=psync\n",
+        directory
+    ));
+
+    for (index, wave) in waves.iter().enumerate() {
+        for (position, profile) in wave.iter().enumerate() {
+            let reads = profile.reads();
+            let writes = profile.writes();
+            let count = reads.count() + writes.count();
+
+            let rw = match (reads.count() > 0, writes.count() > 0) {
+                (true, false) => "read",
+                (false, true) => "write",
+                _ => "rw",
+            };
+            let random = reads.random() + writes.random();
+
+            jobs.push_str(&format!("\n[process_{}]\n", profile.pid()));
+            if index > 0 && position == 0 {
+                jobs.push_str("stonewall\n");
+            }
+            jobs.push_str(&format!(
+                "filename={}\n",
+                profile
+                    .files()
+                    .keys()
+                    .map(|path| names[path].as_str())
+                    .collect::<Vec<_>>()
+                    .join(":")
+            ));
+            jobs.push_str(&format!(
+                "size={}\n",
+                profile
+                    .files()
+                    .values()
+                    .map(|file| file.extent())
+                    .sum::<usize>()
+                    .max(1)
+            ));
+            jobs.push_str(&format!(
+                "io_size={}\n",
+                (reads.bytes() + writes.bytes()).max(1)
+            ));
+            if random > 0 {
+                jobs.push_str(&format!("rw=rand{}\n", rw));
+                jobs.push_str(&format!(
+                    "percentage_random={}\n",
+                    percentage(random, count)
+                ));
+            } else {
+                jobs.push_str(&format!("rw={}\n", rw));
+            }
+            if rw == "rw" {
+                jobs.push_str(&format!("rwmixread={}\n", percentage(reads.count(), count)));
+            }
+            jobs.push_str(&format!(
+                "bssplit={},{}\n",
+                bssplit(&reads),
+                bssplit(&writes)
+            ));
+        }
+    }
+
+    jobs
+}
+
+// a filebench process per traced process, with a flowop for the reads and one for the writes
+// of each file
+fn filebench(
+    waves: &[Vec<ProcessProfile>],
+    names: &BTreeMap<String, String>,
+    directory: &str,
+) -> String {
+    let mut model = String::from("# filebench workload generated by strace-parser\n");
+    for (path, name) in sorted(names) {
+        model.push_str(&format!("# {}: {}\n", name, path));
+    }
+    model.push_str(&format!("\nset $dir={}\n\n", directory));
+
+    // the files are as large as the furthest access of all the processes
+    let mut extents: BTreeMap<&str, usize> = BTreeMap::new();
+    for profile in waves.iter().flatten() {
+        for (path, file) in profile.files() {
+            let extent = extents.entry(names[path].as_str()).or_insert(0);
+            *extent = (*extent).max(file.extent());
+        }
+    }
+    for (_, name) in sorted(names) {
+        model.push_str(&format!(
+            "define file name={},path=$dir,size={},prealloc,reuse\n",
+            name,
+            extents.get(name.as_str()).cloned().unwrap_or(0).max(1)
+        ));
+    }
+
+    for profile in waves.iter().flatten() {
+        let memsize = profile
+            .files()
+            .values()
+            .flat_map(|file| {
+                file.reads()
+                    .sizes()
+                    .keys()
+                    .chain(file.writes().sizes().keys())
+            })
+            .max()
+            .cloned()
+            .unwrap_or(0)
+            .max(1);
+
+        model.push_str(&format!(
+            "\ndefine process name=process_{0},instances=1\n{{\n  \
+             thread name=process_{0}_thread,memsize={1},instances=1\n  {{\n",
+            profile.pid(),
+            memsize
+        ));
+        for (path, file) in profile.files() {
+            for (flowop, summary) in [("read", file.reads()), ("write", file.writes())] {
+                if summary.count() == 0 {
+                    continue;
+                }
+                model.push_str(&format!(
+                    "    flowop {0} name=process_{1}_{0}_{2},filename={2},iosize={3},iters={4}{5}\n",
+                    flowop,
+                    profile.pid(),
+                    names[path],
+                    summary.average_size().max(1),
+                    summary.count(),
+                    if summary.random() > summary.sequential() {
+                        ",random"
+                    } else {
+                        ""
+                    }
+                ));
+            }
+        }
+        model.push_str("  }\n}\n");
+    }
+
+    model.push_str("\nrun 60\n");
+    model
+}
+
+// the files in the order of their names
+fn sorted(names: &BTreeMap<String, String>) -> Vec<(&String, &String)> {
+    let mut sorted: Vec<(&String, &String)> = names.iter().collect();
+    sorted.sort_by_key(|(_, name)| {
+        name.trim_start_matches("file_")
+            .parse::<usize>()
+            .unwrap_or(0)
+    });
+    sorted
+}
+
+fn percentage(part: usize, total: usize) -> usize {
+    if total == 0 {
+        return 0;
+    }
+    (part * 100 + total / 2) / total
+}
+
+// the share of each block size, with the sizes rounded up to the powers of two, as
+// size/percentage entries
+fn bssplit(summary: &IoSummary) -> String {
+    let mut blocks: BTreeMap<usize, usize> = BTreeMap::new();
+    for (size, count) in summary.sizes() {
+        *blocks
+            .entry((*size).max(1).next_power_of_two())
+            .or_insert(0) += count;
+    }
+    if blocks.is_empty() {
+        return "4096/100".to_string();
+    }
+
+    // the last entry takes what is left of the rounding
+    let mut left = 100;
+    let last = blocks.len() - 1;
+    blocks
+        .iter()
+        .enumerate()
+        .map(|(index, (size, count))| {
+            let share = if index == last {
+                left
+            } else {
+                percentage(*count, summary.count()).min(left)
+            };
+            left -= share;
+            format!("{}/{}", size, share)
+        })
+        .collect::<Vec<_>>()
+        .join(":")
+}
+
+#[cfg(test)]
+mod test {
+    use crate::deps::DependencyGraph;
+    use crate::file::File;
+    use crate::op::Operation;
+    use crate::process::Process;
+    use crate::workload::{ProcessProfile, Workload};
+    use std::sync::Arc;
+
+    fn processes() -> Vec<Process> {
+        let input = Arc::new(File::new("/data/input"));
+        let output = Arc::new(File::new("/data/output"));
+
+        let mut reader = Process::new(1);
+        reader.add_op(Operation::read(input.clone(), 4096, 0));
+        reader.add_op(Operation::read(input.clone(), 4096, 4096));
+        reader.add_op(Operation::read(input.clone(), 1000, 20000));
+        reader.add_op(Operation::write(output.clone(), String::new(), 100, 0));
+
+        let mut writer = Process::new(2);
+        writer.add_op(Operation::read(output.clone(), 100, 0));
+
+        vec![reader, writer]
+    }
+
+    #[test]
+    fn profile() {
+        let profile = ProcessProfile::new(&processes()[0]);
+
+        let reads = profile.reads();
+        assert_eq!(reads.count(), 3);
+        assert_eq!(reads.bytes(), 9192);
+        assert_eq!(reads.sequential(), 2);
+        assert_eq!(reads.random(), 1);
+        assert_eq!(reads.sizes().get(&4096), Some(&2));
+        assert_eq!(profile.writes().count(), 1);
+        assert_eq!(profile.files()["/data/input"].extent(), 21000);
+        assert_eq!(profile.files()["/data/output"].extent(), 100);
+    }
+
+    #[test]
+    fn fio() -> Result<(), Box<dyn std::error::Error>> {
+        let mut dep_graph = DependencyGraph::new(processes())?.order()?;
+        let jobs = Workload::Fio.export(&mut dep_graph, "/tmp/fio")?;
+
+        assert!(jobs.contains("; file_1: /data/input\n; file_2: /data/output\n"));
+        assert!(jobs.contains("[global]\ndirectory=/tmp/fio\n"));
+        assert!(jobs.contains(
+            "[process_1]\nfilename=file_1:file_2\nsize=21100\nio_size=9292\nrw=randrw\n\
+             percentage_random=25\nrwmixread=75\nbssplit=1024/33:4096/67,128/100\n"
+        ));
+        assert!(jobs.contains("[process_2]\nstonewall\nfilename=file_2\n"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn filebench() -> Result<(), Box<dyn std::error::Error>> {
+        let mut dep_graph = DependencyGraph::new(processes())?.order()?;
+        let model = Workload::Filebench.export(&mut dep_graph, "/tmp/filebench")?;
+
+        assert!(model.contains("set $dir=/tmp/filebench\n"));
+        assert!(model.contains("define file name=file_1,path=$dir,size=21000,prealloc,reuse\n"));
+        assert!(model.contains(
+            "    flowop read name=process_1_read_file_1,filename=file_1,iosize=3064,iters=3\n"
+        ));
+        assert!(model.contains(
+            "    flowop write name=process_1_write_file_2,filename=file_2,iosize=100,iters=1\n"
+        ));
+        assert!(model.ends_with("run 60\n"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn parse() -> Result<(), Box<dyn std::error::Error>> {
+        assert_eq!("fio".parse::<Workload>()?, Workload::Fio);
+        assert_eq!("filebench".parse::<Workload>()?, Workload::Filebench);
+        assert!("iozone".parse::<Workload>().is_err());
+
+        Ok(())
+    }
+}